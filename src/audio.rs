@@ -0,0 +1,478 @@
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    process::{Child, ChildStdin, Command, Stdio},
+    sync::mpsc::{self, Receiver, Sender, TryRecvError},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::i18n::{self, Msg};
+
+// 程序里合成的音效和背景音乐，单声道16位
+pub const SAMPLE_RATE: u32 = 22050;
+// 每次混音的采样数，约23毫秒
+const CHUNK: usize = 512;
+// 输出比实际时间最多超前的时间，太多了声音会延迟
+const LEAD: Duration = Duration::from_millis(60);
+// 背景音乐的音量，音效叠加在上面
+const MUSIC_VOLUME: f32 = 0.5;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Sound {
+    Eat,
+    Turn,
+    Death,
+    Victory,
+    PowerUp,
+}
+
+pub const SOUNDS: [Sound; 5] = [
+    Sound::Eat,
+    Sound::Turn,
+    Sound::Death,
+    Sound::Victory,
+    Sound::PowerUp,
+];
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Wave {
+    Square,
+    Triangle,
+    Noise,
+}
+
+// 一个音：波形、开始和结束的频率（中间滑音）、时长、音量
+#[derive(PartialEq, Debug, Clone, Copy)]
+struct Tone {
+    wave: Wave,
+    from: f32,
+    to: f32,
+    ms: u32,
+    volume: f32,
+}
+
+const fn tone(wave: Wave, from: f32, to: f32, ms: u32, volume: f32) -> Tone {
+    Tone {
+        wave,
+        from,
+        to,
+        ms,
+        volume,
+    }
+}
+
+impl Sound {
+    fn tones(&self) -> Vec<Tone> {
+        use Wave::*;
+        match self {
+            Sound::Eat => vec![
+                tone(Square, 660.0, 990.0, 60, 0.3),
+                tone(Square, 990.0, 990.0, 50, 0.3),
+            ],
+            Sound::Turn => vec![tone(Triangle, 440.0, 440.0, 25, 0.2)],
+            Sound::Death => vec![
+                tone(Square, 440.0, 110.0, 400, 0.35),
+                tone(Noise, 0.0, 0.0, 200, 0.3),
+            ],
+            // 大三和弦琶音
+            Sound::Victory => [523.3, 659.3, 784.0]
+                .iter()
+                .map(|&f| tone(Square, f, f, 100, 0.3))
+                .chain([tone(Square, 1046.5, 1046.5, 300, 0.3)])
+                .collect(),
+            Sound::PowerUp => vec![tone(Square, 330.0, 1320.0, 250, 0.3)],
+        }
+    }
+
+    pub fn samples(&self) -> Vec<i16> {
+        to_pcm(&synth(&self.tones()))
+    }
+}
+
+// 依次合成几个音，每个音的音量线性衰减
+fn synth(tones: &[Tone]) -> Vec<f32> {
+    let mut samples = Vec::new();
+    let mut noise: u32 = 0x1234_5678; // 固定种子，每次合成的结果一样
+    for t in tones {
+        let count = (SAMPLE_RATE * t.ms / 1000) as usize;
+        let mut phase = 0.0f32;
+        for i in 0..count {
+            let progress = i as f32 / count as f32;
+            let freq = t.from + (t.to - t.from) * progress;
+            phase = (phase + freq / SAMPLE_RATE as f32).fract();
+            let value = match t.wave {
+                Wave::Square => {
+                    if phase < 0.5 {
+                        1.0
+                    } else {
+                        -1.0
+                    }
+                }
+                Wave::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+                Wave::Noise => {
+                    noise ^= noise << 13;
+                    noise ^= noise >> 17;
+                    noise ^= noise << 5;
+                    noise as f32 / u32::MAX as f32 * 2.0 - 1.0
+                }
+            };
+            samples.push(value * t.volume * (1.0 - progress));
+        }
+    }
+    samples
+}
+
+fn to_pcm(samples: &[f32]) -> Vec<i16> {
+    samples
+        .iter()
+        .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+        .collect()
+}
+
+// MIDI 音高转换成频率
+fn note_freq(note: u8) -> f32 {
+    440.0 * 2f32.powf((note as f32 - 69.0) / 12.0)
+}
+
+// 循环播放的背景音乐：方波旋律加三角波低音，每拍150毫秒，0为休止
+pub fn music() -> Vec<i16> {
+    const MELODY: [u8; 32] = [
+        72, 0, 76, 79, 76, 0, 72, 0, 74, 0, 77, 81, 77, 0, 74, 0, //
+        72, 0, 76, 79, 84, 0, 79, 76, 74, 0, 71, 74, 72, 0, 0, 0,
+    ];
+    const BASS: [u8; 8] = [48, 48, 50, 50, 48, 48, 43, 48];
+    const BEAT_MS: u32 = 150;
+
+    let melody: Vec<Tone> = MELODY
+        .iter()
+        .map(|&n| match n {
+            0 => tone(Wave::Square, 0.0, 0.0, BEAT_MS, 0.0),
+            n => tone(Wave::Square, note_freq(n), note_freq(n), BEAT_MS, 0.15),
+        })
+        .collect();
+    let bass: Vec<Tone> = BASS
+        .iter()
+        .map(|&n| {
+            tone(
+                Wave::Triangle,
+                note_freq(n),
+                note_freq(n),
+                BEAT_MS * 4,
+                0.25,
+            )
+        })
+        .collect();
+    let (melody, bass) = (synth(&melody), synth(&bass));
+    let mixed: Vec<f32> = melody.iter().zip(bass.iter()).map(|(m, b)| m + b).collect();
+    to_pcm(&mixed)
+}
+
+// 把背景音乐和正在播放的音效混在一起
+pub struct Mixer {
+    effects: Vec<Vec<i16>>, // 按 SOUNDS 的顺序
+    music: Vec<i16>,
+    music_pos: usize,
+    music_on: bool,
+    voices: Vec<(usize, usize)>, // 正在播放的音效和播放到的位置
+}
+
+impl Mixer {
+    pub fn new() -> Mixer {
+        Mixer {
+            effects: SOUNDS.iter().map(|s| s.samples()).collect(),
+            music: music(),
+            music_pos: 0,
+            music_on: false,
+            voices: vec![],
+        }
+    }
+
+    pub fn play(&mut self, sound: Sound) {
+        let idx = SOUNDS.iter().position(|s| *s == sound).unwrap();
+        self.voices.push((idx, 0));
+    }
+
+    // 关掉再打开时从头播放
+    pub fn set_music(&mut self, on: bool) {
+        if on && !self.music_on {
+            self.music_pos = 0;
+        }
+        self.music_on = on;
+    }
+
+    pub fn mix(&mut self, out: &mut [i16]) {
+        for sample in out.iter_mut() {
+            let mut sum = 0.0f32;
+            if self.music_on && !self.music.is_empty() {
+                sum += self.music[self.music_pos] as f32 * MUSIC_VOLUME;
+                self.music_pos = (self.music_pos + 1) % self.music.len();
+            }
+            for (idx, pos) in self.voices.iter_mut() {
+                if let Some(s) = self.effects[*idx].get(*pos) {
+                    sum += *s as f32;
+                }
+                *pos += 1;
+            }
+            *sample = sum.clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        }
+        let effects = &self.effects;
+        self.voices.retain(|(idx, pos)| *pos < effects[*idx].len());
+    }
+}
+
+// 声音的输出
+pub trait Output: Send {
+    fn write(&mut self, samples: &[i16]) -> Result<(), String>;
+    // 停止时调用
+    fn finish(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// 不输出，没有声卡或者静音时使用
+pub struct NullOutput;
+
+impl Output for NullOutput {
+    fn write(&mut self, _samples: &[i16]) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+// 写到 wav 文件，停止时保存
+pub struct WavOutput {
+    path: PathBuf,
+    samples: Vec<i16>,
+}
+
+impl WavOutput {
+    pub fn new(path: PathBuf) -> WavOutput {
+        WavOutput {
+            path,
+            samples: vec![],
+        }
+    }
+}
+
+impl Output for WavOutput {
+    fn write(&mut self, samples: &[i16]) -> Result<(), String> {
+        self.samples.extend_from_slice(samples);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        fs::write(&self.path, wav_bytes(&self.samples))
+            .map_err(|e| format!("{} {}", self.path.display(), e))
+    }
+}
+
+// 交给 aplay 播放（alsa-utils）
+pub struct AplayOutput {
+    child: Child,
+    stdin: ChildStdin,
+}
+
+impl AplayOutput {
+    pub fn new() -> Result<AplayOutput, String> {
+        let rate = SAMPLE_RATE.to_string();
+        let mut child = Command::new("aplay")
+            .args(["-q", "-t", "raw", "-f", "S16_LE", "-c", "1", "-r", &rate])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("aplay {}", e))?;
+        let stdin = child.stdin.take().ok_or(i18n::tr(Msg::AplayNoInput))?;
+        Ok(AplayOutput { child, stdin })
+    }
+}
+
+impl Output for AplayOutput {
+    fn write(&mut self, samples: &[i16]) -> Result<(), String> {
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        self.stdin.write_all(&bytes).map_err(|e| e.to_string())
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        Ok(())
+    }
+}
+
+// 16位单声道的 wav 文件
+pub fn wav_bytes(samples: &[i16]) -> Vec<u8> {
+    let data_len = samples.len() as u32 * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // 声道数
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+    for s in samples {
+        bytes.extend_from_slice(&s.to_le_bytes());
+    }
+    bytes
+}
+
+enum AudioCommand {
+    Play(Sound),
+    Music(bool),
+}
+
+// 在后台线程里混音和输出，界面线程只发送命令
+pub struct Audio {
+    sender: Option<Sender<AudioCommand>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Audio {
+    pub fn muted() -> Audio {
+        Audio {
+            sender: None,
+            thread: None,
+        }
+    }
+
+    pub fn start(output: Box<dyn Output>) -> Audio {
+        let (sender, receiver) = mpsc::channel();
+        let thread = thread::spawn(move || run_output(output, receiver));
+        Audio {
+            sender: Some(sender),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn play(&self, sound: Sound) {
+        self.send(AudioCommand::Play(sound));
+    }
+
+    pub fn set_music(&self, on: bool) {
+        self.send(AudioCommand::Music(on));
+    }
+
+    fn send(&self, command: AudioCommand) {
+        if let Some(sender) = &self.sender {
+            // 输出线程出错退出后不再发送
+            let _ = sender.send(command);
+        }
+    }
+}
+
+// 关闭时等输出线程保存完
+impl Drop for Audio {
+    fn drop(&mut self) {
+        self.sender = None;
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+// 按实际时间的速度输出，没有声音时也输出静音，保持时间连续
+fn run_output(mut output: Box<dyn Output>, receiver: Receiver<AudioCommand>) {
+    let mut mixer = Mixer::new();
+    let mut chunk = [0i16; CHUNK];
+    let start = Instant::now();
+    let mut written: u64 = 0;
+    loop {
+        loop {
+            match receiver.try_recv() {
+                Ok(AudioCommand::Play(sound)) => mixer.play(sound),
+                Ok(AudioCommand::Music(on)) => mixer.set_music(on),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    if let Err(e) = output.finish() {
+                        println!("{}", i18n::trf(Msg::SaveSoundFailed, &[&e]));
+                    }
+                    return;
+                }
+            }
+        }
+        mixer.mix(&mut chunk);
+        if let Err(e) = output.write(&chunk) {
+            println!("{}", i18n::trf(Msg::PlaySoundFailed, &[&e]));
+            return;
+        }
+        written += CHUNK as u64;
+        let played = Duration::from_secs_f64(written as f64 / SAMPLE_RATE as f64);
+        if let Some(ahead) = played.checked_sub(start.elapsed() + LEAD) {
+            thread::sleep(ahead);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_synth() {
+        for sound in SOUNDS {
+            let samples = sound.samples();
+            assert!(!samples.is_empty(), "{:?}", sound);
+            assert!(samples.iter().any(|s| *s != 0), "{:?}", sound);
+            // 合成结果固定
+            assert_eq!(samples, sound.samples());
+        }
+        // 4个音，每个150毫秒，32拍
+        assert_eq!(music().len(), (SAMPLE_RATE * 150 / 1000) as usize * 32);
+        assert!((note_freq(69) - 440.0).abs() < 0.01);
+        assert!((note_freq(81) - 880.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_mixer() {
+        let mut mixer = Mixer::new();
+        let mut out = vec![1i16; 100];
+        mixer.mix(&mut out);
+        assert!(out.iter().all(|s| *s == 0));
+
+        // 音效播完后自动去掉，两个一样的音效叠加
+        let turn = Sound::Turn.samples();
+        mixer.play(Sound::Turn);
+        mixer.play(Sound::Turn);
+        let mut out = vec![0i16; turn.len()];
+        mixer.mix(&mut out);
+        assert_eq!(out[10], turn[10] * 2);
+        assert!(mixer.voices.is_empty());
+
+        mixer.set_music(true);
+        mixer.mix(&mut out);
+        assert!(out.iter().any(|s| *s != 0));
+    }
+
+    #[test]
+    fn test_wav() {
+        let path = std::env::temp_dir().join("rust_snake_test.wav");
+        let mut output = WavOutput::new(path.clone());
+        let mut mixer = Mixer::new();
+        mixer.play(Sound::Eat);
+        let mut chunk = [0i16; CHUNK];
+        mixer.mix(&mut chunk);
+        output.write(&chunk).unwrap();
+        output.finish().unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        assert_eq!(&bytes[0..4], b"RIFF");
+        assert_eq!(&bytes[8..16], b"WAVEfmt ");
+        assert_eq!(bytes.len(), 44 + CHUNK * 2);
+        assert_eq!(
+            u32::from_le_bytes(bytes[40..44].try_into().unwrap()),
+            CHUNK as u32 * 2
+        );
+        assert_eq!(
+            i16::from_le_bytes([bytes[44 + 20], bytes[45 + 20]]),
+            chunk[10]
+        );
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{consts, snake::Point};
+
+// 逻辑棋盘，游戏逻辑都在逻辑坐标下进行，和窗口大小无关
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Board {
+    width: i32,
+    height: i32,
+}
+
+impl Board {
+    pub fn new(width: i32, height: i32) -> Board {
+        Board { width, height }
+    }
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    // 横向格子数
+    pub fn cols(&self) -> i32 {
+        (self.width - consts::BODY_SIZE) / consts::BODY_SIZE + 1
+    }
+
+    // 纵向格子数
+    pub fn rows(&self) -> i32 {
+        (self.height - consts::BODY_SIZE) / consts::BODY_SIZE + 1
+    }
+
+    // 没被占用的格子，逐列从左上角开始，包括最后一行和最后一列
+    pub fn free_cells(&self, is_taken: impl Fn(&Point) -> bool) -> Vec<Point> {
+        (0..self.cols())
+            .flat_map(|col| {
+                (0..self.rows())
+                    .map(move |row| Point::new(col * consts::BODY_SIZE, row * consts::BODY_SIZE))
+            })
+            .filter(|point| !is_taken(point))
+            .collect()
+    }
+
+    // 格子左上角是否在棋盘内
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= 0
+            && x <= self.width - consts::BODY_SIZE
+            && y >= 0
+            && y <= self.height - consts::BODY_SIZE
+    }
+}
+
+// 逻辑坐标到窗口坐标的变换
+// 保持宽高比缩放，多出来的部分留黑边
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Viewport {
+    scale: f64,
+    offset_x: i32,
+    offset_y: i32,
+}
+
+impl Viewport {
+    // 把棋盘放进w*h的窗口，居中
+    pub fn fit(board: &Board, w: i32, h: i32) -> Viewport {
+        let scale = (w as f64 / board.width() as f64).min(h as f64 / board.height() as f64);
+        let scale = if scale > 0.0 { scale } else { 1.0 };
+        Viewport {
+            scale,
+            offset_x: (w - (board.width() as f64 * scale).round() as i32) / 2,
+            offset_y: (h - (board.height() as f64 * scale).round() as i32) / 2,
+        }
+    }
+
+    // 逻辑坐标原点移动到(dx, dy)，用于在棋盘旁边留出状态栏
+    pub fn shifted(self, dx: i32, dy: i32) -> Viewport {
+        Viewport {
+            offset_x: self.x(dx),
+            offset_y: self.y(dy),
+            ..self
+        }
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn x(&self, x: i32) -> i32 {
+        self.offset_x + (x as f64 * self.scale).round() as i32
+    }
+
+    pub fn y(&self, y: i32) -> i32 {
+        self.offset_y + (y as f64 * self.scale).round() as i32
+    }
+
+    // 长度缩放，至少1像素
+    pub fn len(&self, len: i32) -> i32 {
+        ((len as f64 * self.scale).round() as i32).max(1)
+    }
+
+    // 矩形缩放，用两个角计算，相邻的格子之间不会有缝
+    pub fn rect(&self, x: i32, y: i32, w: i32, h: i32) -> (i32, i32, i32, i32) {
+        let (x1, y1) = (self.x(x), self.y(y));
+        let (x2, y2) = (self.x(x + w), self.y(y + h));
+        (x1, y1, (x2 - x1).max(1), (y2 - y1).max(1))
+    }
+
+    // 窗口坐标转回逻辑坐标，鼠标事件使用
+    pub fn logical_point(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            ((x - self.offset_x) as f64 / self.scale).floor() as i32,
+            ((y - self.offset_y) as f64 / self.scale).floor() as i32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit() {
+        let board = Board::new(500, 500);
+
+        // 宽屏，左右留黑边
+        let vp = Viewport::fit(&board, 1000, 500);
+        assert_eq!(vp.len(consts::BODY_SIZE), consts::BODY_SIZE);
+        assert_eq!(vp.x(0), 250);
+        assert_eq!(vp.y(0), 0);
+
+        // 放大两倍
+        let vp = Viewport::fit(&board, 1000, 1200);
+        assert_eq!(vp.len(consts::BODY_SIZE), 2 * consts::BODY_SIZE);
+        assert_eq!(vp.y(0), 100);
+        assert_eq!(vp.logical_point(vp.x(30), vp.y(60)), (30, 60));
+    }
+
+    #[test]
+    fn test_cells() {
+        let board = Board::new(500, 500);
+        assert_eq!(board.cols(), 16);
+        assert!(board.contains(450, 450));
+        assert!(!board.contains(480, 0));
+        assert!(!board.contains(-30, 0));
+
+        // 只剩右下角一格时食物放在那里，全占满时没有空格
+        let board = Board::new(90, 90);
+        let last = Point::new(60, 60);
+        assert_eq!(board.free_cells(|p| *p != last), vec![last]);
+        assert!(board.free_cells(|_| true).is_empty());
+        assert_eq!(board.free_cells(|_| false).len(), 9);
+    }
+}
@@ -0,0 +1,554 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    board::Board,
+    config::Config,
+    consts,
+    difficulty::{Difficulty, SpeedCurve, SpeedSetting},
+    i18n::{self, Msg},
+    snake::{Direction, Point},
+};
+
+// 过关目标
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Goal {
+    Length(i32), // 长到这么多节
+    Score(i32),  // 这一关得到这么多分
+}
+
+impl Goal {
+    // 关卡文件里的写法：length 10 或者 score 20
+    pub fn parse(text: &str) -> Option<Goal> {
+        let (kind, target) = text.trim().split_once(' ')?;
+        let target = target.trim().parse().ok()?;
+        match kind {
+            "length" => Some(Goal::Length(target)),
+            "score" => Some(Goal::Score(target)),
+            _ => None,
+        }
+    }
+
+    pub fn to_text(self) -> String {
+        match self {
+            Goal::Length(target) => format!("length {}", target),
+            Goal::Score(target) => format!("score {}", target),
+        }
+    }
+
+    // 当前进度，length 为蛇的长度，score 为这一关的得分
+    pub fn progress(&self, length: i32, score: i32) -> i32 {
+        match self {
+            Goal::Length(_) => length,
+            Goal::Score(_) => score,
+        }
+    }
+
+    pub fn is_reached(&self, length: i32, score: i32) -> bool {
+        match *self {
+            Goal::Length(target) => length >= target,
+            Goal::Score(target) => score >= target,
+        }
+    }
+
+    pub fn label(&self, progress: i32) -> String {
+        match *self {
+            Goal::Length(target) => i18n::trf(Msg::GoalLength, &[&progress, &target]),
+            Goal::Score(target) => i18n::trf(Msg::GoalScore, &[&progress, &target]),
+        }
+    }
+}
+
+// 关卡的特殊规则
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Rules {
+    pub wrap: bool,        // 撞到边界从对面出来
+    pub power_ups: bool,   // 是否出现特殊食物和道具
+    pub food_count: usize, // 场上同时存在的食物数
+    pub prey: bool,        // 食物都是会逃跑的猎物
+}
+
+impl Default for Rules {
+    fn default() -> Rules {
+        Rules {
+            wrap: false,
+            power_ups: true,
+            food_count: consts::FOOD_COUNT,
+            prey: false,
+        }
+    }
+}
+
+// 闯关模式的一关
+#[derive(PartialEq, Debug, Clone)]
+pub struct Stage {
+    pub name: String,
+    pub goal: Goal,
+    pub speed: SpeedSetting,
+    pub rules: Rules,
+    pub walls: Vec<Point>,            // 障碍物占的格子
+    pub portals: Vec<(Point, Point)>, // 传送门，每对两个格子
+    pub start: Option<Point>,         // 蛇头的起点，没有时随机
+    pub direction: Option<Direction>, // 开始的方向，没有时朝空间最大的方向
+}
+
+// 内置关卡，格式和关卡文件一样，名字跟着界面语言
+const BUILTIN_STAGES: [(Msg, &str); 6] = [
+    (
+        Msg::StageWarmUp,
+        "goal = length 8
+difficulty = easy
+curve = constant
+power_ups = false
+direction = right
+---
+................
+................
+................
+................
+................
+................
+................
+...S............",
+    ),
+    (
+        Msg::StagePillars,
+        "goal = length 12
+difficulty = easy
+curve = linear
+direction = right
+---
+................
+................
+................
+...##......##...
+...##......##...
+................
+................
+..S.............
+................
+................
+................
+...##......##...
+...##......##...",
+    ),
+    (
+        Msg::StageFence,
+        "goal = score 20
+difficulty = normal
+curve = linear
+wrap = true
+direction = right
+---
+#######..#######
+#..............#
+#..............#
+#..............#
+#..............#
+#..............#
+#..............#
+...S............
+................
+#..............#
+#..............#
+#..............#
+#..............#
+#..............#
+#..............#
+#######..#######",
+    ),
+    (
+        Msg::StageCorridors,
+        "goal = length 16
+difficulty = normal
+curve = stepped
+direction = right
+---
+................
+................
+..############..
+................
+................
+................
+..############..
+..S.............
+................
+..############..
+................
+................
+................
+..############..",
+    ),
+    (
+        Msg::StageMaze,
+        "goal = score 40
+difficulty = hard
+curve = log
+foods = 2
+direction = right
+---
+................
+.######..######.
+.#............#.
+.#..########..#.
+.#............#.
+.#...#....#...#.
+.....#....#.....
+.....#....#.....
+.....#....#.....
+.....#....#.....
+.#...#....#...#.
+.#............#.
+.#..########..#.
+.#............#.
+.######..######.
+..S.............",
+    ),
+    (
+        Msg::StagePortals,
+        "goal = length 14
+difficulty = normal
+curve = linear
+direction = right
+---
+................
+................
+..a..........b..
+.......##.......
+.......##.......
+.......##.......
+.......##.......
+..S....##.......
+.......##.......
+.......##.......
+.......##.......
+.......##.......
+................
+..b..........a..
+................
+................",
+    ),
+];
+
+impl Stage {
+    // 关卡文件：前面是 key = value 的设置，--- 之后是地图
+    // 地图一个字符一格：# 障碍物，S 起点，. 或者空格为空地
+    // 小写字母为传送门，同一个字母正好两个，走进一个从另一个出来
+    pub fn parse(text: &str) -> Result<Stage, String> {
+        let (header, map) = match text.split_once("\n---") {
+            Some((header, map)) => (header, map.split_once('\n').map_or("", |(_, m)| m)),
+            None => (text, ""),
+        };
+        let config = Config::parse(header)?;
+
+        let mut stage = Stage {
+            name: String::new(),
+            goal: Goal::Length(10),
+            speed: SpeedSetting::default(),
+            rules: Rules::default(),
+            walls: vec![],
+            portals: vec![],
+            start: None,
+            direction: None,
+        };
+        let mut has_goal = false;
+        for key in config.keys() {
+            let value = config.get(key).unwrap_or_default();
+            let bool_value = || match value {
+                "true" => Ok(true),
+                "false" => Ok(false),
+                _ => Err(i18n::trf(
+                    Msg::UnknownSetting,
+                    &[&format!("{} = {}", key, value)],
+                )),
+            };
+            match key.as_str() {
+                "name" => stage.name = value.to_string(),
+                "goal" => {
+                    stage.goal =
+                        Goal::parse(value).ok_or_else(|| i18n::trf(Msg::UnknownGoal, &[&value]))?;
+                    has_goal = true;
+                }
+                "difficulty" => {
+                    stage.speed.difficulty = Difficulty::from_name(value)
+                        .ok_or_else(|| i18n::trf(Msg::UnknownSetting, &[&value]))?
+                }
+                "curve" => {
+                    stage.speed.curve = SpeedCurve::from_name(value)
+                        .ok_or_else(|| i18n::trf(Msg::UnknownSetting, &[&value]))?
+                }
+                "wrap" => stage.rules.wrap = bool_value()?,
+                "power_ups" => stage.rules.power_ups = bool_value()?,
+                "prey" => stage.rules.prey = bool_value()?,
+                "foods" => {
+                    stage.rules.food_count = value
+                        .parse()
+                        .ok()
+                        .filter(|count| *count > 0)
+                        .ok_or_else(|| i18n::trf(Msg::UnknownSetting, &[&value]))?
+                }
+                "direction" => {
+                    stage.direction = Some(
+                        Direction::from_name(value)
+                            .ok_or_else(|| i18n::trf(Msg::UnknownSetting, &[&value]))?,
+                    )
+                }
+                _ => return Err(i18n::trf(Msg::UnknownOption, &[key])),
+            }
+        }
+        if !has_goal {
+            return Err(i18n::trf(Msg::Missing, &[&"goal"]));
+        }
+
+        let mut portals: BTreeMap<char, Vec<Point>> = BTreeMap::new();
+        for (row, line) in map.lines().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                let point = Point::new(
+                    col as i32 * consts::BODY_SIZE,
+                    row as i32 * consts::BODY_SIZE,
+                );
+                match c {
+                    '#' => stage.walls.push(point),
+                    'S' => stage.start = Some(point),
+                    '.' | ' ' => {}
+                    'a'..='z' => portals.entry(c).or_default().push(point),
+                    _ => return Err(i18n::trf(Msg::UnknownCell, &[&(row + 1), &c])),
+                }
+            }
+        }
+        for (c, points) in portals {
+            match points[..] {
+                [ref a, ref b] => stage.portals.push((a.clone(), b.clone())),
+                _ => return Err(i18n::trf(Msg::UnpairedPortal, &[&c])),
+            }
+        }
+        Ok(stage)
+    }
+
+    pub fn load(path: &Path) -> Result<Stage, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let mut stage = Stage::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        // 没有写名字时用文件名
+        if stage.name.is_empty() {
+            if let Some(stem) = path.file_stem() {
+                stage.name = stem.to_string_lossy().to_string();
+            }
+        }
+        Ok(stage)
+    }
+
+    pub fn builtin() -> Vec<Stage> {
+        BUILTIN_STAGES
+            .iter()
+            .map(|(name, text)| Stage {
+                name: i18n::tr(*name).to_string(),
+                ..Stage::parse(text).unwrap()
+            })
+            .collect()
+    }
+
+    // 读取目录下所有的 .stage 文件，按文件名排在内置关卡后面，读取失败的跳过
+    pub fn load_dir(dir: &Path) -> Vec<Stage> {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "stage"))
+                .collect(),
+            Err(_) => return vec![],
+        };
+        paths.sort();
+
+        paths
+            .iter()
+            .filter_map(|path| match Stage::load(path) {
+                Ok(stage) => Some(stage),
+                Err(e) => {
+                    println!("{}", i18n::trf(Msg::LoadStageFailed, &[&e]));
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // 在棋盘里的障碍物，地图比棋盘大时多出来的部分不要
+    pub fn walls_in(&self, board: &Board) -> Vec<Point> {
+        self.walls
+            .iter()
+            .filter(|p| board.contains(p.x(), p.y()))
+            .cloned()
+            .collect()
+    }
+
+    // 两个都在棋盘里的传送门
+    pub fn portals_in(&self, board: &Board) -> Vec<(Point, Point)> {
+        self.portals
+            .iter()
+            .filter(|(a, b)| board.contains(a.x(), a.y()) && board.contains(b.x(), b.y()))
+            .cloned()
+            .collect()
+    }
+}
+
+// 状态栏显示的关卡进度，录像里也记下来
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct StageStatus {
+    pub stage: usize, // 第几关，从0开始
+    pub goal: Goal,
+    pub progress: i32,
+}
+
+impl StageStatus {
+    // 关卡,目标,进度，比如 2,length 12,5
+    pub fn to_text(self) -> String {
+        format!("{},{},{}", self.stage, self.goal.to_text(), self.progress)
+    }
+
+    pub fn from_text(text: &str) -> Option<StageStatus> {
+        let mut fields = text.split(',');
+        Some(StageStatus {
+            stage: fields.next()?.parse().ok()?,
+            goal: Goal::parse(fields.next()?)?,
+            progress: fields.next()?.parse().ok()?,
+        })
+    }
+
+    pub fn label(&self) -> String {
+        i18n::trf(
+            Msg::HudStage,
+            &[&(self.stage + 1), &self.goal.label(self.progress)],
+        )
+    }
+}
+
+// 闯关进度，保存在文件中
+pub struct Progress {
+    path: PathBuf,
+    unlocked: usize, // 解锁的关数，第一关一直可以玩
+}
+
+impl Progress {
+    // 读取进度文件，文件不存在或者格式错误时只解锁第一关
+    pub fn load(path: PathBuf) -> Progress {
+        let unlocked = Config::load(&path)
+            .ok()
+            .and_then(|config| config.get("unlocked").and_then(|v| v.parse().ok()))
+            .unwrap_or(1);
+        Progress {
+            path,
+            unlocked: unlocked.max(1),
+        }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let mut config = Config::new();
+        config.set("unlocked", &self.unlocked.to_string());
+        config.save(&self.path)
+    }
+
+    pub fn unlocked(&self) -> usize {
+        self.unlocked
+    }
+
+    // 通过第idx关，解锁下一关，返回是否新解锁了关卡
+    pub fn clear(&mut self, idx: usize) -> bool {
+        if idx + 2 <= self.unlocked {
+            return false;
+        }
+        self.unlocked = idx + 2;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_goal() {
+        assert_eq!(Goal::parse("length 10"), Some(Goal::Length(10)));
+        assert_eq!(Goal::parse(" score  20 "), Some(Goal::Score(20)));
+        assert_eq!(Goal::parse("time 60"), None);
+        assert_eq!(Goal::parse("length"), None);
+        let goal = Goal::Score(20);
+        assert_eq!(Goal::parse(&goal.to_text()), Some(goal));
+        assert!(!goal.is_reached(30, 19));
+        assert!(goal.is_reached(1, 20));
+        assert!(Goal::Length(5).is_reached(5, 0));
+
+        let status = StageStatus {
+            stage: 2,
+            goal: Goal::Length(12),
+            progress: 5,
+        };
+        assert_eq!(StageStatus::from_text(&status.to_text()), Some(status));
+    }
+
+    #[test]
+    fn test_parse() {
+        let stage = Stage::parse(
+            "name = 测试\ngoal = score 15\ncurve = stepped\nwrap = true\nfoods = 1\n---\n#.\n.S#\n",
+        )
+        .unwrap();
+        assert_eq!(stage.name, "测试");
+        assert_eq!(stage.goal, Goal::Score(15));
+        assert_eq!(stage.speed.curve.name(), "stepped");
+        assert_eq!(stage.speed.difficulty, Difficulty::Normal);
+        assert!(stage.rules.wrap && stage.rules.power_ups);
+        assert_eq!(stage.rules.food_count, 1);
+        assert_eq!(stage.walls, vec![Point::new(0, 0), Point::new(60, 30)]);
+        assert_eq!(stage.start, Some(Point::new(30, 30)));
+        assert_eq!(stage.direction, None);
+
+        // 没有地图也可以
+        assert!(Stage::parse("goal = length 3").unwrap().walls.is_empty());
+        assert!(Stage::parse("name = a").is_err());
+        assert!(Stage::parse("goal = length 3\nwrap = yes").is_err());
+        assert!(Stage::parse("goal = length 3\n---\n#X").is_err());
+
+        // 传送门按字母配对
+        let stage = Stage::parse("goal = length 3\n---\nb.a\na.b\n").unwrap();
+        assert_eq!(
+            stage.portals,
+            vec![
+                (Point::new(60, 0), Point::new(0, 30)),
+                (Point::new(0, 0), Point::new(60, 30)),
+            ]
+        );
+        assert!(Stage::parse("goal = length 3\n---\na.a\na").is_err());
+    }
+
+    #[test]
+    fn test_builtin() {
+        // 默认窗口的棋盘里，起点不能是障碍物，前面要留出空地
+        let board = Board::new(500, 500);
+        for stage in Stage::builtin() {
+            assert!(!stage.name.is_empty());
+            let walls = stage.walls_in(&board);
+            assert_eq!(walls.len(), stage.walls.len(), "{}", stage.name);
+            assert_eq!(stage.portals_in(&board), stage.portals);
+            let start = stage.start.clone().unwrap();
+            assert!(board.contains(start.x(), start.y()));
+            let ahead = Point::new(start.x() + consts::BODY_SIZE, start.y());
+            assert_eq!(stage.direction, Some(Direction::Right));
+            assert!(!walls.contains(&start) && !walls.contains(&ahead));
+            let is_portal = |p: &Point| stage.portals.iter().any(|(a, b)| a == p || b == p);
+            assert!(!is_portal(&start) && !is_portal(&ahead));
+        }
+    }
+
+    #[test]
+    fn test_progress() {
+        let mut progress = Progress {
+            path: PathBuf::new(),
+            unlocked: 1,
+        };
+        assert!(progress.clear(0));
+        assert_eq!(progress.unlocked(), 2);
+        assert!(!progress.clear(0));
+        assert!(progress.clear(3));
+        assert_eq!(progress.unlocked(), 5);
+    }
+}
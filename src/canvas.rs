@@ -0,0 +1,179 @@
+use std::path::Path;
+
+use fltk::{enums, image, prelude::*};
+
+use crate::i18n::{self, Msg};
+
+// 画面的绘制接口，窗口里用 FLTK 绘制，测试时画到内存里
+// 坐标都是窗口坐标，颜色都是 0xRRGGBB
+pub trait Canvas {
+    fn rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: u32);
+    // 实心圆，(x, y) 为外接正方形的左上角，d 为直径
+    fn circle(&mut self, x: i32, y: i32, d: i32, color: u32);
+    // 实心的凸多边形
+    fn polygon(&mut self, points: &[(i32, i32)], color: u32);
+    fn line(&mut self, from: (i32, i32), to: (i32, i32), width: i32, color: u32);
+    // 椭圆的边，dotted 时画成虚线
+    fn ring(&mut self, rect: (i32, i32, i32, i32), width: i32, dotted: bool, color: u32);
+    // 在矩形里画一行文字，竖直方向居中
+    fn text(&mut self, text: &str, rect: (i32, i32, i32, i32), align: Align, style: &TextStyle);
+    fn measure(&mut self, text: &str, style: &TextStyle) -> (i32, i32);
+    // 把图片缩放到矩形里
+    fn image(&mut self, image: &Bitmap, x: i32, y: i32, w: i32, h: i32);
+
+    // 只重画一部分时，不需要重画的区域可以跳过
+    fn is_visible(&self, _x: i32, _y: i32, _w: i32, _h: i32) -> bool {
+        true
+    }
+}
+
+// 文字水平方向的对齐
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Align {
+    Left,
+    Center,
+}
+
+// 字体：helvetica/courier/times/screen，不认识的使用 helvetica
+#[derive(PartialEq, Debug, Clone)]
+pub struct TextStyle<'a> {
+    pub font: &'a str,
+    pub bold: bool,
+    pub size: i32,
+    pub color: u32,
+}
+
+// 内存里的图片，每个像素4个字节 RGBA
+#[derive(PartialEq, Debug, Clone)]
+pub struct Bitmap {
+    pub width: i32,
+    pub height: i32,
+    pub data: Vec<u8>,
+}
+
+impl Bitmap {
+    // 从每个像素 depth 个字节的数据转换，支持灰度、灰度+透明、RGB、RGBA
+    pub fn new(pixels: &[u8], width: i32, height: i32, depth: usize) -> Option<Bitmap> {
+        if width <= 0 || height <= 0 || pixels.len() != (width * height) as usize * depth {
+            return None;
+        }
+        let data = pixels
+            .chunks_exact(depth)
+            .flat_map(|p| match depth {
+                1 => [p[0], p[0], p[0], 255],
+                2 => [p[0], p[0], p[0], p[1]],
+                3 => [p[0], p[1], p[2], 255],
+                _ => [p[0], p[1], p[2], p[3]],
+            })
+            .collect();
+        match depth {
+            1..=4 => Some(Bitmap {
+                width,
+                height,
+                data,
+            }),
+            _ => None,
+        }
+    }
+
+    // 读取图片文件，格式由 FLTK 识别
+    pub fn load(path: &Path) -> Result<Bitmap, String> {
+        let image = image::SharedImage::load(path).map_err(|e| e.to_string())?;
+        Bitmap::new(
+            &image.to_rgb_data(),
+            image.data_w(),
+            image.data_h(),
+            image.depth() as usize,
+        )
+        .ok_or_else(|| i18n::tr(Msg::UnsupportedImage).to_string())
+    }
+}
+
+// 主题字体名对应的 FLTK 字体
+pub fn fltk_font(font: &str, bold: bool) -> enums::Font {
+    match (font, bold) {
+        ("courier", false) => enums::Font::Courier,
+        ("courier", true) => enums::Font::CourierBold,
+        ("times", false) => enums::Font::Times,
+        ("times", true) => enums::Font::TimesBold,
+        ("screen", false) => enums::Font::Screen,
+        ("screen", true) => enums::Font::ScreenBold,
+        (_, false) => enums::Font::Helvetica,
+        (_, true) => enums::Font::HelveticaBold,
+    }
+}
+
+// 用 FLTK 绘制到当前的窗口、离屏缓冲或者图片上
+pub struct FltkCanvas;
+
+impl Canvas for FltkCanvas {
+    fn rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: u32) {
+        fltk::draw::draw_rect_fill(x, y, w, h, enums::Color::from_hex(color));
+    }
+
+    fn circle(&mut self, x: i32, y: i32, d: i32, color: u32) {
+        fltk::draw::draw_circle_fill(x, y, d, enums::Color::from_hex(color));
+    }
+
+    fn polygon(&mut self, points: &[(i32, i32)], color: u32) {
+        fltk::draw::set_draw_color(enums::Color::from_hex(color));
+        fltk::draw::begin_polygon();
+        for (x, y) in points {
+            fltk::draw::vertex(*x as f64, *y as f64);
+        }
+        fltk::draw::end_polygon();
+    }
+
+    fn line(&mut self, from: (i32, i32), to: (i32, i32), width: i32, color: u32) {
+        fltk::draw::set_draw_color(enums::Color::from_hex(color));
+        fltk::draw::set_line_style(fltk::draw::LineStyle::Solid, width);
+        fltk::draw::draw_line(from.0, from.1, to.0, to.1);
+        fltk::draw::set_line_style(fltk::draw::LineStyle::Solid, 0);
+    }
+
+    fn ring(&mut self, rect: (i32, i32, i32, i32), width: i32, dotted: bool, color: u32) {
+        let (x, y, w, h) = rect;
+        let style = if dotted {
+            fltk::draw::LineStyle::Dot
+        } else {
+            fltk::draw::LineStyle::Solid
+        };
+        fltk::draw::set_draw_color(enums::Color::from_hex(color));
+        fltk::draw::set_line_style(style, width);
+        fltk::draw::draw_arc(x, y, w, h, 0.0, 360.0);
+        fltk::draw::set_line_style(fltk::draw::LineStyle::Solid, 0);
+    }
+
+    fn text(&mut self, text: &str, rect: (i32, i32, i32, i32), align: Align, style: &TextStyle) {
+        let (x, y, w, h) = rect;
+        let align = match align {
+            Align::Left => enums::Align::Left,
+            Align::Center => enums::Align::Center,
+        };
+        fltk::draw::set_font(fltk_font(style.font, style.bold), style.size);
+        fltk::draw::set_draw_color(enums::Color::from_hex(style.color));
+        fltk::draw::draw_text2(text, x, y, w, h, align);
+    }
+
+    fn measure(&mut self, text: &str, style: &TextStyle) -> (i32, i32) {
+        fltk::draw::set_font(fltk_font(style.font, style.bold), style.size);
+        fltk::draw::measure(text, false)
+    }
+
+    fn image(&mut self, image: &Bitmap, x: i32, y: i32, w: i32, h: i32) {
+        let rgba = image::RgbImage::new(
+            &image.data,
+            image.width,
+            image.height,
+            enums::ColorDepth::Rgba8,
+        );
+        if let Ok(mut rgba) = rgba {
+            rgba.scale(w, h, false, true);
+            rgba.draw(x, y, w, h);
+        }
+    }
+
+    fn is_visible(&self, x: i32, y: i32, w: i32, h: i32) -> bool {
+        fltk::draw::not_clipped(x, y, w, h)
+    }
+}
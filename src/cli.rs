@@ -0,0 +1,87 @@
+use std::path::PathBuf;
+
+use crate::i18n::{self, Msg};
+
+// 命令行参数
+#[derive(PartialEq, Debug, Default)]
+pub struct Options {
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub gif: Option<PathBuf>,
+    pub screenshot: Option<PathBuf>,
+    pub step: Option<usize>,
+    pub mute: bool,
+    pub sound: Option<PathBuf>,
+}
+
+impl Options {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+        let mut options = Options::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| i18n::trf(Msg::MissingValue, &[&arg]))
+            };
+            match arg.as_str() {
+                "--record" => options.record = Some(PathBuf::from(value()?)),
+                "--replay" => options.replay = Some(PathBuf::from(value()?)),
+                "--gif" => options.gif = Some(PathBuf::from(value()?)),
+                "--screenshot" => options.screenshot = Some(PathBuf::from(value()?)),
+                "--mute" => options.mute = true,
+                "--sound" => options.sound = Some(PathBuf::from(value()?)),
+                "--step" => {
+                    let step = value()?;
+                    options.step = Some(step.parse().map_err(|_| format!("--step: {}", step))?)
+                }
+                _ => return Err(i18n::trf(Msg::UnknownArgument, &[&arg])),
+            }
+        }
+
+        if options.replay.is_none() && (options.gif.is_some() || options.screenshot.is_some()) {
+            return Err(i18n::tr(Msg::NeedsReplay).to_string());
+        }
+        if options.replay.is_some() && options.gif.is_none() && options.screenshot.is_none() {
+            return Err(i18n::tr(Msg::NeedsOutput).to_string());
+        }
+        if options.mute && options.sound.is_some() {
+            return Err(i18n::tr(Msg::MuteWithSound).to_string());
+        }
+        Ok(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|s| s.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(&[]), Ok(Options::default()));
+        let options = parse(&[
+            "--replay",
+            "a.replay",
+            "--screenshot",
+            "a.png",
+            "--step",
+            "3",
+        ])
+        .unwrap();
+        assert_eq!(options.replay, Some(PathBuf::from("a.replay")));
+        assert_eq!(options.step, Some(3));
+
+        assert!(parse(&["--gif", "a.gif"]).is_err());
+        assert!(parse(&["--replay", "a.replay"]).is_err());
+        assert!(parse(&["--record"]).is_err());
+        assert!(parse(&["--fast"]).is_err());
+        assert!(parse(&["--mute", "--sound", "a.wav"]).is_err());
+        assert_eq!(
+            parse(&["--sound", "a.wav"]).unwrap().sound,
+            Some(PathBuf::from("a.wav"))
+        );
+    }
+}
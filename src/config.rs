@@ -0,0 +1,101 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::i18n::{self, Msg};
+
+// 简单的配置文件格式：
+// 一行一个 key = value，# 开头为注释，空行忽略
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct Config {
+    values: BTreeMap<String, String>,
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            values: BTreeMap::new(),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut config = Config::new();
+        for (idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((key, value)) => config.set(key.trim(), value.trim()),
+                None => return Err(i18n::trf(Msg::LineFormatAt, &[&(idx + 1), &line])),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn load(path: &Path) -> Result<Config, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Config::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|v| v.as_str())
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.values.keys()
+    }
+
+    pub fn to_text(&self) -> String {
+        let lines: Vec<String> = self
+            .values
+            .iter()
+            .map(|(key, value)| format!("{} = {}", key, value))
+            .collect();
+        lines.join("\n")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, self.to_text()).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+// 颜色格式：#RRGGBB 或者 0xRRGGBB
+pub fn parse_color(value: &str) -> Result<u32, String> {
+    let hex = value
+        .strip_prefix('#')
+        .or_else(|| value.strip_prefix("0x"))
+        .ok_or_else(|| i18n::trf(Msg::ColorFormat, &[&value]))?;
+    if hex.len() != 6 {
+        return Err(i18n::trf(Msg::ColorFormat, &[&value]));
+    }
+    u32::from_str_radix(hex, 16).map_err(|_| i18n::trf(Msg::ColorFormat, &[&value]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let config = Config::parse("# 注释\n\nname = 暗色\n  font=  times \n").unwrap();
+        assert_eq!(config.get("name"), Some("暗色"));
+        assert_eq!(config.get("font"), Some("times"));
+        assert_eq!(config.get("background"), None);
+        assert_eq!(Config::parse(&config.to_text()), Ok(config));
+        assert!(Config::parse("name").is_err());
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#00A4E9"), Ok(0x00A4E9));
+        assert_eq!(parse_color("0xff0000"), Ok(0xFF0000));
+        assert!(parse_color("red").is_err());
+        assert!(parse_color("#FFF").is_err());
+    }
+}
@@ -1,8 +1,65 @@
-// 移动步伐大小
-pub const MOVE_STEP: i32 = 1;
+use std::time::Duration;
+
 // body大小，小方框
 pub const BODY_SIZE: i32 = 30;
 // 眼睛大小，小圆
 pub const EYE_SIZE: i32 = 10;
 // 眼睛眼白大小，小圆，必须小于EYE_SIZE
 pub const EYE_WHITE_SIZE: i32 = 4;
+// 场上同时存在的食物数量
+pub const FOOD_COUNT: usize = 3;
+// 猎物每隔几格逃一步，比蛇慢才追得上
+pub const PREY_MOVE_TICKS: u32 = 2;
+// 狩猎模式场上的猎物数量
+pub const PREY_COUNT: usize = 4;
+// 谨慎的对手走过去以后，除了放下整条蛇还要多留的格子
+pub const RIVAL_SPACE_MARGIN: usize = 3;
+// 拦截的对手瞄准玩家蛇头前面几格
+pub const RIVAL_CUT_AHEAD: i32 = 3;
+// 对手至少离玩家蛇头这么多格出现
+pub const RIVAL_SPAWN_DISTANCE: i32 = 5;
+// 对手撞死后过几格重新出现
+pub const RIVAL_RESPAWN_TICKS: u32 = 20;
+// 磁铁吸引食物的范围，格子数
+pub const MAGNET_RADIUS: i32 = 4;
+// 最多保存的最高分记录数
+pub const MAX_HIGH_SCORES: usize = 10;
+// 渲染帧率，两次移动之间的帧对位置插值
+pub const RENDER_FPS: u32 = 60;
+// 鼠标、触摸屏滑动超过这个距离才算转弯，逻辑坐标
+pub const SWIPE_DISTANCE: i32 = 30;
+// 手柄摇杆的死区，推到最大为1.0
+pub const GAMEPAD_DEADZONE: f64 = 0.5;
+// 检查手柄插拔的间隔
+pub const GAMEPAD_SCAN_INTERVAL: Duration = Duration::from_secs(2);
+// 一格之内最多排队的转向数
+pub const MAX_QUEUED_TURNS: usize = 3;
+// 尾巴变细的节数
+pub const TAPER_SEGMENTS: usize = 4;
+// 吐舌头的周期和每次吐舌头的时间
+pub const TONGUE_PERIOD: Duration = Duration::from_millis(2500);
+pub const TONGUE_FLICK: Duration = Duration::from_millis(400);
+
+// 一帧里需要重画的区域超过这个数量时合并成一个
+pub const MAX_DAMAGE_RECTS: usize = 16;
+
+// 状态栏在棋盘上方时的高度，在右边时的宽度，逻辑坐标
+pub const HUD_HEIGHT: i32 = 40;
+pub const HUD_WIDTH: i32 = 150;
+// 状态栏最小字号，再小也放不下时超出部分不显示
+pub const HUD_MIN_FONT: i32 = 8;
+
+// 限时模式一局的时间
+pub const TIME_ATTACK_DURATION: Duration = Duration::from_secs(60);
+// 生存模式出现障碍物、棋盘缩小一圈的间隔
+pub const SURVIVAL_SPAWN_INTERVAL: Duration = Duration::from_secs(5);
+pub const SURVIVAL_SHRINK_INTERVAL: Duration = Duration::from_secs(30);
+// 生存模式棋盘最小缩到多少格宽
+pub const SURVIVAL_MIN_SIZE: i32 = 6;
+// 生存模式的障碍物至少离蛇头这么多格，来得及躲开
+pub const SURVIVAL_SPAWN_DISTANCE: i32 = 3;
+// 禅模式咬断尾巴时，每少一节扣的分
+pub const ZEN_CUT_PENALTY: i32 = 1;
+
+// 录制gif时两帧之间的间隔
+pub const RECORD_INTERVAL: Duration = Duration::from_millis(100);
@@ -0,0 +1,126 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use crate::consts;
+
+// 逻辑坐标下的矩形 (x, y, w, h)
+pub type Rect = (i32, i32, i32, i32);
+
+// 一帧里画出来的一个东西，key 由影响画法的所有状态计算
+// 两帧之间 key 和位置都没变的东西不需要重画
+#[derive(PartialEq, Eq, Hash, Clone, Debug)]
+pub struct Sprite {
+    key: u64,
+    rect: Rect,
+}
+
+impl Sprite {
+    pub fn new<T: Hash>(content: &T, rect: Rect) -> Sprite {
+        Sprite {
+            key: hash_of(content),
+            rect,
+        }
+    }
+}
+
+pub fn hash_of<T: Hash>(content: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+// 比较前后两帧，计算需要重画的区域
+pub struct DamageTracker {
+    scene: Option<u64>, // 主题等整体的状态，变了需要全部重画
+    sprites: HashSet<Sprite>,
+}
+
+impl DamageTracker {
+    pub fn new() -> DamageTracker {
+        DamageTracker {
+            scene: None,
+            sprites: HashSet::new(),
+        }
+    }
+
+    // 下一帧全部重画，切换界面时使用
+    pub fn invalidate(&mut self) {
+        self.scene = None;
+        self.sprites.clear();
+    }
+
+    // 记录这一帧，返回需要重画的区域，None 表示全部重画
+    pub fn update(&mut self, scene: u64, sprites: Vec<Sprite>) -> Option<Vec<Rect>> {
+        let sprites: HashSet<Sprite> = sprites.into_iter().collect();
+        let full = self.scene != Some(scene);
+        let mut rects: Vec<Rect> = self
+            .sprites
+            .symmetric_difference(&sprites)
+            .map(|sprite| sprite.rect)
+            .collect();
+        self.scene = Some(scene);
+        self.sprites = sprites;
+        if full {
+            return None;
+        }
+
+        rects.sort();
+        rects.dedup();
+        // 区域太多时合并成一个
+        if rects.len() > consts::MAX_DAMAGE_RECTS {
+            rects = vec![bounding_box(&rects)];
+        }
+        Some(rects)
+    }
+}
+
+// 包含所有矩形的最小矩形
+pub fn bounding_box(rects: &[Rect]) -> Rect {
+    let x1 = rects.iter().map(|r| r.0).min().unwrap_or(0);
+    let y1 = rects.iter().map(|r| r.1).min().unwrap_or(0);
+    let x2 = rects.iter().map(|r| r.0 + r.2).max().unwrap_or(0);
+    let y2 = rects.iter().map(|r| r.1 + r.3).max().unwrap_or(0);
+    (x1, y1, x2 - x1, y2 - y1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        let mut tracker = DamageTracker::new();
+        let cell = |x: i32| (x, 0, 30, 30);
+
+        // 第一帧全部重画
+        let frame = vec![
+            Sprite::new(&"head", cell(30)),
+            Sprite::new(&"body", cell(0)),
+        ];
+        assert_eq!(tracker.update(0, frame.clone()), None);
+        assert_eq!(tracker.update(0, frame), Some(vec![]));
+
+        // 蛇头移动，新旧两个位置都要重画
+        let frame = vec![
+            Sprite::new(&"head", cell(60)),
+            Sprite::new(&"body", cell(0)),
+        ];
+        assert_eq!(
+            tracker.update(0, frame.clone()),
+            Some(vec![cell(30), cell(60)])
+        );
+
+        // 换了主题
+        assert_eq!(tracker.update(1, frame), None);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        assert_eq!(
+            bounding_box(&[(0, 0, 30, 30), (60, 90, 30, 30)]),
+            (0, 0, 90, 120)
+        );
+    }
+}
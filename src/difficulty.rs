@@ -0,0 +1,176 @@
+use crate::i18n::{self, Msg};
+
+// 难度预设，决定初始速度和最快速度
+#[derive(PartialEq, Debug, Clone, Copy, Hash, Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Insane,
+}
+
+pub const DIFFICULTIES: [Difficulty; 4] = [
+    Difficulty::Easy,
+    Difficulty::Normal,
+    Difficulty::Hard,
+    Difficulty::Insane,
+];
+
+impl Difficulty {
+    // 保存到文件时使用的名字
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+            Difficulty::Insane => "insane",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        DIFFICULTIES.iter().find(|d| d.name() == name).copied()
+    }
+
+    // 界面上显示的名字
+    pub fn label(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => i18n::tr(Msg::Easy),
+            Difficulty::Normal => i18n::tr(Msg::Normal),
+            Difficulty::Hard => i18n::tr(Msg::Hard),
+            Difficulty::Insane => i18n::tr(Msg::Insane),
+        }
+    }
+
+    // 初始移动间隔，秒
+    pub fn base_interval(&self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.25,
+            Difficulty::Normal => 0.21,
+            Difficulty::Hard => 0.15,
+            Difficulty::Insane => 0.10,
+        }
+    }
+
+    // 最小移动间隔，秒，速度不会超过这个
+    pub fn min_interval(&self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.10,
+            Difficulty::Normal => 0.06,
+            Difficulty::Hard => 0.04,
+            Difficulty::Insane => 0.02,
+        }
+    }
+}
+
+// 速度曲线，根据吃到的食物数计算加速
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum SpeedCurve {
+    Constant,                            // 匀速
+    Linear { per_food: f64 },            // 每吃一个减少固定间隔
+    Stepped { every: u32, factor: f64 }, // 每吃every个，间隔乘以factor
+    Logarithmic { scale: f64 },          // 先快后慢
+}
+
+pub const SPEED_CURVES: [SpeedCurve; 4] = [
+    SpeedCurve::Constant,
+    SpeedCurve::Linear { per_food: 0.005 },
+    SpeedCurve::Stepped {
+        every: 5,
+        factor: 0.85,
+    },
+    SpeedCurve::Logarithmic { scale: 0.04 },
+];
+
+impl SpeedCurve {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SpeedCurve::Constant => "constant",
+            SpeedCurve::Linear { .. } => "linear",
+            SpeedCurve::Stepped { .. } => "stepped",
+            SpeedCurve::Logarithmic { .. } => "log",
+        }
+    }
+
+    // 根据名字获取默认参数的曲线
+    pub fn from_name(name: &str) -> Option<SpeedCurve> {
+        SPEED_CURVES.iter().find(|c| c.name() == name).copied()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SpeedCurve::Constant => i18n::tr(Msg::CurveConstant),
+            SpeedCurve::Linear { .. } => i18n::tr(Msg::CurveLinear),
+            SpeedCurve::Stepped { .. } => i18n::tr(Msg::CurveStepped),
+            SpeedCurve::Logarithmic { .. } => i18n::tr(Msg::CurveLogarithmic),
+        }
+    }
+
+    // 吃到eaten个食物后的移动间隔，未限制最小值
+    fn apply(&self, base: f64, eaten: u32) -> f64 {
+        match *self {
+            SpeedCurve::Constant => base,
+            SpeedCurve::Linear { per_food } => base - eaten as f64 * per_food,
+            SpeedCurve::Stepped { every, factor } => base * factor.powi((eaten / every) as i32),
+            SpeedCurve::Logarithmic { scale } => base - scale * (1.0 + eaten as f64).ln(),
+        }
+    }
+}
+
+// 一局游戏的速度设置
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct SpeedSetting {
+    pub difficulty: Difficulty,
+    pub curve: SpeedCurve,
+}
+
+impl Default for SpeedSetting {
+    fn default() -> Self {
+        SpeedSetting {
+            difficulty: Difficulty::Normal,
+            curve: SpeedCurve::Linear { per_food: 0.005 },
+        }
+    }
+}
+
+impl SpeedSetting {
+    // sleep 时间决定了speed，吃得越多，speed越快
+    pub fn interval(&self, eaten: u32) -> f64 {
+        let base = self.difficulty.base_interval();
+        self.curve
+            .apply(base, eaten)
+            .max(self.difficulty.min_interval())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval() {
+        let setting = SpeedSetting {
+            difficulty: Difficulty::Hard,
+            curve: SpeedCurve::Constant,
+        };
+        assert_eq!(setting.interval(100), 0.15);
+
+        let setting = SpeedSetting {
+            difficulty: Difficulty::Normal,
+            curve: SpeedCurve::from_name("stepped").unwrap(),
+        };
+        assert_eq!(setting.interval(4), 0.21);
+        assert!(setting.interval(5) < 0.21);
+        // 最快不超过min_interval
+        assert_eq!(setting.interval(1000), 0.06);
+    }
+
+    #[test]
+    fn test_name() {
+        for d in DIFFICULTIES.iter() {
+            assert_eq!(Difficulty::from_name(d.name()), Some(*d));
+        }
+        for c in SPEED_CURVES.iter() {
+            assert_eq!(SpeedCurve::from_name(c.name()), Some(*c));
+        }
+    }
+}
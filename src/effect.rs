@@ -0,0 +1,179 @@
+use std::time::{Duration, Instant};
+
+// 道具效果种类
+#[derive(PartialEq, Debug, Clone, Copy, Hash, Eq)]
+pub enum EffectKind {
+    Ghost,    // 幽灵，蛇头可以穿过自己的身体
+    Magnet,   // 磁铁，吸引附近的食物
+    Shield,   // 护盾，抵挡一次撞墙或咬到自己
+    SpeedUp,  // 加速
+    SlowDown, // 减速
+}
+
+pub const EFFECT_KINDS: [EffectKind; 5] = [
+    EffectKind::Ghost,
+    EffectKind::Magnet,
+    EffectKind::Shield,
+    EffectKind::SpeedUp,
+    EffectKind::SlowDown,
+];
+
+impl EffectKind {
+    // 录像里使用的名字
+    pub fn name(&self) -> &'static str {
+        match self {
+            EffectKind::Ghost => "ghost",
+            EffectKind::Magnet => "magnet",
+            EffectKind::Shield => "shield",
+            EffectKind::SpeedUp => "speedup",
+            EffectKind::SlowDown => "slowdown",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<EffectKind> {
+        EFFECT_KINDS.iter().find(|k| k.name() == name).copied()
+    }
+
+    // 持续时间
+    pub fn duration(&self) -> Duration {
+        match self {
+            EffectKind::Ghost => Duration::from_secs(5),
+            EffectKind::Magnet => Duration::from_secs(8),
+            EffectKind::Shield => Duration::from_secs(15),
+            EffectKind::SpeedUp | EffectKind::SlowDown => Duration::from_secs(5),
+        }
+    }
+
+    // HUD 上显示的图标
+    pub fn icon(&self) -> &'static str {
+        match self {
+            EffectKind::Ghost => "👻",
+            EffectKind::Magnet => "🧲",
+            EffectKind::Shield => "🛡",
+            EffectKind::SpeedUp => "⚡",
+            EffectKind::SlowDown => "🐢",
+        }
+    }
+
+    // 速度变化，作用在每次移动的间隔上，小于1为加速
+    pub fn speed_factor(&self) -> f64 {
+        match self {
+            EffectKind::SpeedUp => 0.6,
+            EffectKind::SlowDown => 1.6,
+            _ => 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Effect {
+    kind: EffectKind,
+    end: Instant, // 结束时间
+}
+
+impl Effect {
+    pub fn kind(&self) -> EffectKind {
+        self.kind
+    }
+
+    // 剩余时间
+    pub fn remaining(&self, now: Instant) -> Duration {
+        self.end.saturating_duration_since(now)
+    }
+}
+
+// 当前生效中的道具效果，每帧 tick 一次
+#[derive(Clone, Debug, Default)]
+pub struct ActiveEffects {
+    effects: Vec<Effect>,
+}
+
+impl ActiveEffects {
+    pub fn new() -> ActiveEffects {
+        ActiveEffects { effects: vec![] }
+    }
+
+    // 添加效果，已经存在的同类效果重新计时
+    // 加速和减速互相抵消，只保留最后一个
+    pub fn add(&mut self, kind: EffectKind, now: Instant) {
+        self.effects.retain(|e| {
+            e.kind != kind
+                && !matches!(
+                    (e.kind, kind),
+                    (EffectKind::SpeedUp, EffectKind::SlowDown)
+                        | (EffectKind::SlowDown, EffectKind::SpeedUp)
+                )
+        });
+        self.effects.push(Effect {
+            kind,
+            end: now + kind.duration(),
+        });
+    }
+
+    // 去掉过期的效果
+    pub fn tick(&mut self, now: Instant) {
+        self.effects.retain(|e| e.end > now);
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    // 顺延所有效果的结束时间，暂停期间不计时
+    pub fn postpone(&mut self, duration: Duration) {
+        for effect in self.effects.iter_mut() {
+            effect.end += duration;
+        }
+    }
+
+    pub fn is_active(&self, kind: EffectKind) -> bool {
+        self.effects.iter().any(|e| e.kind == kind)
+    }
+
+    // 消耗一个效果（护盾），成功消耗返回true
+    pub fn consume(&mut self, kind: EffectKind) -> bool {
+        let len = self.effects.len();
+        self.effects.retain(|e| e.kind != kind);
+        self.effects.len() != len
+    }
+
+    pub fn effects(&self) -> &Vec<Effect> {
+        &self.effects
+    }
+
+    // 所有生效中的速度变化相乘
+    pub fn speed_factor(&self) -> f64 {
+        self.effects.iter().map(|e| e.kind.speed_factor()).product()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick_and_consume() {
+        let now = Instant::now();
+        let mut effects = ActiveEffects::new();
+        effects.add(EffectKind::Ghost, now);
+        effects.add(EffectKind::Shield, now);
+        assert!(effects.is_active(EffectKind::Ghost));
+
+        effects.tick(now + Duration::from_secs(6));
+        assert!(!effects.is_active(EffectKind::Ghost));
+        assert!(effects.is_active(EffectKind::Shield));
+
+        assert!(effects.consume(EffectKind::Shield));
+        assert!(!effects.consume(EffectKind::Shield));
+    }
+
+    #[test]
+    fn test_speed_override() {
+        let now = Instant::now();
+        let mut effects = ActiveEffects::new();
+        effects.add(EffectKind::SpeedUp, now);
+        effects.add(EffectKind::SlowDown, now);
+        assert_eq!(effects.effects().len(), 1);
+        assert_eq!(effects.speed_factor(), 1.6);
+    }
+}
@@ -1,12 +1,176 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    consts,
+    effect::EffectKind,
+    snake::{Direction, Point},
+    utils,
+};
+
+// 食物种类
+#[derive(PartialEq, Debug, Clone, Copy, Hash, Eq)]
+pub enum FoodKind {
+    Normal,   // 普通食物，长1节
+    Golden,   // 金色食物，长3节，额外得分，超时消失
+    Shrink,   // 缩小食物，减少节数
+    SpeedUp,  // 加速
+    SlowDown, // 减速
+    Ghost,    // 道具：幽灵
+    Magnet,   // 道具：磁铁
+    Shield,   // 道具：护盾
+    Prey,     // 猎物，会逃离蛇头，长2节
+}
+
+// 所有种类，按权重随机时使用
+pub const FOOD_KINDS: [FoodKind; 9] = [
+    FoodKind::Normal,
+    FoodKind::Golden,
+    FoodKind::Shrink,
+    FoodKind::SpeedUp,
+    FoodKind::SlowDown,
+    FoodKind::Ghost,
+    FoodKind::Magnet,
+    FoodKind::Shield,
+    FoodKind::Prey,
+];
+
+impl FoodKind {
+    // 刷新权重，越大越容易出现
+    pub fn weight(&self) -> u32 {
+        match self {
+            FoodKind::Normal => 60,
+            FoodKind::Golden => 10,
+            FoodKind::Shrink => 10,
+            FoodKind::SpeedUp => 10,
+            FoodKind::SlowDown => 10,
+            FoodKind::Ghost | FoodKind::Magnet | FoodKind::Shield => 5,
+            FoodKind::Prey => 5,
+        }
+    }
+
+    // 存活时间，None 表示不会消失
+    pub fn lifetime(&self) -> Option<Duration> {
+        match self {
+            FoodKind::Normal => None,
+            FoodKind::Golden => Some(Duration::from_secs(6)),
+            FoodKind::Shrink => Some(Duration::from_secs(10)),
+            FoodKind::SpeedUp | FoodKind::SlowDown => Some(Duration::from_secs(8)),
+            FoodKind::Ghost | FoodKind::Magnet | FoodKind::Shield => Some(Duration::from_secs(8)),
+            FoodKind::Prey => None,
+        }
+    }
+
+    // 配置文件里使用的名字
+    pub fn name(&self) -> &'static str {
+        match self {
+            FoodKind::Normal => "normal",
+            FoodKind::Golden => "golden",
+            FoodKind::Shrink => "shrink",
+            FoodKind::SpeedUp => "speedup",
+            FoodKind::SlowDown => "slowdown",
+            FoodKind::Ghost => "ghost",
+            FoodKind::Magnet => "magnet",
+            FoodKind::Shield => "shield",
+            FoodKind::Prey => "prey",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<FoodKind> {
+        FOOD_KINDS.iter().find(|k| k.name() == name).copied()
+    }
+
+    // 绘制用的字符
+    pub fn glyph(&self) -> &'static str {
+        match self {
+            FoodKind::Normal => "💩",
+            FoodKind::Golden => "🌟",
+            FoodKind::Shrink => "🍄",
+            FoodKind::SpeedUp => "⚡",
+            FoodKind::SlowDown => "🐢",
+            FoodKind::Ghost => "👻",
+            FoodKind::Magnet => "🧲",
+            FoodKind::Shield => "🛡",
+            FoodKind::Prey => "🐭",
+        }
+    }
+
+    // 吃到后长度变化，负数为缩短
+    pub fn growth(&self) -> i32 {
+        match self {
+            FoodKind::Normal => 1,
+            FoodKind::Golden => 3,
+            FoodKind::Shrink => -2,
+            FoodKind::SpeedUp | FoodKind::SlowDown => 1,
+            FoodKind::Ghost | FoodKind::Magnet | FoodKind::Shield => 0,
+            FoodKind::Prey => 2,
+        }
+    }
+
+    // 吃到后的得分
+    pub fn score(&self) -> i32 {
+        match self {
+            FoodKind::Normal => 1,
+            FoodKind::Golden => 10,
+            FoodKind::Shrink => 0,
+            FoodKind::SpeedUp | FoodKind::SlowDown => 1,
+            FoodKind::Ghost | FoodKind::Magnet | FoodKind::Shield => 0,
+            FoodKind::Prey => 5,
+        }
+    }
+
+    // 会不会自己移动
+    pub fn is_moving(&self) -> bool {
+        *self == FoodKind::Prey
+    }
+
+    // 吃到后获得的效果
+    pub fn effect(&self) -> Option<EffectKind> {
+        match self {
+            FoodKind::SpeedUp => Some(EffectKind::SpeedUp),
+            FoodKind::SlowDown => Some(EffectKind::SlowDown),
+            FoodKind::Ghost => Some(EffectKind::Ghost),
+            FoodKind::Magnet => Some(EffectKind::Magnet),
+            FoodKind::Shield => Some(EffectKind::Shield),
+            _ => None,
+        }
+    }
+
+    // 按权重随机一个种类
+    pub fn random() -> FoodKind {
+        let total: u32 = FOOD_KINDS.iter().map(|k| k.weight()).sum();
+        FoodKind::pick(utils::rand_range(0, total))
+    }
+
+    // roll 落在[0, 权重总和)，找到对应的种类
+    fn pick(mut roll: u32) -> FoodKind {
+        for kind in FOOD_KINDS.iter() {
+            if roll < kind.weight() {
+                return *kind;
+            }
+            roll -= kind.weight();
+        }
+        FoodKind::Normal
+    }
+}
+
 #[derive(Clone)]
 pub struct Food {
     x: i32,
     y: i32,
+    kind: FoodKind,
+    spawned_at: Instant,      // 生成时间，用来判断是否过期
+    from: Option<(i32, i32)>, // 猎物这一格刚离开的位置
 }
 
 impl Food {
-    pub fn new(x: i32, y: i32) -> Food {
-        Food { x, y }
+    pub fn new(x: i32, y: i32, kind: FoodKind) -> Food {
+        Food {
+            x,
+            y,
+            kind,
+            spawned_at: Instant::now(),
+            from: None,
+        }
     }
     pub fn x(&self) -> i32 {
         self.x
@@ -14,4 +178,112 @@ impl Food {
     pub fn y(&self) -> i32 {
         self.y
     }
+    // 移动食物，磁铁吸引时使用
+    pub fn set_position(&mut self, x: i32, y: i32) {
+        self.x = x;
+        self.y = y;
+    }
+    pub fn kind(&self) -> FoodKind {
+        self.kind
+    }
+
+    // 猎物走一步，记下离开的位置
+    pub fn step_to(&mut self, x: i32, y: i32) {
+        self.from = Some((self.x, self.y));
+        self.set_position(x, y);
+    }
+
+    // 新的一格开始，上一格离开的位置不算了
+    pub fn settle(&mut self) {
+        self.from = None;
+    }
+
+    // 是否被蛇头(x, y)吃到：在同一格，或者这一格刚从那里逃走
+    pub fn is_caught(&self, x: i32, y: i32) -> bool {
+        (self.x == x && self.y == y) || self.from == Some((x, y))
+    }
+
+    // 顺延生成时间，暂停期间不计时
+    pub fn postpone(&mut self, duration: Duration) {
+        self.spawned_at += duration;
+    }
+
+    // 是否已经过期
+    pub fn is_expired(&self, now: Instant) -> bool {
+        match self.kind.lifetime() {
+            Some(lifetime) => now.duration_since(self.spawned_at) >= lifetime,
+            None => false,
+        }
+    }
+}
+
+// 猎物逃跑的下一格：相邻的空格子里离蛇头最远的，要比现在远，没有时不动
+// 距离按格子数算横竖相加
+pub fn flee_step(prey: &Point, head: &Point, is_free: impl Fn(&Point) -> bool) -> Option<Point> {
+    let distance = |p: &Point| (p.x() - head.x()).abs() + (p.y() - head.y()).abs();
+    [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ]
+    .iter()
+    .map(|direction| {
+        let (dx, dy) = match direction {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        };
+        Point::new(
+            prey.x() + dx * consts::BODY_SIZE,
+            prey.y() + dy * consts::BODY_SIZE,
+        )
+    })
+    .filter(|p| distance(p) > distance(prey) && is_free(p))
+    .max_by_key(|p| distance(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_by_weight() {
+        assert_eq!(FoodKind::pick(0), FoodKind::Normal);
+        assert_eq!(FoodKind::pick(59), FoodKind::Normal);
+        assert_eq!(FoodKind::pick(60), FoodKind::Golden);
+        assert_eq!(FoodKind::pick(99), FoodKind::SlowDown);
+        assert_eq!(FoodKind::pick(114), FoodKind::Shield);
+    }
+
+    #[test]
+    fn test_is_expired() {
+        let normal = Food::new(0, 0, FoodKind::Normal);
+        let golden = Food::new(0, 0, FoodKind::Golden);
+        let later = Instant::now() + Duration::from_secs(7);
+        assert!(!normal.is_expired(later));
+        assert!(golden.is_expired(later));
+        assert!(!golden.is_expired(Instant::now()));
+    }
+
+    #[test]
+    fn test_flee() {
+        let head = Point::new(60, 60);
+        let prey = Point::new(90, 60);
+        // 往右逃，右边最远
+        assert_eq!(flee_step(&prey, &head, |_| true), Some(Point::new(120, 60)));
+        // 右边被挡住时往上下逃
+        let blocked = |p: &Point| p.x() < 120 && p.y() != 30;
+        assert_eq!(flee_step(&prey, &head, blocked), Some(Point::new(90, 90)));
+        // 无路可逃
+        assert_eq!(flee_step(&prey, &head, |_| false), None);
+
+        // 刚逃走的猎物也算吃到
+        let mut food = Food::new(90, 60, FoodKind::Prey);
+        food.step_to(120, 60);
+        assert!(food.is_caught(90, 60) && food.is_caught(120, 60));
+        food.settle();
+        assert!(!food.is_caught(90, 60));
+    }
 }
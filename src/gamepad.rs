@@ -0,0 +1,366 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use crate::{
+    consts,
+    i18n::{self, Msg},
+    snake::Direction,
+};
+
+// Linux 的手柄输入：读取 /dev/input/event* 的 input_event
+// 64位系统上每个事件24个字节：时间16字节，类型、代码各2字节，值4字节
+pub const EVENT_SIZE: usize = 24;
+
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+
+const BTN_SOUTH: u16 = 0x130; // A，同时也是判断手柄的 BTN_GAMEPAD
+const BTN_EAST: u16 = 0x131; // B
+const BTN_START: u16 = 0x13b;
+const BTN_DPAD_UP: u16 = 0x220;
+const BTN_DPAD_DOWN: u16 = 0x221;
+const BTN_DPAD_LEFT: u16 = 0x222;
+const BTN_DPAD_RIGHT: u16 = 0x223;
+
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_HAT0X: u16 = 0x10; // 很多手柄的十字键是这两个轴，值为 -1/0/1
+const ABS_HAT0Y: u16 = 0x11;
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct InputEvent {
+    pub kind: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+impl InputEvent {
+    pub fn parse(bytes: &[u8; EVENT_SIZE]) -> InputEvent {
+        InputEvent {
+            kind: u16::from_ne_bytes([bytes[16], bytes[17]]),
+            code: u16::from_ne_bytes([bytes[18], bytes[19]]),
+            value: i32::from_ne_bytes([bytes[20], bytes[21], bytes[22], bytes[23]]),
+        }
+    }
+}
+
+// 手柄的操作，和按键走同样的处理
+#[derive(PartialEq, Debug, Clone)]
+pub enum PadInput {
+    Turn(Direction),
+    Start,   // 开始、暂停、继续
+    Confirm, // A：菜单确认
+    Back,    // B：菜单返回
+}
+
+// 摇杆轴的范围
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct AxisRange {
+    pub min: i32,
+    pub max: i32,
+}
+
+impl AxisRange {
+    // 大多数手柄的摇杆范围
+    pub const DEFAULT: AxisRange = AxisRange {
+        min: -32768,
+        max: 32767,
+    };
+
+    // 换算到 -1.0 ~ 1.0，中间为0
+    fn normalize(&self, value: i32) -> f64 {
+        let center = (self.min as f64 + self.max as f64) / 2.0;
+        let half = (self.max as f64 - self.min as f64) / 2.0;
+        if half <= 0.0 {
+            return 0.0;
+        }
+        ((value as f64 - center) / half).clamp(-1.0, 1.0)
+    }
+}
+
+// 一个手柄的状态，把原始事件转换成操作
+#[derive(PartialEq, Debug, Clone)]
+pub struct Gamepad {
+    x: AxisRange,
+    y: AxisRange,
+    stick: (f64, f64),
+    stick_direction: Option<Direction>, // 摇杆当前指向，回到死区之前不重复发送
+}
+
+impl Gamepad {
+    pub fn new(x: AxisRange, y: AxisRange) -> Gamepad {
+        Gamepad {
+            x,
+            y,
+            stick: (0.0, 0.0),
+            stick_direction: None,
+        }
+    }
+
+    pub fn handle(&mut self, event: &InputEvent) -> Option<PadInput> {
+        match (event.kind, event.code) {
+            // 按钮只处理按下，不处理松开和长按重复
+            (EV_KEY, code) if event.value == 1 => match code {
+                BTN_DPAD_UP => Some(PadInput::Turn(Direction::Up)),
+                BTN_DPAD_DOWN => Some(PadInput::Turn(Direction::Down)),
+                BTN_DPAD_LEFT => Some(PadInput::Turn(Direction::Left)),
+                BTN_DPAD_RIGHT => Some(PadInput::Turn(Direction::Right)),
+                BTN_START => Some(PadInput::Start),
+                BTN_SOUTH => Some(PadInput::Confirm),
+                BTN_EAST => Some(PadInput::Back),
+                _ => None,
+            },
+            (EV_ABS, ABS_HAT0X) => match event.value.signum() {
+                -1 => Some(PadInput::Turn(Direction::Left)),
+                1 => Some(PadInput::Turn(Direction::Right)),
+                _ => None,
+            },
+            (EV_ABS, ABS_HAT0Y) => match event.value.signum() {
+                -1 => Some(PadInput::Turn(Direction::Up)),
+                1 => Some(PadInput::Turn(Direction::Down)),
+                _ => None,
+            },
+            (EV_ABS, ABS_X) => {
+                self.stick.0 = self.x.normalize(event.value);
+                self.stick_turn()
+            }
+            (EV_ABS, ABS_Y) => {
+                self.stick.1 = self.y.normalize(event.value);
+                self.stick_turn()
+            }
+            _ => None,
+        }
+    }
+
+    // 摇杆推出死区后按偏得多的一边转向，方向变了才发送
+    fn stick_turn(&mut self) -> Option<PadInput> {
+        let (x, y) = self.stick;
+        let direction = if x.abs().max(y.abs()) < consts::GAMEPAD_DEADZONE {
+            None
+        } else if x.abs() >= y.abs() {
+            Some(if x > 0.0 {
+                Direction::Right
+            } else {
+                Direction::Left
+            })
+        } else {
+            Some(if y > 0.0 {
+                Direction::Down
+            } else {
+                Direction::Up
+            })
+        };
+        if direction == self.stick_direction {
+            return None;
+        }
+        self.stick_direction = direction.clone();
+        direction.map(PadInput::Turn)
+    }
+}
+
+// 从事件流里读取事件，发送转换后的操作，直到读完或者设备被拔掉
+pub fn pump(
+    mut reader: impl Read,
+    mut gamepad: Gamepad,
+    sender: &Sender<PadInput>,
+    notify: &impl Fn(),
+) {
+    let mut bytes = [0; EVENT_SIZE];
+    while reader.read_exact(&mut bytes).is_ok() {
+        if let Some(input) = gamepad.handle(&InputEvent::parse(&bytes)) {
+            if sender.send(input).is_err() {
+                return;
+            }
+            notify();
+        }
+    }
+}
+
+// sysfs 里的能力位图：空格分隔的十六进制数，高位的数在前，每个数64位
+pub fn has_capability(bitmap: &str, bit: usize) -> bool {
+    let words: Vec<&str> = bitmap.split_whitespace().rev().collect();
+    words
+        .get(bit / 64)
+        .and_then(|word| u64::from_str_radix(word, 16).ok())
+        .is_some_and(|word| word & (1 << (bit % 64)) != 0)
+}
+
+// 有手柄按钮的输入设备
+fn find_gamepads() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir("/sys/class/input")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("event"))
+        .filter(|entry| {
+            fs::read_to_string(entry.path().join("device/capabilities/key"))
+                .is_ok_and(|keys| has_capability(&keys, BTN_SOUTH as usize))
+        })
+        .map(|entry| Path::new("/dev/input").join(entry.file_name()))
+        .collect();
+    paths.sort();
+    paths
+}
+
+// 读取设备的摇杆范围，读不到时使用默认范围
+#[cfg(target_os = "linux")]
+fn axis_range(file: &File, axis: u16) -> AxisRange {
+    use std::os::{
+        fd::AsRawFd,
+        raw::{c_int, c_ulong},
+    };
+    extern "C" {
+        fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    }
+    // EVIOCGABS(axis)：读取 input_absinfo，6个 i32，第2、3个为最小值和最大值
+    let request = 0x8018_4540 + axis as c_ulong;
+    let mut info = [0i32; 6];
+    let result = unsafe { ioctl(file.as_raw_fd(), request, info.as_mut_ptr()) };
+    match result >= 0 && info[1] < info[2] {
+        true => AxisRange {
+            min: info[1],
+            max: info[2],
+        },
+        false => AxisRange::DEFAULT,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn axis_range(_file: &File, _axis: u16) -> AxisRange {
+    AxisRange::DEFAULT
+}
+
+// 在后台监听手柄，支持热插拔，每个手柄一个线程
+// 收到操作后调用 notify 唤醒界面线程
+pub fn spawn(notify: impl Fn() + Send + Sync + 'static) -> Receiver<PadInput> {
+    let (sender, receiver) = mpsc::channel();
+    let notify = Arc::new(notify);
+    let opened: Arc<Mutex<HashSet<PathBuf>>> = Arc::new(Mutex::new(HashSet::new()));
+    thread::spawn(move || loop {
+        let found = find_gamepads();
+        // 拔掉的设备从列表里去掉，再插上时重新打开
+        opened.lock().unwrap().retain(|path| found.contains(path));
+        for path in found {
+            if !opened.lock().unwrap().insert(path.clone()) {
+                continue;
+            }
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) => {
+                    println!(
+                        "{}",
+                        i18n::trf(Msg::GamepadOpenFailed, &[&path.display(), &e])
+                    );
+                    continue;
+                }
+            };
+            println!("{}", i18n::trf(Msg::GamepadConnected, &[&path.display()]));
+            let gamepad = Gamepad::new(axis_range(&file, ABS_X), axis_range(&file, ABS_Y));
+            let (sender, notify, opened) =
+                (sender.clone(), Arc::clone(&notify), Arc::clone(&opened));
+            thread::spawn(move || {
+                pump(file, gamepad, &sender, &|| notify());
+                println!(
+                    "{}",
+                    i18n::trf(Msg::GamepadDisconnected, &[&path.display()])
+                );
+                opened.lock().unwrap().remove(&path);
+            });
+        }
+        thread::sleep(consts::GAMEPAD_SCAN_INTERVAL);
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(kind: u16, code: u16, value: i32) -> InputEvent {
+        InputEvent { kind, code, value }
+    }
+
+    // 转换成设备里的格式，用来录制事件流
+    fn record(e: InputEvent) -> [u8; EVENT_SIZE] {
+        let mut bytes = [0; EVENT_SIZE];
+        bytes[16..18].copy_from_slice(&e.kind.to_ne_bytes());
+        bytes[18..20].copy_from_slice(&e.code.to_ne_bytes());
+        bytes[20..24].copy_from_slice(&e.value.to_ne_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_parse() {
+        let e = event(EV_ABS, ABS_HAT0Y, -1);
+        assert_eq!(InputEvent::parse(&record(e)), e);
+    }
+
+    #[test]
+    fn test_capability() {
+        // BTN_SOUTH 为 0x130，在第5个64位数里
+        let keys = "7fff000000000000 0 100040000000 0 0";
+        assert!(has_capability(keys, 0x130));
+        assert!(!has_capability(keys, 0x120));
+        assert!(!has_capability("", 0x130));
+        assert!(has_capability("1 0 0 0 0", 0x100));
+    }
+
+    #[test]
+    fn test_recorded_stream() {
+        // 录下来的事件流：十字键、摇杆在死区里晃、推到左边、松开、Start
+        let events = [
+            event(EV_KEY, BTN_DPAD_UP, 1),
+            event(EV_KEY, BTN_DPAD_UP, 0),
+            event(EV_ABS, ABS_X, 8000),
+            event(EV_ABS, ABS_X, -30000),
+            event(EV_ABS, ABS_Y, 3000),
+            event(EV_ABS, ABS_X, 0),
+            event(EV_ABS, ABS_HAT0X, 1),
+            event(EV_ABS, ABS_HAT0X, 0),
+            event(EV_KEY, BTN_START, 1),
+        ];
+        let stream: Vec<u8> = events.into_iter().flat_map(record).collect();
+
+        let (sender, receiver) = mpsc::channel();
+        let gamepad = Gamepad::new(AxisRange::DEFAULT, AxisRange::DEFAULT);
+        let count = std::cell::Cell::new(0);
+        pump(stream.as_slice(), gamepad, &sender, &|| {
+            count.set(count.get() + 1)
+        });
+        let inputs: Vec<PadInput> = receiver.try_iter().collect();
+        assert_eq!(
+            inputs,
+            vec![
+                PadInput::Turn(Direction::Up),
+                PadInput::Turn(Direction::Left),
+                PadInput::Turn(Direction::Right),
+                PadInput::Start,
+            ]
+        );
+        assert_eq!(count.get(), 4);
+    }
+
+    #[test]
+    fn test_axis_range() {
+        // 0~255 的摇杆，中间为127.5
+        let mut gamepad = Gamepad::new(
+            AxisRange { min: 0, max: 255 },
+            AxisRange { min: 0, max: 255 },
+        );
+        assert_eq!(gamepad.handle(&event(EV_ABS, ABS_Y, 140)), None);
+        assert_eq!(
+            gamepad.handle(&event(EV_ABS, ABS_Y, 250)),
+            Some(PadInput::Turn(Direction::Down))
+        );
+        assert_eq!(gamepad.handle(&event(EV_ABS, ABS_Y, 255)), None);
+    }
+}
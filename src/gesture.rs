@@ -0,0 +1,132 @@
+use crate::{
+    consts,
+    snake::{Direction, Point},
+};
+
+// 鼠标和触摸屏的手势，坐标都是逻辑坐标
+// 按下后拖动超过一段距离算滑动，没有滑动就松开算点击
+#[derive(PartialEq, Debug, Clone)]
+pub struct Gesture {
+    anchor: Option<(i32, i32)>, // 按下的位置，滑动一次后移到当前位置
+    swiped: bool,               // 这次按下后已经滑动过
+    distance: i32,              // 算作滑动的最短距离
+}
+
+impl Gesture {
+    pub fn new(distance: i32) -> Gesture {
+        Gesture {
+            anchor: None,
+            swiped: false,
+            distance,
+        }
+    }
+
+    pub fn press(&mut self, x: i32, y: i32) {
+        self.anchor = Some((x, y));
+        self.swiped = false;
+    }
+
+    // 拖动超过距离时返回滑动的方向，不用松开，一次拖动可以连续转几次弯
+    pub fn drag(&mut self, x: i32, y: i32) -> Option<Direction> {
+        let (ax, ay) = self.anchor?;
+        let (dx, dy) = (x - ax, y - ay);
+        if dx.abs().max(dy.abs()) < self.distance {
+            return None;
+        }
+        self.anchor = Some((x, y));
+        self.swiped = true;
+        Some(main_direction(dx, dy))
+    }
+
+    // 松开时返回滑动的方向，没有滑动过就按点击处理：
+    // 点在蛇头的哪一侧就往哪边转，head 为蛇头中心
+    pub fn release(
+        &mut self,
+        x: i32,
+        y: i32,
+        head: (i32, i32),
+        direction: &Direction,
+    ) -> Option<Direction> {
+        let swipe = self.drag(x, y);
+        let swiped = self.swiped;
+        self.anchor = None;
+        self.swiped = false;
+        match swiped {
+            true => swipe,
+            false => click_direction(head, direction, (x, y)),
+        }
+    }
+}
+
+// 水平和竖直方向哪个移动得多
+fn main_direction(dx: i32, dy: i32) -> Direction {
+    if dx.abs() >= dy.abs() {
+        if dx > 0 {
+            Direction::Right
+        } else {
+            Direction::Left
+        }
+    } else if dy > 0 {
+        Direction::Down
+    } else {
+        Direction::Up
+    }
+}
+
+// 点击只用来转弯：横着走时点上下，竖着走时点左右
+// 点在蛇头所在的行或者列上时不转
+pub fn click_direction(
+    head: (i32, i32),
+    direction: &Direction,
+    point: (i32, i32),
+) -> Option<Direction> {
+    let (dx, dy) = (point.0 - head.0, point.1 - head.1);
+    let half = consts::BODY_SIZE / 2;
+    match direction {
+        Direction::Left | Direction::Right if dy.abs() > half => Some(main_direction(0, dy)),
+        Direction::Up | Direction::Down if dx.abs() > half => Some(main_direction(dx, 0)),
+        _ => None,
+    }
+}
+
+// 格子中心，点击时和蛇头比较
+pub fn cell_center(point: &Point) -> (i32, i32) {
+    let half = consts::BODY_SIZE / 2;
+    (point.x() + half, point.y() + half)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_swipe() {
+        let mut gesture = Gesture::new(30);
+        gesture.press(100, 100);
+        assert_eq!(gesture.drag(110, 95), None);
+        assert_eq!(gesture.drag(140, 110), Some(Direction::Right));
+        // 接着往下拖，从上次转弯的位置算起
+        assert_eq!(gesture.drag(145, 130), None);
+        assert_eq!(gesture.drag(150, 150), Some(Direction::Down));
+        assert_eq!(gesture.release(150, 160, (0, 0), &Direction::Right), None);
+        // 没有按下时拖动不算
+        assert_eq!(gesture.drag(300, 300), None);
+    }
+
+    #[test]
+    fn test_click() {
+        let mut gesture = Gesture::new(30);
+        gesture.press(100, 20);
+        let head = (100, 100);
+        assert_eq!(
+            gesture.release(105, 25, head, &Direction::Right),
+            Some(Direction::Up)
+        );
+        // 竖着走时点上下不转，点左右转
+        assert_eq!(click_direction(head, &Direction::Up, (100, 20)), None);
+        assert_eq!(
+            click_direction(head, &Direction::Down, (20, 110)),
+            Some(Direction::Left)
+        );
+    }
+}
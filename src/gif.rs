@@ -0,0 +1,190 @@
+use std::{collections::HashMap, io::Write};
+
+// GIF 动图编码，每帧写入一次，录制时不用把所有帧留在内存里
+pub struct GifWriter<W: Write> {
+    writer: W,
+    width: u16,
+    height: u16,
+}
+
+impl<W: Write> GifWriter<W> {
+    // 写入文件头，循环播放
+    pub fn new(mut writer: W, width: u16, height: u16) -> std::io::Result<GifWriter<W>> {
+        writer.write_all(b"GIF89a")?;
+        writer.write_all(&width.to_le_bytes())?;
+        writer.write_all(&height.to_le_bytes())?;
+        writer.write_all(&[0, 0, 0])?; // 没有全局调色板
+        writer.write_all(&[0x21, 0xFF, 0x0B])?;
+        writer.write_all(b"NETSCAPE2.0")?;
+        writer.write_all(&[0x03, 0x01, 0x00, 0x00, 0x00])?;
+        Ok(GifWriter {
+            writer,
+            width,
+            height,
+        })
+    }
+
+    // 写入一帧，rgb 为逐行排列的像素数据，delay 为显示时间，单位1/100秒
+    pub fn frame(&mut self, rgb: &[u8], delay: u16) -> std::io::Result<()> {
+        let (palette, indices) = quantize(rgb);
+
+        // 图形控制扩展：显示时间
+        self.writer.write_all(&[0x21, 0xF9, 0x04, 0x04])?;
+        self.writer.write_all(&delay.to_le_bytes())?;
+        self.writer.write_all(&[0x00, 0x00])?;
+
+        // 图像描述，使用256色的局部调色板
+        self.writer.write_all(&[0x2C, 0, 0, 0, 0])?;
+        self.writer.write_all(&self.width.to_le_bytes())?;
+        self.writer.write_all(&self.height.to_le_bytes())?;
+        self.writer.write_all(&[0x87])?;
+        self.writer.write_all(&palette)?;
+
+        self.writer.write_all(&[8])?;
+        for block in lzw(&indices).chunks(255) {
+            self.writer.write_all(&[block.len() as u8])?;
+            self.writer.write_all(block)?;
+        }
+        self.writer.write_all(&[0])
+    }
+
+    // 写入结尾
+    pub fn finish(mut self) -> std::io::Result<W> {
+        self.writer.write_all(&[0x3B])?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// 转换成256色，颜色不超过256种时不损失，否则按 RGB 3-3-2 位量化
+// 返回768字节的调色板和每个像素的下标
+fn quantize(rgb: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let mut colors: HashMap<[u8; 3], u8> = HashMap::new();
+    let mut indices = Vec::with_capacity(rgb.len() / 3);
+    for pixel in rgb.chunks_exact(3) {
+        let color = [pixel[0], pixel[1], pixel[2]];
+        let next = colors.len();
+        match colors.get(&color) {
+            Some(idx) => indices.push(*idx),
+            None if next < 256 => {
+                colors.insert(color, next as u8);
+                indices.push(next as u8);
+            }
+            None => {
+                let palette = (0..=255u8)
+                    .flat_map(|i| [(i >> 5) * 36, ((i >> 2) & 7) * 36, (i & 3) * 85])
+                    .collect();
+                let indices = rgb
+                    .chunks_exact(3)
+                    .map(|p| (p[0] & 0xE0) | ((p[1] >> 5) << 2) | (p[2] >> 6))
+                    .collect();
+                return (palette, indices);
+            }
+        }
+    }
+
+    let mut palette = vec![0; 768];
+    for (color, idx) in colors {
+        let start = idx as usize * 3;
+        palette[start..start + 3].copy_from_slice(&color);
+    }
+    (palette, indices)
+}
+
+// LZW 压缩，8位的像素下标
+fn lzw(indices: &[u8]) -> Vec<u8> {
+    const CLEAR: u32 = 256;
+    const END: u32 = 257;
+    const MAX_CODE: u32 = 4096;
+
+    let mut bytes = vec![];
+    let (mut bit, mut bits) = (0u32, 0u32);
+    let mut emit = |code: u32, size: u32| {
+        bit |= code << bits;
+        bits += size;
+        while bits >= 8 {
+            bytes.push(bit as u8);
+            bit >>= 8;
+            bits -= 8;
+        }
+    };
+
+    let mut table: HashMap<(u32, u8), u32> = HashMap::new();
+    let mut next = END + 1;
+    let mut size = 9;
+    emit(CLEAR, size);
+
+    let mut prefix = match indices.first() {
+        Some(first) => *first as u32,
+        None => {
+            emit(END, size);
+            return finish_bits(bytes, bit, bits);
+        }
+    };
+    for index in indices[1..].iter() {
+        if let Some(code) = table.get(&(prefix, *index)) {
+            prefix = *code;
+            continue;
+        }
+        // 解码时比编码晚一步建表，码长在这里提前一格增加
+        if next > 1 << size && size < 12 {
+            size += 1;
+        }
+        emit(prefix, size);
+        if next < MAX_CODE {
+            table.insert((prefix, *index), next);
+            next += 1;
+        } else {
+            // 表满了，重新开始
+            emit(CLEAR, size);
+            table.clear();
+            next = END + 1;
+            size = 9;
+        }
+        prefix = *index as u32;
+    }
+    if next > 1 << size && size < 12 {
+        size += 1;
+    }
+    emit(prefix, size);
+    // 结束码之前解码端会再建一项
+    if next < MAX_CODE {
+        next += 1;
+    }
+    if next > 1 << size && size < 12 {
+        size += 1;
+    }
+    emit(END, size);
+    finish_bits(bytes, bit, bits)
+}
+
+fn finish_bits(mut bytes: Vec<u8>, bit: u32, bits: u32) -> Vec<u8> {
+    if bits > 0 {
+        bytes.push(bit as u8);
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quantize() {
+        let (palette, indices) = quantize(&[255, 0, 0, 0, 0, 255, 255, 0, 0]);
+        assert_eq!(indices, vec![0, 1, 0]);
+        assert_eq!(&palette[..6], &[255, 0, 0, 0, 0, 255]);
+        assert_eq!(palette.len(), 768);
+    }
+
+    #[test]
+    fn test_gif() {
+        let mut gif = GifWriter::new(vec![], 2, 2).unwrap();
+        gif.frame(&[0; 12], 10).unwrap();
+        let data = gif.finish().unwrap();
+        assert_eq!(&data[..6], b"GIF89a");
+        assert_eq!(data[data.len() - 1], 0x3B);
+        // 4个相同的像素：清除码、0、258、0、结束码
+        assert_eq!(lzw(&[0, 0, 0, 0]), vec![0x00, 0x01, 0x08, 0x04, 0x10, 0x10]);
+    }
+}
@@ -0,0 +1,125 @@
+use std::{cmp::Reverse, fs, path::PathBuf};
+
+use crate::{
+    consts,
+    difficulty::{Difficulty, SpeedCurve, SpeedSetting},
+};
+
+// 一条得分记录
+#[derive(PartialEq, Debug, Clone)]
+pub struct ScoreRecord {
+    pub score: i32,
+    pub length: i32,
+    pub speed: SpeedSetting, // 这局的难度和速度曲线
+    pub time: u64,           // 结束时间戳
+}
+
+impl ScoreRecord {
+    // 一行一条记录：score length difficulty curve time，用\t分隔
+    pub fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}",
+            self.score,
+            self.length,
+            self.speed.difficulty.name(),
+            self.speed.curve.name(),
+            self.time
+        )
+    }
+
+    pub fn from_line(line: &str) -> Option<ScoreRecord> {
+        let fields: Vec<&str> = line.trim().split('\t').collect();
+        if fields.len() != 5 {
+            return None;
+        }
+        Some(ScoreRecord {
+            score: fields[0].parse().ok()?,
+            length: fields[1].parse().ok()?,
+            speed: SpeedSetting {
+                difficulty: Difficulty::from_name(fields[2])?,
+                curve: SpeedCurve::from_name(fields[3])?,
+            },
+            time: fields[4].parse().ok()?,
+        })
+    }
+}
+
+// 最高分排行，保存在文件中
+pub struct HighScores {
+    path: PathBuf,
+    records: Vec<ScoreRecord>,
+}
+
+impl HighScores {
+    // 读取排行文件，文件不存在或者格式错误的行直接忽略
+    pub fn load(path: PathBuf) -> HighScores {
+        let records = fs::read_to_string(&path)
+            .map(|content| content.lines().filter_map(ScoreRecord::from_line).collect())
+            .unwrap_or_default();
+        HighScores { path, records }
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let content: Vec<String> = self.records.iter().map(|r| r.to_line()).collect();
+        fs::write(&self.path, content.join("\n")).map_err(|e| e.to_string())
+    }
+
+    // 添加记录，按得分从高到低，只保留前MAX_HIGH_SCORES条
+    // 返回是否是新的最高分
+    pub fn add(&mut self, record: ScoreRecord) -> bool {
+        let is_best = self.best().map_or(true, |best| record.score > best.score);
+        self.records.push(record);
+        self.records.sort_by_key(|r| Reverse(r.score));
+        self.records.truncate(consts::MAX_HIGH_SCORES);
+        is_best
+    }
+
+    pub fn best(&self) -> Option<&ScoreRecord> {
+        self.records.first()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_line() {
+        let record = ScoreRecord {
+            score: 12,
+            length: 8,
+            speed: SpeedSetting {
+                difficulty: Difficulty::Hard,
+                curve: SpeedCurve::from_name("log").unwrap(),
+            },
+            time: 1700000000,
+        };
+        assert_eq!(ScoreRecord::from_line(&record.to_line()), Some(record));
+        assert_eq!(ScoreRecord::from_line("12\t8\thard"), None);
+    }
+
+    #[test]
+    fn test_add() {
+        let mut scores = HighScores {
+            path: PathBuf::new(),
+            records: vec![],
+        };
+        let record = |score| ScoreRecord {
+            score,
+            length: 1,
+            speed: SpeedSetting::default(),
+            time: 0,
+        };
+        assert!(scores.add(record(5)));
+        assert!(!scores.add(record(3)));
+        assert!(scores.add(record(9)));
+        for i in 0..20 {
+            scores.add(record(i));
+        }
+        assert_eq!(scores.records.len(), consts::MAX_HIGH_SCORES);
+        assert_eq!(scores.best().unwrap().score, 19);
+    }
+}
@@ -0,0 +1,132 @@
+use std::time::Duration;
+
+use crate::{
+    board::{Board, Viewport},
+    consts,
+    i18n::{self, Msg},
+};
+
+// 状态栏的位置
+#[derive(PartialEq, Debug, Clone, Copy, Hash)]
+pub enum HudPlacement {
+    Top,  // 棋盘上方，窗口偏高时
+    Side, // 棋盘右边，窗口偏宽时
+}
+
+impl HudPlacement {
+    // 状态栏的逻辑坐标区域，棋盘左上角为原点
+    pub fn rect(&self, board: &Board) -> (i32, i32, i32, i32) {
+        match self {
+            HudPlacement::Top => (0, -consts::HUD_HEIGHT, board.width(), consts::HUD_HEIGHT),
+            HudPlacement::Side => (board.width(), 0, consts::HUD_WIDTH, board.height()),
+        }
+    }
+}
+
+// 棋盘和状态栏一起放进窗口，选择棋盘显示得更大的摆法
+pub fn fit(board: &Board, w: i32, h: i32) -> (Viewport, HudPlacement) {
+    let top = Viewport::fit(
+        &Board::new(board.width(), board.height() + consts::HUD_HEIGHT),
+        w,
+        h,
+    )
+    .shifted(0, consts::HUD_HEIGHT);
+    let side = Viewport::fit(
+        &Board::new(board.width() + consts::HUD_WIDTH, board.height()),
+        w,
+        h,
+    );
+    if side.scale() > top.scale() {
+        (side, HudPlacement::Side)
+    } else {
+        (top, HudPlacement::Top)
+    }
+}
+
+// 状态栏显示的数据
+pub struct HudStats {
+    pub score: i32,
+    pub mode: Vec<String>, // 游戏模式的状态：关卡目标、倒计时
+    pub length: usize,
+    pub elapsed: Duration, // 不含暂停
+    pub speed: f64,        // 每秒移动的格数
+    pub eaten: u32,
+    pub best: i32,
+    pub effects: Vec<String>, // 生效中的道具和剩余时间
+}
+
+impl HudStats {
+    // 每一项的文字，按重要程度排列
+    pub fn items(&self) -> Vec<String> {
+        let mut items = vec![i18n::trf(Msg::HudScore, &[&self.score])];
+        items.extend(self.mode.iter().cloned());
+        items.extend([
+            i18n::trf(Msg::HudBest, &[&self.best]),
+            i18n::trf(Msg::HudLength, &[&self.length]),
+            i18n::trf(Msg::HudTime, &[&format_time(self.elapsed)]),
+            i18n::trf(Msg::HudSpeed, &[&format!("{:.1}", self.speed)]),
+            i18n::trf(Msg::HudEaten, &[&self.eaten]),
+        ]);
+        items.extend(self.effects.iter().cloned());
+        items
+    }
+}
+
+// 分:秒
+pub fn format_time(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
+
+// 依次排列，一行放不下时换行，返回每一项所在的行和横坐标
+pub fn flow(widths: &[i32], width: i32, gap: i32) -> Vec<(usize, i32)> {
+    let mut row = 0;
+    let mut x = 0;
+    widths
+        .iter()
+        .map(|w| {
+            if x > 0 && x + w > width {
+                row += 1;
+                x = 0;
+            }
+            let position = (row, x);
+            x += w + gap;
+            position
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit() {
+        let board = Board::new(500, 500);
+
+        // 偏高的窗口，状态栏在上面
+        let (vp, placement) = fit(&board, 500, 540);
+        assert_eq!(placement, HudPlacement::Top);
+        assert_eq!(vp.y(0), consts::HUD_HEIGHT);
+        assert_eq!(vp.len(consts::BODY_SIZE), consts::BODY_SIZE);
+
+        // 宽屏，状态栏在右边
+        let (vp, placement) = fit(&board, 1000, 500);
+        assert_eq!(placement, HudPlacement::Side);
+        assert_eq!(vp.y(0), 0);
+        let (x, _, _, _) = placement.rect(&board);
+        assert_eq!(vp.x(x), vp.x(0) + 500);
+    }
+
+    #[test]
+    fn test_flow() {
+        assert_eq!(flow(&[40, 40, 40], 100, 10), vec![(0, 0), (0, 50), (1, 0)]);
+        // 比一行还宽的项单独一行
+        assert_eq!(flow(&[200, 40], 100, 10), vec![(0, 0), (1, 0)]);
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(Duration::from_secs(83)), "01:23");
+    }
+}
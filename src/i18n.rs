@@ -0,0 +1,499 @@
+use std::{
+    env,
+    fmt::Display,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+// 界面语言
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Locale {
+    ZhCn,
+    En,
+}
+
+pub const LOCALES: [Locale; 2] = [Locale::ZhCn, Locale::En];
+
+impl Locale {
+    // 配置文件里的名字
+    pub fn name(&self) -> &'static str {
+        match self {
+            Locale::ZhCn => "zh-CN",
+            Locale::En => "en",
+        }
+    }
+
+    // 用这种语言自己的名字显示
+    pub fn label(&self) -> &'static str {
+        match self {
+            Locale::ZhCn => "中文",
+            Locale::En => "English",
+        }
+    }
+
+    // 接受 zh-CN、zh_CN.UTF-8、en_US 等写法
+    // 只有简体中文，zh-TW、zh_HK 等繁体中文不支持
+    pub fn from_name(name: &str) -> Option<Locale> {
+        let name = name
+            .split(['.', '@'])
+            .next()?
+            .to_lowercase()
+            .replace('_', "-");
+        let mut parts = name.split('-');
+        match (parts.next()?, parts.next()) {
+            ("zh", None | Some("cn" | "sg" | "hans")) => Some(Locale::ZhCn),
+            ("en", _) => Some(Locale::En),
+            _ => None,
+        }
+    }
+
+    // 按 LC_ALL、LC_MESSAGES、LANG 的顺序找第一个支持的语言，C 等不支持的跳过
+    pub fn from_env() -> Locale {
+        Locale::from_values(
+            ["LC_ALL", "LC_MESSAGES", "LANG"]
+                .iter()
+                .filter_map(|var| env::var(var).ok()),
+        )
+    }
+
+    // 都没有设置时用中文，设置了但都不支持时用英文
+    fn from_values(values: impl Iterator<Item = String>) -> Locale {
+        let values: Vec<String> = values.filter(|value| !value.is_empty()).collect();
+        if values.is_empty() {
+            return Locale::ZhCn;
+        }
+        values
+            .iter()
+            .find_map(|value| Locale::from_name(value))
+            .unwrap_or(Locale::En)
+    }
+
+    pub fn cycle(&self, step: i32) -> Locale {
+        let idx = LOCALES.iter().position(|l| l == self).unwrap_or(0) as i32;
+        LOCALES[(idx + step).rem_euclid(LOCALES.len() as i32) as usize]
+    }
+}
+
+// 当前语言，界面线程和后台线程共用，下标对应 LOCALES
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+
+pub fn set_locale(locale: Locale) {
+    let idx = LOCALES.iter().position(|l| *l == locale).unwrap_or(0);
+    CURRENT.store(idx, Ordering::Relaxed);
+}
+
+pub fn locale() -> Locale {
+    LOCALES[CURRENT.load(Ordering::Relaxed)]
+}
+
+// 当前语言的文字
+pub fn tr(msg: Msg) -> &'static str {
+    msg.text(locale())
+}
+
+// 当前语言的文字，依次填入 {}
+pub fn trf(msg: Msg, args: &[&dyn Display]) -> String {
+    fill(tr(msg), args)
+}
+
+fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut parts = template.split("{}");
+    let mut text = String::from(parts.next().unwrap_or_default());
+    for (idx, part) in parts.enumerate() {
+        if let Some(arg) = args.get(idx) {
+            text.push_str(&arg.to_string());
+        }
+        text.push_str(part);
+    }
+    text
+}
+
+// 定义 Msg 的同时列出全部消息，新加的消息测试里自动检查
+macro_rules! messages {
+    ($($name:ident,)*) => {
+        // 所有显示给玩家的文字
+        #[derive(PartialEq, Debug, Clone, Copy)]
+        pub enum Msg {
+            $($name,)*
+        }
+
+        impl Msg {
+            #[cfg(test)]
+            const ALL: &'static [Msg] = &[$(Msg::$name,)*];
+        }
+    };
+}
+
+messages! {
+    // 开始界面
+    GameTitle,
+    StartGame,
+    TitleHelp,
+    Endless,
+    TimeAttack,
+    Survival,
+    Zen,
+    Hunt,
+    StageLabel,
+    // 结束界面
+    GameOver,
+    Victory,
+    TimeUp,
+    EndHint,
+    // 状态栏
+    HudScore,
+    HudBest,
+    HudLength,
+    HudTime,
+    HudSpeed,
+    HudEaten,
+    HudStage,
+    HudTimeLeft,
+    HudShrink,
+    GoalLength,
+    GoalScore,
+    // 暂停菜单
+    Paused,
+    Settings,
+    KeySettings,
+    Resume,
+    Restart,
+    BackToTitle,
+    Quit,
+    MenuDifficulty,
+    MenuCurve,
+    MenuTheme,
+    MenuLanguage,
+    Keys,
+    Back,
+    MenuPlayer,
+    MenuPreset,
+    PresetCustom,
+    PressKey,
+    Unbound,
+    KeyDefaults,
+    MenuRivals,
+    NoRivals,
+    // 难度、速度曲线、操作、按键的名字
+    Easy,
+    Normal,
+    Hard,
+    Insane,
+    CurveConstant,
+    CurveLinear,
+    CurveStepped,
+    CurveLogarithmic,
+    ActionUp,
+    ActionDown,
+    ActionLeft,
+    ActionRight,
+    ActionPause,
+    PresetArrows,
+    KeySpace,
+    ThemeClassic,
+    ThemeDark,
+    ThemeHighContrast,
+    ThemeForest,
+    StageWarmUp,
+    StagePillars,
+    StageFence,
+    StageCorridors,
+    StageMaze,
+    StagePortals,
+    RivalGreedy,
+    RivalCautious,
+    RivalAggressive,
+    // 错误
+    OppositeDirection,
+    ReservedKey,
+    ReservedKeyUsed,
+    KeyInUse,
+    DuplicateKey,
+    UnknownSetting,
+    UnknownPreset,
+    UnknownKey,
+    LineFormat,
+    LineFormatAt,
+    ColorFormat,
+    UnsupportedImage,
+    UnsupportedImageAt,
+    TileOutOfImage,
+    RenderFailed,
+    NothingToCapture,
+    ReplayTooShort,
+    BodyNeedsColor,
+    UnknownPattern,
+    UnknownHeadShape,
+    UnknownOption,
+    Missing,
+    SizePositive,
+    UnknownTile,
+    TileFormat,
+    AplayNoInput,
+    UnknownGoal,
+    UnknownCell,
+    UnknownRival,
+    UnpairedPortal,
+    // 命令行
+    Usage,
+    MissingValue,
+    UnknownArgument,
+    NeedsReplay,
+    NeedsOutput,
+    MuteWithSound,
+    // 控制台输出
+    LoadKeysFailed,
+    SaveKeysFailed,
+    LoadSettingsFailed,
+    SaveSettingsFailed,
+    LoadImageFailed,
+    LoadTilesetFailed,
+    LoadThemeFailed,
+    SaveScoresFailed,
+    LoadStageFailed,
+    SaveProgressFailed,
+    ScreenshotSaved,
+    ScreenshotFailed,
+    RecordStarted,
+    RecordSaved,
+    RecordFailed,
+    ReplaySaved,
+    NoSound,
+    PlaySoundFailed,
+    SaveSoundFailed,
+    GamepadConnected,
+    GamepadDisconnected,
+    GamepadOpenFailed,
+}
+
+impl Msg {
+    pub fn text(&self, locale: Locale) -> &'static str {
+        let [zh, en] = self.texts();
+        match locale {
+            Locale::ZhCn => zh,
+            Locale::En => en,
+        }
+    }
+
+    // 中文和英文，{} 的个数必须一样
+    fn texts(&self) -> [&'static str; 2] {
+        match self {
+            Msg::GameTitle => ["贪吃蛇游戏", "Snake"],
+            Msg::StartGame => ["开始游戏", "Start Game"],
+            Msg::Endless => ["无尽模式", "Endless"],
+            Msg::TimeAttack => ["限时模式", "Time Attack"],
+            Msg::Survival => ["生存模式", "Survival"],
+            Msg::Zen => ["禅模式", "Zen"],
+            Msg::Hunt => ["狩猎模式", "Hunt"],
+            Msg::StageLabel => ["第{}关 {}", "Stage {}: {}"],
+            Msg::TitleHelp => [
+                "{} {} {} {} 控制移动方向\n{}键暂停，暂停菜单的设置里可以修改按键",
+                "{} {} {} {} to move\n{} to pause, keys can be changed in the pause menu settings",
+            ],
+            Msg::GameOver => ["游戏结束", "Game Over"],
+            Msg::Victory => ["胜利", "Victory"],
+            Msg::TimeUp => ["时间到", "Time's Up"],
+            Msg::EndHint => [
+                "{}键再来一局，回车返回标题",
+                "{} to play again, Enter for the title screen",
+            ],
+            Msg::HudScore => ["得分 {}", "Score {}"],
+            Msg::HudBest => ["最高 {}", "Best {}"],
+            Msg::HudLength => ["长度 {}", "Length {}"],
+            Msg::HudTime => ["时间 {}", "Time {}"],
+            Msg::HudSpeed => ["速度 {}格/秒", "Speed {}/s"],
+            Msg::HudEaten => ["食物 {}", "Food {}"],
+            Msg::HudStage => ["第{}关 {}", "Stage {} {}"],
+            Msg::HudTimeLeft => ["剩余 {}", "Left {}"],
+            Msg::HudShrink => ["缩小 {}s", "Shrink {}s"],
+            Msg::GoalLength => ["长度 {}/{}", "Length {}/{}"],
+            Msg::GoalScore => ["得分 {}/{}", "Score {}/{}"],
+            Msg::Paused => ["暂停", "Paused"],
+            Msg::Settings => ["设置", "Settings"],
+            Msg::KeySettings => ["按键设置", "Key Bindings"],
+            Msg::Resume => ["继续游戏", "Resume"],
+            Msg::Restart => ["重新开始", "Restart"],
+            Msg::BackToTitle => ["返回标题", "Title Screen"],
+            Msg::Quit => ["退出游戏", "Quit"],
+            Msg::MenuDifficulty => ["难度: ◀ {} ▶", "Difficulty: ◀ {} ▶"],
+            Msg::MenuCurve => ["速度: ◀ {} ▶", "Speed: ◀ {} ▶"],
+            Msg::MenuTheme => ["主题: ◀ {} ▶", "Theme: ◀ {} ▶"],
+            Msg::MenuLanguage => ["语言: ◀ {} ▶", "Language: ◀ {} ▶"],
+            Msg::Keys => ["按键", "Keys"],
+            Msg::Back => ["返回", "Back"],
+            Msg::MenuPlayer => ["玩家: ◀ P{} ▶", "Player: ◀ P{} ▶"],
+            Msg::MenuPreset => ["预设: ◀ {} ▶", "Preset: ◀ {} ▶"],
+            Msg::PresetCustom => ["自定义", "Custom"],
+            Msg::PressKey => ["{}: 请按键，Esc取消", "{}: press a key, Esc to cancel"],
+            Msg::Unbound => ["{}: 未设置", "{}: unbound"],
+            Msg::KeyDefaults => ["恢复默认", "Restore Defaults"],
+            Msg::MenuRivals => ["对手: ◀ {} ▶", "Rivals: ◀ {} ▶"],
+            Msg::NoRivals => ["无", "None"],
+            Msg::Easy => ["简单", "Easy"],
+            Msg::Normal => ["普通", "Normal"],
+            Msg::Hard => ["困难", "Hard"],
+            Msg::Insane => ["地狱", "Insane"],
+            Msg::CurveConstant => ["匀速", "Constant"],
+            Msg::CurveLinear => ["线性加速", "Linear"],
+            Msg::CurveStepped => ["阶梯加速", "Stepped"],
+            Msg::CurveLogarithmic => ["对数加速", "Logarithmic"],
+            Msg::ActionUp => ["上", "Up"],
+            Msg::ActionDown => ["下", "Down"],
+            Msg::ActionLeft => ["左", "Left"],
+            Msg::ActionRight => ["右", "Right"],
+            Msg::ActionPause => ["暂停", "Pause"],
+            Msg::PresetArrows => ["方向键", "Arrows"],
+            Msg::KeySpace => ["空格", "Space"],
+            Msg::ThemeClassic => ["经典", "Classic"],
+            Msg::ThemeDark => ["暗色", "Dark"],
+            Msg::ThemeHighContrast => ["高对比度", "High Contrast"],
+            Msg::ThemeForest => ["森林", "Forest"],
+            Msg::StageWarmUp => ["热身", "Warm-up"],
+            Msg::StagePillars => ["四根柱子", "Pillars"],
+            Msg::StageFence => ["围栏", "Fence"],
+            Msg::StageCorridors => ["走廊", "Corridors"],
+            Msg::StageMaze => ["迷宫", "Maze"],
+            Msg::StagePortals => ["传送门", "Portals"],
+            Msg::RivalGreedy => ["贪吃", "Greedy"],
+            Msg::RivalCautious => ["谨慎", "Cautious"],
+            Msg::RivalAggressive => ["拦截", "Aggressive"],
+            Msg::OppositeDirection => ["不能移动相反方向", "Cannot turn back on itself"],
+            Msg::ReservedKey => ["{} 是系统按键", "{} is a reserved key"],
+            Msg::ReservedKeyUsed => ["{} 是系统按键: {}", "{} is a reserved key: {}"],
+            Msg::KeyInUse => ["{} 已用于 {}", "{} is already used by {}"],
+            Msg::DuplicateKey => ["{} 重复绑定: {}", "{} is bound more than once: {}"],
+            Msg::UnknownSetting => ["未知的设置: {}", "Unknown setting: {}"],
+            Msg::UnknownPreset => ["未知的预设: {}", "Unknown preset: {}"],
+            Msg::UnknownKey => ["未知的按键: {}", "Unknown key: {}"],
+            Msg::LineFormat => ["第{}行格式错误", "Line {} is malformed"],
+            Msg::LineFormatAt => ["第{}行格式错误: {}", "Line {} is malformed: {}"],
+            Msg::ColorFormat => ["颜色格式错误: {}", "Invalid color: {}"],
+            Msg::UnsupportedImage => ["不支持的图片格式", "Unsupported image format"],
+            Msg::UnsupportedImageAt => ["不支持的图片格式: {}", "Unsupported image format: {}"],
+            Msg::TileOutOfImage => ["图块超出图片范围: {}", "Tile is outside the image: {}"],
+            Msg::RenderFailed => ["绘制画面失败", "Failed to render the frame"],
+            Msg::NothingToCapture => ["没有画面", "Nothing to capture"],
+            Msg::ReplayTooShort => ["录像只有{}步", "The replay has only {} steps"],
+            Msg::BodyNeedsColor => ["body 至少需要一个颜色", "body needs at least one color"],
+            Msg::UnknownPattern => ["未知的花纹: {}", "Unknown pattern: {}"],
+            Msg::UnknownHeadShape => ["未知的蛇头形状: {}", "Unknown head shape: {}"],
+            Msg::UnknownOption => ["未知的配置项: {}", "Unknown option: {}"],
+            Msg::Missing => ["缺少 {}", "Missing {}"],
+            Msg::SizePositive => ["size 必须大于0", "size must be greater than 0"],
+            Msg::UnknownTile => ["未知的图块: {}", "Unknown tile: {}"],
+            Msg::TileFormat => ["图块位置格式错误: {} = {}", "Invalid tile position: {} = {}"],
+            Msg::AplayNoInput => ["aplay 没有输入", "aplay has no input"],
+            Msg::UnknownGoal => ["未知的目标: {}", "Unknown goal: {}"],
+            Msg::UnknownCell => ["地图第{}行有未知的字符: {}", "Unknown character on map line {}: {}"],
+            Msg::UnknownRival => ["未知的对手: {}", "Unknown rival: {}"],
+            Msg::UnpairedPortal => ["传送门 {} 需要正好两个", "Portal {} needs exactly two cells"],
+            Msg::Usage => [
+                "用法: Rust_snake [选项]
+  --record <文件.gif>       开始游戏后马上录制，结束时保存gif和同名的 .replay
+  --replay <文件.replay>    读取录像，配合下面的选项输出画面后退出，不打开窗口
+  --gif <文件.gif>          把录像画成gif
+  --screenshot <文件.png>   把录像的一步画成png
+  --step <n>                截图使用第n步（从0开始），默认最后一步
+  --mute                    静音
+  --sound <文件.wav>        不播放声音，写到wav文件",
+                "Usage: Rust_snake [options]
+  --record <file.gif>       record as soon as the game starts, save the gif and a .replay with the same name
+  --replay <file.replay>    load a replay and render it with the options below, without opening a window
+  --gif <file.gif>          render the replay as a gif
+  --screenshot <file.png>   render one step of the replay as a png
+  --step <n>                step used for the screenshot (from 0), defaults to the last step
+  --mute                    no sound
+  --sound <file.wav>        write the sound to a wav file instead of playing it",
+            ],
+            Msg::MissingValue => ["{} 缺少参数", "{} needs a value"],
+            Msg::UnknownArgument => ["未知的参数: {}", "Unknown argument: {}"],
+            Msg::NeedsReplay => [
+                "--gif 和 --screenshot 需要配合 --replay 使用",
+                "--gif and --screenshot need --replay",
+            ],
+            Msg::NeedsOutput => [
+                "--replay 需要配合 --gif 或 --screenshot 使用",
+                "--replay needs --gif or --screenshot",
+            ],
+            Msg::MuteWithSound => [
+                "--mute 和 --sound 不能同时使用",
+                "--mute and --sound cannot be used together",
+            ],
+            Msg::LoadKeysFailed => [
+                "读取按键设置失败，使用默认按键: {}",
+                "Failed to load key bindings, using the defaults: {}",
+            ],
+            Msg::SaveKeysFailed => ["保存按键设置失败: {}", "Failed to save key bindings: {}"],
+            Msg::LoadSettingsFailed => ["读取设置失败: {}", "Failed to load settings: {}"],
+            Msg::SaveSettingsFailed => ["保存设置失败: {}", "Failed to save settings: {}"],
+            Msg::LoadImageFailed => ["读取图片失败: {} {}", "Failed to load image: {} {}"],
+            Msg::LoadTilesetFailed => ["读取图块集失败: {}", "Failed to load tileset: {}"],
+            Msg::LoadThemeFailed => ["读取主题失败: {}", "Failed to load theme: {}"],
+            Msg::SaveScoresFailed => ["保存最高分失败: {}", "Failed to save high scores: {}"],
+            Msg::LoadStageFailed => ["读取关卡失败: {}", "Failed to load stage: {}"],
+            Msg::SaveProgressFailed => ["保存闯关进度失败: {}", "Failed to save campaign progress: {}"],
+            Msg::ScreenshotSaved => ["截图已保存: {}", "Screenshot saved: {}"],
+            Msg::ScreenshotFailed => ["截图失败: {}", "Screenshot failed: {}"],
+            Msg::RecordStarted => ["开始录制: {}", "Recording: {}"],
+            Msg::RecordSaved => ["录制已保存: {}", "Recording saved: {}"],
+            Msg::RecordFailed => ["录制失败: {}", "Recording failed: {}"],
+            Msg::ReplaySaved => ["录像已保存: {}", "Replay saved: {}"],
+            Msg::NoSound => ["无法播放声音: {}", "Cannot play sound: {}"],
+            Msg::PlaySoundFailed => ["播放声音失败: {}", "Failed to play sound: {}"],
+            Msg::SaveSoundFailed => ["保存声音失败: {}", "Failed to save sound: {}"],
+            Msg::GamepadConnected => ["连接手柄: {}", "Gamepad connected: {}"],
+            Msg::GamepadDisconnected => ["手柄断开: {}", "Gamepad disconnected: {}"],
+            Msg::GamepadOpenFailed => ["打开手柄失败: {} {}", "Failed to open gamepad: {} {}"],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale() {
+        assert_eq!(Locale::from_name("zh_CN.UTF-8"), Some(Locale::ZhCn));
+        assert_eq!(Locale::from_name("zh-TW"), None);
+        assert_eq!(Locale::from_name("zh_HK.UTF-8"), None);
+        assert_eq!(Locale::from_name("zh"), Some(Locale::ZhCn));
+        assert_eq!(Locale::from_name("en_US.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::from_name("en"), Some(Locale::En));
+        assert_eq!(Locale::from_name("C"), None);
+        assert_eq!(Locale::from_name(""), None);
+        for locale in LOCALES {
+            assert_eq!(Locale::from_name(locale.name()), Some(locale));
+        }
+        let from = |values: &[&str]| Locale::from_values(values.iter().map(|v| v.to_string()));
+        assert_eq!(from(&["C", "", "zh_CN.UTF-8"]), Locale::ZhCn);
+        assert_eq!(from(&["C"]), Locale::En);
+        assert_eq!(from(&["zh_TW.UTF-8"]), Locale::En);
+        assert_eq!(from(&[""]), Locale::ZhCn);
+        assert_eq!(Locale::ZhCn.cycle(1), Locale::En);
+        assert_eq!(Locale::ZhCn.cycle(-1), Locale::En);
+    }
+
+    #[test]
+    fn test_fill() {
+        assert_eq!(fill("{} 已用于 {}", &[&"↓", &"P1 下"]), "↓ 已用于 P1 下");
+        assert_eq!(fill("第{}行格式错误", &[&3]), "第3行格式错误");
+        // 参数不够时留空
+        assert_eq!(fill("{}: {}", &[&"a"]), "a: ");
+        assert_eq!(fill(Msg::HudScore.text(Locale::En), &[&12]), "Score 12");
+    }
+
+    #[test]
+    fn test_catalogue() {
+        // 每条消息两种语言的参数个数一样
+        for msg in Msg::ALL {
+            let [zh, en] = msg.texts();
+            assert_eq!(
+                zh.matches("{}").count(),
+                en.matches("{}").count(),
+                "{:?}",
+                msg
+            );
+            assert!(!zh.is_empty() && !en.is_empty());
+        }
+    }
+}
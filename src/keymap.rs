@@ -0,0 +1,437 @@
+use std::{collections::BTreeMap, path::Path};
+
+use fltk::enums::Key;
+
+use crate::{
+    config::Config,
+    i18n::{self, Msg},
+    snake::Direction,
+};
+
+// 玩家人数，每个玩家有自己的一套按键
+pub const PLAYERS: usize = 2;
+
+pub const SPACE: Key = Key::from_char(' ');
+
+// 系统按键：Esc 暂停/返回，回车确认，F9 录制，F11 全屏，F12 截图，不能绑定
+const RESERVED_KEYS: [Key; 6] = [
+    Key::Escape,
+    Key::Enter,
+    Key::KPEnter,
+    Key::F9,
+    Key::F11,
+    Key::F12,
+];
+
+// 可以绑定按键的操作
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Pause,
+}
+
+pub const ACTIONS: [Action; 5] = [
+    Action::Up,
+    Action::Down,
+    Action::Left,
+    Action::Right,
+    Action::Pause,
+];
+
+impl Action {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Pause => "pause",
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::Up => i18n::tr(Msg::ActionUp),
+            Action::Down => i18n::tr(Msg::ActionDown),
+            Action::Left => i18n::tr(Msg::ActionLeft),
+            Action::Right => i18n::tr(Msg::ActionRight),
+            Action::Pause => i18n::tr(Msg::ActionPause),
+        }
+    }
+
+    pub fn direction(&self) -> Option<Direction> {
+        match self {
+            Action::Up => Some(Direction::Up),
+            Action::Down => Some(Direction::Down),
+            Action::Left => Some(Direction::Left),
+            Action::Right => Some(Direction::Right),
+            Action::Pause => None,
+        }
+    }
+}
+
+// 方向键的预设
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Preset {
+    Arrows,
+    Wasd,
+    Hjkl,
+}
+
+pub const PRESETS: [Preset; 3] = [Preset::Arrows, Preset::Wasd, Preset::Hjkl];
+
+impl Preset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Arrows => "arrows",
+            Preset::Wasd => "wasd",
+            Preset::Hjkl => "hjkl",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Preset> {
+        PRESETS.iter().find(|p| p.name() == name).copied()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Preset::Arrows => i18n::tr(Msg::PresetArrows),
+            Preset::Wasd => "WASD",
+            Preset::Hjkl => "HJKL",
+        }
+    }
+
+    // 上下左右的按键
+    pub fn keys(&self) -> [(Action, Key); 4] {
+        let [up, down, left, right] = match self {
+            Preset::Arrows => [Key::Up, Key::Down, Key::Left, Key::Right],
+            Preset::Wasd => ['w', 's', 'a', 'd'].map(Key::from_char),
+            Preset::Hjkl => ['k', 'j', 'h', 'l'].map(Key::from_char),
+        };
+        [
+            (Action::Up, up),
+            (Action::Down, down),
+            (Action::Left, left),
+            (Action::Right, right),
+        ]
+    }
+}
+
+// 一个按键位置：第几个玩家（从0开始）的哪个操作
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
+pub struct Slot {
+    pub player: usize,
+    pub action: Action,
+}
+
+impl Slot {
+    pub fn new(player: usize, action: Action) -> Slot {
+        Slot { player, action }
+    }
+
+    pub fn label(&self) -> String {
+        format!("P{} {}", self.player + 1, self.action.label())
+    }
+}
+
+// 有名字的按键，配置文件里使用
+const NAMED_KEYS: [(&str, Key); 10] = [
+    ("space", SPACE),
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("escape", Key::Escape),
+    ("enter", Key::Enter),
+    ("tab", Key::Tab),
+    ("backspace", Key::BackSpace),
+    ("kp_enter", Key::KPEnter),
+];
+
+// 配置文件里的按键名：space/up/down/left/right/f1-f12，字母数字等为字符本身
+pub fn key_name(key: Key) -> String {
+    if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, k)| *k == key) {
+        return name.to_string();
+    }
+    if Key::is_fn_key(key) {
+        return format!("f{}", key.bits() - Key::F1.bits() + 1);
+    }
+    match key.to_char() {
+        Some(c) if c.is_ascii_graphic() => c.to_string(),
+        _ => format!("0x{:x}", key.bits()),
+    }
+}
+
+pub fn key_from_name(name: &str) -> Option<Key> {
+    let name = name.to_lowercase();
+    if let Some((_, key)) = NAMED_KEYS.iter().find(|(n, _)| *n == name) {
+        return Some(*key);
+    }
+    if let Some(n) = name.strip_prefix('f').and_then(|n| n.parse::<i32>().ok()) {
+        return (1..=12).contains(&n).then(|| Key::fn_key(n));
+    }
+    if let Some(hex) = name.strip_prefix("0x") {
+        return i32::from_str_radix(hex, 16).ok().map(Key::from_i32);
+    }
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_graphic() => Some(Key::from_char(c)),
+        _ => None,
+    }
+}
+
+// 菜单里显示的按键名
+pub fn key_label(key: Key) -> String {
+    match key {
+        Key::Up => String::from("↑"),
+        Key::Down => String::from("↓"),
+        Key::Left => String::from("←"),
+        Key::Right => String::from("→"),
+        SPACE => i18n::tr(Msg::KeySpace).to_string(),
+        _ => key_name(key).to_uppercase(),
+    }
+}
+
+// 按键绑定，一个位置可以绑定多个键
+#[derive(PartialEq, Debug, Clone)]
+pub struct KeyMap {
+    bindings: BTreeMap<Slot, Vec<Key>>,
+}
+
+impl Default for KeyMap {
+    // 玩家1用方向键，空格暂停；玩家2默认不绑定
+    fn default() -> KeyMap {
+        let mut keymap = KeyMap {
+            bindings: BTreeMap::new(),
+        };
+        keymap.apply_preset(0, Preset::Arrows);
+        keymap
+            .bindings
+            .insert(Slot::new(0, Action::Pause), vec![SPACE]);
+        keymap
+    }
+}
+
+impl KeyMap {
+    pub fn keys(&self, slot: Slot) -> &[Key] {
+        self.bindings.get(&slot).map_or(&[], |keys| keys.as_slice())
+    }
+
+    // 按键对应的位置
+    pub fn lookup(&self, key: Key) -> Option<Slot> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.contains(&key))
+            .map(|(slot, _)| *slot)
+    }
+
+    pub fn apply_preset(&mut self, player: usize, preset: Preset) {
+        for (action, key) in preset.keys() {
+            self.bindings.insert(Slot::new(player, action), vec![key]);
+        }
+    }
+
+    // 当前的方向键和哪个预设一样
+    pub fn preset(&self, player: usize) -> Option<Preset> {
+        PRESETS.iter().copied().find(|preset| {
+            preset
+                .keys()
+                .iter()
+                .all(|(action, key)| self.keys(Slot::new(player, *action)) == [*key])
+        })
+    }
+
+    // 换成预设的方向键，和其他位置冲突时不修改
+    pub fn try_preset(&mut self, player: usize, preset: Preset) -> Result<(), String> {
+        let mut keymap = self.clone();
+        keymap.apply_preset(player, preset);
+        match keymap.conflicts().first() {
+            Some(conflict) => Err(conflict.clone()),
+            None => {
+                *self = keymap;
+                Ok(())
+            }
+        }
+    }
+
+    // 给一个位置换一个键，系统按键或者已经被别的位置使用时返回原因
+    pub fn bind(&mut self, slot: Slot, key: Key) -> Result<(), String> {
+        if RESERVED_KEYS.contains(&key) {
+            return Err(i18n::trf(Msg::ReservedKey, &[&key_label(key)]));
+        }
+        if let Some(other) = self.lookup(key).filter(|other| *other != slot) {
+            return Err(i18n::trf(Msg::KeyInUse, &[&key_label(key), &other.label()]));
+        }
+        self.bindings.insert(slot, vec![key]);
+        Ok(())
+    }
+
+    // 冲突：系统按键，或者一个键绑定了多个位置
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut slots: BTreeMap<Key, Vec<Slot>> = BTreeMap::new();
+        for (slot, keys) in self.bindings.iter() {
+            for key in keys {
+                slots.entry(*key).or_default().push(*slot);
+            }
+        }
+        let mut conflicts = vec![];
+        for (key, slots) in slots {
+            let labels: Vec<String> = slots.iter().map(|slot| slot.label()).collect();
+            if RESERVED_KEYS.contains(&key) {
+                conflicts.push(i18n::trf(
+                    Msg::ReservedKeyUsed,
+                    &[&key_name(key), &labels.join(", ")],
+                ));
+            } else if slots.len() > 1 {
+                conflicts.push(i18n::trf(
+                    Msg::DuplicateKey,
+                    &[&key_name(key), &labels.join(", ")],
+                ));
+            }
+        }
+        conflicts
+    }
+
+    // 配置文件：
+    // p1.preset = wasd       先使用预设
+    // p1.up = w, up          再逐个修改，多个键用逗号分隔，空值为不绑定
+    pub fn from_config(config: &Config) -> Result<KeyMap, String> {
+        let mut keymap = KeyMap::default();
+        for key in config.keys() {
+            let known = (1..=PLAYERS).any(|n| {
+                key == &format!("p{}.preset", n)
+                    || ACTIONS
+                        .iter()
+                        .any(|a| key == &format!("p{}.{}", n, a.name()))
+            });
+            if !known {
+                return Err(i18n::trf(Msg::UnknownSetting, &[key]));
+            }
+        }
+
+        for player in 0..PLAYERS {
+            let prefix = format!("p{}", player + 1);
+            if let Some(name) = config.get(&format!("{}.preset", prefix)) {
+                let preset = Preset::from_name(name)
+                    .ok_or_else(|| i18n::trf(Msg::UnknownPreset, &[&name]))?;
+                keymap.apply_preset(player, preset);
+            }
+            for action in ACTIONS.iter() {
+                let value = match config.get(&format!("{}.{}", prefix, action.name())) {
+                    Some(value) => value,
+                    None => continue,
+                };
+                let keys = value
+                    .split(',')
+                    .map(|name| name.trim())
+                    .filter(|name| !name.is_empty())
+                    .map(|name| {
+                        key_from_name(name).ok_or_else(|| i18n::trf(Msg::UnknownKey, &[&name]))
+                    })
+                    .collect::<Result<Vec<Key>, String>>()?;
+                let slot = Slot::new(player, *action);
+                match keys.is_empty() {
+                    true => keymap.bindings.remove(&slot),
+                    false => keymap.bindings.insert(slot, keys),
+                };
+            }
+        }
+
+        let conflicts = keymap.conflicts();
+        if !conflicts.is_empty() {
+            return Err(conflicts.join("; "));
+        }
+        Ok(keymap)
+    }
+
+    // 保存全部绑定，不写预设
+    pub fn to_config(&self) -> Config {
+        let mut config = Config::new();
+        for player in 0..PLAYERS {
+            for action in ACTIONS.iter() {
+                let names: Vec<String> = self
+                    .keys(Slot::new(player, *action))
+                    .iter()
+                    .map(|key| key_name(*key))
+                    .collect();
+                config.set(
+                    &format!("p{}.{}", player + 1, action.name()),
+                    &names.join(", "),
+                );
+            }
+        }
+        config
+    }
+
+    pub fn load(path: &Path) -> Result<KeyMap, String> {
+        KeyMap::from_config(&Config::load(path)?).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        self.to_config().save(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_name() {
+        for key in [
+            SPACE,
+            Key::Up,
+            Key::F5,
+            Key::from_char('w'),
+            Key::from_char(';'),
+        ] {
+            assert_eq!(key_from_name(&key_name(key)), Some(key));
+        }
+        assert_eq!(key_name(Key::F12), "f12");
+        assert_eq!(key_from_name("W"), Some(Key::from_char('w')));
+        assert_eq!(key_from_name("f13"), None);
+        assert_eq!(key_from_name("ab"), None);
+    }
+
+    #[test]
+    fn test_bind() {
+        let mut keymap = KeyMap::default();
+        let up = Slot::new(0, Action::Up);
+        assert_eq!(keymap.lookup(Key::Up), Some(up));
+        assert_eq!(keymap.preset(0), Some(Preset::Arrows));
+
+        // 冲突的键和系统按键不能绑定
+        assert!(keymap.bind(up, Key::Down).is_err());
+        assert!(keymap.bind(up, Key::F12).is_err());
+        assert!(keymap.bind(up, Key::from_char('w')).is_ok());
+        assert_eq!(keymap.lookup(Key::Up), None);
+        assert_eq!(keymap.preset(0), None);
+
+        // 玩家2的预设和玩家1冲突时不修改
+        assert!(keymap.try_preset(1, Preset::Wasd).is_err());
+        assert!(keymap.try_preset(1, Preset::Hjkl).is_ok());
+        assert_eq!(keymap.preset(1), Some(Preset::Hjkl));
+        assert!(keymap.conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_config() {
+        let config =
+            Config::parse("p1.preset = wasd\np1.pause = space, p\np2.preset = arrows").unwrap();
+        let keymap = KeyMap::from_config(&config).unwrap();
+        assert_eq!(
+            keymap.lookup(Key::from_char('p')),
+            Some(Slot::new(0, Action::Pause))
+        );
+        assert_eq!(keymap.lookup(Key::Left), Some(Slot::new(1, Action::Left)));
+        assert_eq!(KeyMap::from_config(&keymap.to_config()), Ok(keymap));
+
+        // 方向键同时给了两个玩家
+        assert!(KeyMap::from_config(&Config::parse("p2.preset = arrows").unwrap()).is_err());
+        assert!(KeyMap::from_config(&Config::parse("p1.jump = x").unwrap()).is_err());
+        assert!(KeyMap::from_config(&Config::parse("p1.up = nothing").unwrap()).is_err());
+    }
+}
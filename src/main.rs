@@ -1,13 +1,85 @@
+mod audio;
+mod board;
+mod campaign;
+mod canvas;
+mod cli;
+mod config;
 mod consts;
+mod damage;
+mod difficulty;
+mod effect;
 mod food;
+mod gamepad;
+mod gesture;
+mod gif;
+mod highscore;
+mod hud;
+mod i18n;
+mod keymap;
+mod mode;
+mod motion;
 mod myapp;
+mod pause;
+mod png;
+mod raster;
+mod record;
+mod render;
+mod rival;
+mod scene;
+mod settings;
+mod shape;
 mod snake;
+mod state;
+mod theme;
+mod tileset;
 mod utils;
 
 fn main() {
+    i18n::set_locale(i18n::Locale::from_env());
+    let options = match cli::Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            println!("{}\n{}", e, i18n::tr(i18n::Msg::Usage));
+            std::process::exit(2);
+        }
+    };
+
+    // 只把录像画成图片，不打开窗口
+    if let Some(path) = &options.replay {
+        let result = scene::Replay::load(path).and_then(|replay| {
+            myapp::MyApp::export_replay(
+                &replay,
+                options.gif.as_deref(),
+                options.screenshot.as_deref(),
+                options.step,
+            )
+        });
+        if let Err(e) = result {
+            println!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let mut my_app = myapp::MyApp::new(100, 100, 500, 500);
-    loop {
-        // 结束后仍然可以重启
-        my_app.run();
+
+    // 默认用 aplay 播放声音，没有时静音
+    let output: Box<dyn audio::Output> = match options.sound {
+        Some(path) => Box::new(audio::WavOutput::new(path)),
+        None if options.mute => Box::new(audio::NullOutput),
+        None => match audio::AplayOutput::new() {
+            Ok(output) => Box::new(output),
+            Err(e) => {
+                println!("{}", i18n::trf(i18n::Msg::NoSound, &[&e]));
+                Box::new(audio::NullOutput)
+            }
+        },
+    };
+    my_app.set_audio(audio::Audio::start(output));
+
+    if let Some(path) = options.record {
+        my_app.record_to(path);
     }
+    // 结束后回到开始界面，选择退出时返回
+    my_app.run();
 }
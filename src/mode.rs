@@ -0,0 +1,263 @@
+use std::time::Duration;
+
+use crate::{
+    board::Board,
+    consts, hud,
+    i18n::{self, Msg},
+    snake::Point,
+};
+
+// 游戏模式，每种模式有自己的结束条件和排行榜
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum GameMode {
+    Endless,         // 无尽模式，占满棋盘就是胜利
+    TimeAttack,      // 限时模式，时间到为止吃得越多越好
+    Survival,        // 生存模式，不断出现障碍物，棋盘越来越小
+    Zen,             // 禅模式，撞墙从对面出来，咬到自己只断掉尾巴
+    Hunt,            // 狩猎模式，场上都是会逃跑的猎物
+    Campaign(usize), // 闯关模式，正在玩的关卡
+}
+
+// 开始界面里排在关卡前面的模式
+pub const MODES: [GameMode; 5] = [
+    GameMode::Endless,
+    GameMode::TimeAttack,
+    GameMode::Survival,
+    GameMode::Zen,
+    GameMode::Hunt,
+];
+
+impl GameMode {
+    // 排行榜文件使用的名字，所有关卡共用一个排行榜
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::Endless => "endless",
+            GameMode::TimeAttack => "time",
+            GameMode::Survival => "survival",
+            GameMode::Zen => "zen",
+            GameMode::Hunt => "hunt",
+            GameMode::Campaign(_) => "campaign",
+        }
+    }
+
+    // 排行榜文件，无尽模式沿用原来的文件
+    pub fn scores_file(&self) -> String {
+        match self {
+            GameMode::Endless => String::from("highscores.txt"),
+            _ => format!("highscores-{}.txt", self.name()),
+        }
+    }
+
+    // 开始界面上显示的名字，关卡的名字由关卡决定
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameMode::Endless => i18n::tr(Msg::Endless),
+            GameMode::TimeAttack => i18n::tr(Msg::TimeAttack),
+            GameMode::Survival => i18n::tr(Msg::Survival),
+            GameMode::Zen => i18n::tr(Msg::Zen),
+            GameMode::Hunt => i18n::tr(Msg::Hunt),
+            GameMode::Campaign(_) => "",
+        }
+    }
+
+    // 没有空位放食物时是否胜利
+    // 限时模式等时间到，生存模式一直玩到死
+    pub fn wins_when_full(&self) -> bool {
+        !matches!(self, GameMode::TimeAttack | GameMode::Survival)
+    }
+
+    // 禅模式不会死，往回走也只是不理会
+    pub fn can_die(&self) -> bool {
+        *self != GameMode::Zen
+    }
+
+    // 开始界面选项的序号：先是 MODES，后面是各个关卡
+    pub fn from_choice(idx: usize) -> GameMode {
+        match idx.checked_sub(MODES.len()) {
+            Some(stage) => GameMode::Campaign(stage),
+            None => MODES[idx],
+        }
+    }
+
+    pub fn choice(&self) -> usize {
+        match self {
+            GameMode::Campaign(stage) => MODES.len() + stage,
+            mode => MODES.iter().position(|m| m == mode).unwrap_or(0),
+        }
+    }
+}
+
+// 状态栏上的倒计时，录像里也记下来
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Timer {
+    TimeLeft(Duration), // 限时模式剩下的时间
+    Shrink(Duration),   // 生存模式下一次缩小的时间
+}
+
+impl Timer {
+    // 种类,毫秒，比如 time,42000
+    pub fn to_text(self) -> String {
+        match self {
+            Timer::TimeLeft(left) => format!("time,{}", left.as_millis()),
+            Timer::Shrink(left) => format!("shrink,{}", left.as_millis()),
+        }
+    }
+
+    pub fn from_text(text: &str) -> Option<Timer> {
+        let (kind, millis) = text.split_once(',')?;
+        let left = Duration::from_millis(millis.parse().ok()?);
+        match kind {
+            "time" => Some(Timer::TimeLeft(left)),
+            "shrink" => Some(Timer::Shrink(left)),
+            _ => None,
+        }
+    }
+
+    // 向上取整到秒，剩下不到一秒时还显示1秒
+    pub fn label(&self) -> String {
+        let ceil = |left: &Duration| Duration::from_secs(left.as_millis().div_ceil(1000) as u64);
+        match self {
+            Timer::TimeLeft(left) => i18n::trf(Msg::HudTimeLeft, &[&hud::format_time(ceil(left))]),
+            Timer::Shrink(left) => i18n::trf(Msg::HudShrink, &[&ceil(left).as_secs()]),
+        }
+    }
+}
+
+// 限时模式剩下的时间
+pub fn time_left(elapsed: Duration) -> Duration {
+    consts::TIME_ATTACK_DURATION.saturating_sub(elapsed)
+}
+
+// 从外往里第k圈的格子，k从0开始
+pub fn ring(board: &Board, k: i32) -> Vec<Point> {
+    let (cols, rows) = (board.cols(), board.rows());
+    let (left, top, right, bottom) = (k, k, cols - 1 - k, rows - 1 - k);
+    if left > right || top > bottom {
+        return vec![];
+    }
+    let cell = |col: i32, row: i32| Point::new(col * consts::BODY_SIZE, row * consts::BODY_SIZE);
+    let mut cells = vec![];
+    for col in left..=right {
+        cells.push(cell(col, top));
+        if bottom != top {
+            cells.push(cell(col, bottom));
+        }
+    }
+    for row in top + 1..bottom {
+        cells.push(cell(left, row));
+        if right != left {
+            cells.push(cell(right, row));
+        }
+    }
+    cells
+}
+
+// 生存模式的进度：按这局的时间出现障碍物、缩小棋盘
+// 时间不含暂停，只要和已经做了的比较就知道该做什么
+pub struct Survival {
+    spawned: u32, // 已经出现的障碍物数
+    rings: i32,   // 已经缩小的圈数
+}
+
+impl Survival {
+    pub fn new() -> Survival {
+        Survival {
+            spawned: 0,
+            rings: 0,
+        }
+    }
+
+    // 到elapsed为止还要出现几个障碍物
+    pub fn spawns_due(&mut self, elapsed: Duration) -> u32 {
+        let total = (elapsed.as_millis() / consts::SURVIVAL_SPAWN_INTERVAL.as_millis()) as u32;
+        let due = total.saturating_sub(self.spawned);
+        self.spawned = self.spawned.max(total);
+        due
+    }
+
+    // 最多缩小的圈数，中间至少留下 SURVIVAL_MIN_SIZE 格
+    fn max_rings(board: &Board) -> i32 {
+        ((board.cols().min(board.rows()) - consts::SURVIVAL_MIN_SIZE) / 2).max(0)
+    }
+
+    // 到elapsed为止新缩小的圈
+    pub fn rings_due(&mut self, elapsed: Duration, board: &Board) -> Vec<i32> {
+        let total = (elapsed.as_millis() / consts::SURVIVAL_SHRINK_INTERVAL.as_millis()) as i32;
+        let total = total.min(Survival::max_rings(board));
+        let rings = (self.rings..total).collect();
+        self.rings = self.rings.max(total);
+        rings
+    }
+
+    // 下一次缩小的倒计时，已经缩到最小时没有
+    pub fn timer(&self, elapsed: Duration, board: &Board) -> Option<Timer> {
+        if self.rings >= Survival::max_rings(board) {
+            return None;
+        }
+        let next = consts::SURVIVAL_SHRINK_INTERVAL * (self.rings + 1) as u32;
+        Some(Timer::Shrink(next.saturating_sub(elapsed)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ring() {
+        let board = Board::new(150, 120); // 5x4格
+        assert_eq!(ring(&board, 0).len(), 14);
+        assert_eq!(ring(&board, 1).len(), 6);
+        assert!(ring(&board, 1).contains(&Point::new(90, 60)));
+        assert!(ring(&board, 2).is_empty());
+        // 只有一格时不能重复
+        assert_eq!(ring(&Board::new(30, 30), 0), vec![Point::new(0, 0)]);
+    }
+
+    #[test]
+    fn test_survival() {
+        let board = Board::new(500, 500); // 16x16格
+        let mut survival = Survival::new();
+        let interval = consts::SURVIVAL_SPAWN_INTERVAL;
+        assert_eq!(survival.spawns_due(interval / 2), 0);
+        assert_eq!(survival.spawns_due(interval * 3), 3);
+        assert_eq!(survival.spawns_due(interval * 3), 0);
+
+        let shrink = consts::SURVIVAL_SHRINK_INTERVAL;
+        assert_eq!(
+            survival.timer(shrink / 4, &board),
+            Some(Timer::Shrink(shrink * 3 / 4))
+        );
+        assert!(survival.rings_due(shrink / 2, &board).is_empty());
+        assert_eq!(survival.rings_due(shrink * 2, &board), vec![0, 1]);
+        // 缩到最小以后不再缩小
+        let max = Survival::max_rings(&board);
+        assert_eq!(
+            survival.rings_due(shrink * 100, &board).len() as i32,
+            max - 2
+        );
+        assert_eq!(survival.timer(shrink * 100, &board), None);
+    }
+
+    #[test]
+    fn test_timer() {
+        let timer = Timer::TimeLeft(Duration::from_millis(41_200));
+        assert_eq!(Timer::from_text(&timer.to_text()), Some(timer));
+        assert_eq!(timer.label(), "剩余 00:42");
+        assert_eq!(Timer::from_text("shrink,x"), None);
+        assert_eq!(time_left(consts::TIME_ATTACK_DURATION * 2), Duration::ZERO);
+        assert_eq!(GameMode::Endless.scores_file(), "highscores.txt");
+        assert!(!GameMode::Survival.wins_when_full());
+        assert!(!GameMode::Zen.can_die());
+    }
+
+    #[test]
+    fn test_choice() {
+        for (idx, mode) in MODES.iter().enumerate() {
+            assert_eq!(GameMode::from_choice(idx), *mode);
+            assert_eq!(mode.choice(), idx);
+        }
+        assert_eq!(GameMode::from_choice(MODES.len()), GameMode::Campaign(0));
+        assert_eq!(GameMode::Campaign(2).choice(), MODES.len() + 2);
+    }
+}
@@ -0,0 +1,151 @@
+use std::time::{Duration, Instant};
+
+use crate::{consts, snake::Point};
+
+// 移动计时：逻辑上按格移动，渲染时根据进度在两格之间插值
+#[derive(Clone, Debug)]
+pub struct StepClock {
+    last_step: Instant,
+}
+
+impl StepClock {
+    pub fn new(now: Instant) -> StepClock {
+        StepClock { last_step: now }
+    }
+
+    // 距离上次移动超过interval秒，需要移动一格时返回true
+    // 落后超过一格时不追赶，避免卡顿后连续移动好几格
+    pub fn step(&mut self, now: Instant, interval: f64) -> bool {
+        let interval = Duration::from_secs_f64(interval);
+        if now.saturating_duration_since(self.last_step) < interval {
+            return false;
+        }
+        self.last_step += interval;
+        if now.saturating_duration_since(self.last_step) >= interval {
+            self.last_step = now;
+        }
+        true
+    }
+
+    // 这一格走了多少，0~1
+    pub fn progress(&self, now: Instant, interval: f64) -> f64 {
+        let elapsed = now.saturating_duration_since(self.last_step).as_secs_f64();
+        (elapsed / interval).clamp(0.0, 1.0)
+    }
+
+    // 暂停期间不计时
+    pub fn postpone(&mut self, duration: Duration) {
+        self.last_step += duration;
+    }
+}
+
+// from到to之间插值，t在0~1之间
+// 两格不相邻（穿墙）时不插值，直接在to
+pub fn lerp_point(from: &Point, to: &Point, t: f64) -> Point {
+    let (dx, dy) = (to.x() - from.x(), to.y() - from.y());
+    if dx.abs() + dy.abs() > consts::BODY_SIZE {
+        return to.clone();
+    }
+    Point::new(
+        from.x() + (dx as f64 * t).round() as i32,
+        from.y() + (dy as f64 * t).round() as i32,
+    )
+}
+
+// from走到to时经过的传送门，入口和from相邻，出口和to相邻
+fn portal_between<'a>(
+    from: &Point,
+    to: &Point,
+    portals: &'a [(Point, Point)],
+) -> Option<(&'a Point, &'a Point)> {
+    let is_next =
+        |a: &Point, b: &Point| (a.x() - b.x()).abs() + (a.y() - b.y()).abs() == consts::BODY_SIZE;
+    portals
+        .iter()
+        .flat_map(|(a, b)| [(a, b), (b, a)])
+        .find(|(entry, exit)| is_next(from, entry) && is_next(exit, to))
+}
+
+// 渲染用的节点和位置
+// 上一次移动丢掉的尾节点也画出来，从那里收回到现在的尾巴，蛇头从上一格滑到现在的位置
+// 经过传送门时蛇头从出口滑出来，尾巴收进入口
+// 返回的节点用来选择图块和颜色，位置用来绘制
+pub fn render_segments(
+    points: &[Point],
+    last_tail: &Point,
+    t: f64,
+    portals: &[(Point, Point)],
+) -> (Vec<Point>, Vec<Point>) {
+    let mut segments = points.to_vec();
+    if points.last() != Some(last_tail) {
+        segments.push(last_tail.clone());
+    }
+    let mut positions = segments.clone();
+    if let Some(prev) = segments.get(1) {
+        let from = portal_between(prev, &segments[0], portals).map_or(prev, |(_, exit)| exit);
+        positions[0] = lerp_point(from, &segments[0], t);
+    }
+    if segments.len() > points.len() {
+        let tail = positions.len() - 1;
+        let to = &points[points.len() - 1];
+        let to = portal_between(last_tail, to, portals).map_or(to, |(entry, _)| entry);
+        positions[tail] = lerp_point(last_tail, to, t);
+    }
+    (segments, positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step() {
+        let start = Instant::now();
+        let mut clock = StepClock::new(start);
+        assert!(!clock.step(start + Duration::from_millis(100), 0.2));
+        assert_eq!(clock.progress(start + Duration::from_millis(100), 0.2), 0.5);
+        assert!(clock.step(start + Duration::from_millis(250), 0.2));
+        assert!(!clock.step(start + Duration::from_millis(300), 0.2));
+
+        // 落后太多不追赶
+        assert!(clock.step(start + Duration::from_secs(2), 0.2));
+        assert!(!clock.step(start + Duration::from_secs(2), 0.2));
+    }
+
+    #[test]
+    fn test_lerp_point() {
+        let (from, to) = (Point::new(0, 0), Point::new(30, 0));
+        assert_eq!(lerp_point(&from, &to, 0.5), Point::new(15, 0));
+        assert_eq!(lerp_point(&from, &to, 1.0), to);
+
+        // 穿墙
+        let from = Point::new(450, 0);
+        assert_eq!(lerp_point(&from, &to, 0.5), to);
+    }
+
+    #[test]
+    fn test_render_segments() {
+        // 向右移动了一格，尾巴在(0,0)
+        let points = vec![Point::new(60, 0), Point::new(30, 0)];
+        let (segments, positions) = render_segments(&points, &Point::new(0, 0), 0.5, &[]);
+        assert_eq!(segments.len(), 3);
+        assert_eq!(
+            positions,
+            vec![Point::new(45, 0), Point::new(30, 0), Point::new(15, 0)]
+        );
+
+        // 刚长了一节，没有需要收回的尾巴
+        let (segments, _) = render_segments(&points, &Point::new(30, 0), 0.5, &[]);
+        assert_eq!(segments, points);
+
+        // 向右走进(90,0)的传送门，从(0,90)出来到(30,90)，尾巴还在入口前面
+        let portals = [(Point::new(90, 0), Point::new(0, 90))];
+        let points = vec![Point::new(30, 90), Point::new(60, 0)];
+        let (_, positions) = render_segments(&points, &Point::new(30, 0), 0.5, &portals);
+        assert_eq!(positions[0], Point::new(15, 90));
+        // 尾巴走进入口
+        let points = vec![Point::new(60, 90), Point::new(30, 90)];
+        let (_, positions) = render_segments(&points, &Point::new(60, 0), 0.5, &portals);
+        assert_eq!(positions[2], Point::new(75, 0));
+    }
+}
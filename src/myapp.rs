@@ -1,24 +1,106 @@
-use std::{cell::RefCell, cmp::max, collections::HashSet, rc::Rc};
+use std::{
+    cell::RefCell,
+    cmp::max,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::mpsc::Receiver,
+    time::{Duration, Instant},
+};
 
 use fltk::{enums::*, prelude::*, window::DoubleWindow, *};
 
 use crate::{
+    audio::{Audio, Sound},
+    board::Board,
+    campaign::{Progress, Rules, Stage, StageStatus},
+    canvas::{self, Bitmap, FltkCanvas},
+    config::Config,
     consts,
-    food::Food,
-    snake::{self, Direction, Point},
+    damage::{self, DamageTracker},
+    difficulty::{SpeedSetting, DIFFICULTIES, SPEED_CURVES},
+    effect::{ActiveEffects, EffectKind},
+    food::{self, Food, FoodKind, FOOD_KINDS},
+    gamepad::{self, PadInput},
+    gesture::{self, Gesture},
+    highscore::{HighScores, ScoreRecord},
+    hud,
+    i18n::{self, Locale, Msg},
+    keymap::{self, Action, KeyMap, Slot},
+    mode::{self, GameMode, Survival, Timer, MODES},
+    motion::StepClock,
+    pause::{MenuAction, PauseMenu},
+    raster::RgbaCanvas,
+    record::{self, Capture, Recorder},
+    render::{self, Frame},
+    rival::{self, Behaviour, Rival},
+    scene::{Replay, RivalScene, Scene},
+    settings::Settings,
+    snake::{self, Point, Snake},
+    state::{GameState, StateMachine, Transition},
+    theme::{FoodSprite, Theme},
+    tileset::{self, Tile, Tileset},
     utils,
 };
+
+// 主题里的食物图片，按路径缓存，读取失败的记为None不再重试
+type ImageCache = Rc<RefCell<HashMap<PathBuf, Option<Bitmap>>>>;
+
+// 当前主题的图块集切好的图块，切换主题时重新读取
+struct TileCache {
+    theme: Option<usize>,
+    tiles: HashMap<Tile, Bitmap>,
+}
+
+// 开始界面的控件，返回标题时重新显示
+struct TitleScreen {
+    group: group::Group,
+    title: button::Button,
+    start_button: button::Button,
+    difficulty_choice: menu::Choice,
+    curve_choice: menu::Choice,
+    mode_choice: menu::Choice,
+}
+
 pub struct MyApp {
     _app: app::App,
     _snake: Rc<RefCell<snake::Snake>>, // 多所有者
     _window: DoubleWindow,
-    _food: Food,
+    _board: Board,     // 逻辑棋盘，窗口缩放时不变
+    _foods: Vec<Food>, // 场上的食物，可以同时存在多个
+    _score: i32,
+    _effects: ActiveEffects,          // 生效中的道具效果
+    _eaten: u32,                      // 吃到的食物数，决定速度
+    _ticks: u32,                      // 这一关走了几格，猎物按这个逃跑
+    _clock: StepClock,                // 移动计时，渲染时插值用
+    _started_at: Instant,             // 这局开始的时间，不含暂停
+    _settings: Rc<RefCell<Settings>>, // 开始界面和暂停菜单里的设置
+    _themes: Vec<Rc<Theme>>,          // 内置主题和用户主题
+    _images: ImageCache,
+    _tiles: Rc<RefCell<TileCache>>,
+    // 每种模式一个排行榜
+    _high_scores: HashMap<&'static str, HighScores>,
+    _stages: Vec<Stage>,           // 内置关卡和用户关卡
+    _progress: Progress,           // 解锁到第几关
+    _stage_score: i32,             // 这一关开始时的得分
+    _speed: SpeedSetting,          // 这局开始时设置里的速度，中途在暂停菜单里改了也不变
+    _walls: Vec<Point>,            // 关卡地图里的障碍物，这一关里不变
+    _obstacles: Vec<Point>,        // 生存模式里不断增加的障碍物
+    _portals: Vec<(Point, Point)>, // 关卡的传送门，上面不放东西
+    _survival: Survival,           // 生存模式出现障碍物和缩小棋盘的进度
+    _rivals: Vec<Rival>,           // 电脑控制的蛇，和玩家抢食物
+    _title: Option<TitleScreen>,
+    _frame: Rc<RefCell<Option<Frame>>>, // 当前帧，和绘制回调共享
+    _damage: DamageTracker,             // 和上一帧比较，只重画变化的区域
+    _capture: Rc<RefCell<Option<Capture>>>, // 截图、录制的快捷键
+    _recorder: Option<Recorder>,        // 录制中的gif和录像
+    _record_path: Option<PathBuf>,      // 命令行指定的录制文件，开始游戏时录制
 
     // state
-    _is_display: Rc<RefCell<bool>>,
-    _is_init: bool,
-    _is_game_over: Rc<RefCell<bool>>,
-    _is_win: bool,
+    _state: Rc<RefCell<StateMachine>>,
+    _pause: Rc<RefCell<PauseMenu>>, // 暂停菜单，Paused 状态时显示
+    _gamepad: Receiver<PadInput>,   // 手柄线程发来的操作
+    _audio: Audio,                  // 音效和背景音乐
 }
 
 impl MyApp {
@@ -27,475 +109,1746 @@ impl MyApp {
         let a = app::App::default().with_scheme(app::Scheme::Gleam);
 
         // 渲染窗口
-        let wind = MyApp::new_window(x, y, w, h);
+        // 状态栏在棋盘上方，窗口加上状态栏的高度
+        let wind = MyApp::new_window(x, y, w, h + consts::HUD_HEIGHT);
+
+        // 逻辑棋盘大小和初始窗口一样
+        let board = Board::new(w, h);
 
         // init snake/根据consts::BODY_SIZE 分为相应的份数
-        let max_x = (w - consts::BODY_SIZE) / consts::BODY_SIZE;
-        let max_y = (h - consts::BODY_SIZE) / consts::BODY_SIZE;
-        let rand_x: i32 = utils::rand_range(0, max_x) * consts::BODY_SIZE;
-        let rand_y = utils::rand_range(0, max_y) * consts::BODY_SIZE;
-        let _snake = snake::Snake::new(rand_x, rand_y, wind.clone()); // 初始化snake
+        let rand_x: i32 = utils::rand_range(0, board.cols()) * consts::BODY_SIZE;
+        let rand_y = utils::rand_range(0, board.rows()) * consts::BODY_SIZE;
+        let _snake = snake::Snake::new(rand_x, rand_y, board); // 初始化snake
+
+        // 设置文件里的语言优先于环境变量，要在创建内置主题之前
+        let rivals = MyApp::load_settings();
+
+        // 内置主题加上用户目录下的主题文件
+        let mut themes = Theme::builtin();
+        themes.extend(Theme::load_dir(&utils::data_dir().join("themes")));
+        let theme_names = themes.iter().map(|t| t.name.clone()).collect();
+        let mut settings = Settings::new(theme_names);
+        settings.rivals = rivals;
+        // 按键设置，没有文件时使用默认按键
+        let keys_path = MyApp::keys_path();
+        if keys_path.exists() {
+            match KeyMap::load(&keys_path) {
+                Ok(keys) => settings.keys = keys,
+                Err(e) => println!("{}", i18n::trf(Msg::LoadKeysFailed, &[&e])),
+            }
+        }
+
+        // 每种模式的排行榜，所有关卡共用一个
+        let high_scores = MODES
+            .iter()
+            .chain([GameMode::Campaign(0)].iter())
+            .map(|mode| {
+                let path = utils::data_dir().join(mode.scores_file());
+                (mode.name(), HighScores::load(path))
+            })
+            .collect();
+
+        // 内置关卡加上用户目录下的关卡文件
+        let mut stages = Stage::builtin();
+        stages.extend(Stage::load_dir(&utils::data_dir().join("stages")));
 
         MyApp {
             _app: a,
             _snake: Rc::new(RefCell::new(_snake)),
             _window: wind,
-            _food: Food::new(0, 0),
-            _is_display: Rc::new(RefCell::new(false)),
-            _is_init: false,
-            _is_game_over: Rc::new(RefCell::new(false)),
-            _is_win: false,
+            _board: board,
+            _foods: vec![],
+            _score: 0,
+            _effects: ActiveEffects::new(),
+            _eaten: 0,
+            _ticks: 0,
+            _clock: StepClock::new(Instant::now()),
+            _started_at: Instant::now(),
+            _settings: Rc::new(RefCell::new(settings)),
+            _themes: themes.into_iter().map(Rc::new).collect(),
+            _images: Rc::new(RefCell::new(HashMap::new())),
+            _tiles: Rc::new(RefCell::new(TileCache {
+                theme: None,
+                tiles: HashMap::new(),
+            })),
+            _high_scores: high_scores,
+            _stages: stages,
+            _progress: Progress::load(utils::data_dir().join("campaign.conf")),
+            _stage_score: 0,
+            _speed: SpeedSetting::default(),
+            _walls: vec![],
+            _obstacles: vec![],
+            _portals: vec![],
+            _survival: Survival::new(),
+            _rivals: vec![],
+            _title: None,
+            _frame: Rc::new(RefCell::new(None)),
+            _damage: DamageTracker::new(),
+            _capture: Rc::new(RefCell::new(None)),
+            _recorder: None,
+            _record_path: None,
+            _state: Rc::new(RefCell::new(StateMachine::new())),
+            _pause: Rc::new(RefCell::new(PauseMenu::new())),
+            _gamepad: gamepad::spawn(app::awake),
+            _audio: Audio::muted(),
         }
     }
 
+    // 主循环，根据当前状态做不同的事，退出游戏或者窗口被关闭时返回
     pub fn run(&mut self) {
-        if !self._is_init {
-            self.draw_window(); // 开机动画
-            self.watch_key(); // 监听key
-            self._is_init = true;
+        self.draw_window(); // 开机动画
+        self.watch_key(); // 监听key
+
+        loop {
+            // 窗口被关闭
+            if !self._window.shown() {
+                self.stop_recording();
+                return;
+            }
+
+            self.poll_gamepad();
+
+            // 暂停菜单的选择转换成状态转换
+            if self.state() == GameState::Paused {
+                let action = self._pause.borrow_mut().take_action();
+                let transition = match action {
+                    Some(MenuAction::Resume) => Some(Transition::Resume),
+                    Some(MenuAction::Restart) => Some(Transition::Restart),
+                    Some(MenuAction::Title) => Some(Transition::ToTitle),
+                    Some(MenuAction::Quit) => Some(Transition::Quit),
+                    Some(MenuAction::SaveKeys) => {
+                        self.save_keys();
+                        None
+                    }
+                    Some(MenuAction::SaveSettings) => {
+                        self.apply_locale();
+                        self.save_settings();
+                        None
+                    }
+                    None => None,
+                };
+                if let Some(transition) = transition {
+                    self._state.borrow_mut().request(transition);
+                }
+            }
+
+            // 处理按键、按钮和菜单发起的状态转换
+            let pending = self._state.borrow_mut().take_pending();
+            if let Some(transition) = pending {
+                if transition == Transition::Quit {
+                    self.stop_recording();
+                    return;
+                }
+                self.apply(transition);
+            }
+
+            match self.state() {
+                GameState::Playing => {
+                    self.tick();
+                    self.capture();
+                    app::sleep(1.0 / consts::RENDER_FPS as f64);
+                }
+                // 暂停中，游戏状态保持不变，只绘制菜单
+                GameState::Paused => {
+                    self.draw();
+                    self.capture();
+                    app::sleep(1.0 / consts::RENDER_FPS as f64);
+                }
+                GameState::Title | GameState::GameOver | GameState::Victory => {
+                    // 交出一点时间片。不然要卡死
+                    app::wait();
+                }
+            }
         }
+    }
 
-        // 初始化food
-        self.init_food();
+    // 手柄的操作，和按键一样处理
+    fn poll_gamepad(&mut self) {
+        let inputs: Vec<PadInput> = self._gamepad.try_iter().collect();
+        for input in inputs {
+            let transition = match (self.state(), input) {
+                (GameState::Playing, PadInput::Turn(direction)) => {
+                    match self._snake.borrow_mut().set_direction(direction) {
+                        Err(_) if self.mode().can_die() => Some(Transition::Die),
+                        _ => None,
+                    }
+                }
+                (GameState::Playing, PadInput::Start) => Some(Transition::Pause),
+                // 等待按键时只能用键盘绑定
+                (GameState::Paused, _) if self._pause.borrow().is_capturing() => None,
+                (GameState::Paused, input) => {
+                    let mut menu = self._pause.borrow_mut();
+                    let mut settings = self._settings.borrow_mut();
+                    match input {
+                        PadInput::Turn(snake::Direction::Up) => menu.select_prev(),
+                        PadInput::Turn(snake::Direction::Down) => menu.select_next(),
+                        PadInput::Turn(snake::Direction::Left) => menu.change(&mut settings, -1),
+                        PadInput::Turn(snake::Direction::Right) => menu.change(&mut settings, 1),
+                        PadInput::Confirm => menu.activate(&mut settings),
+                        PadInput::Back => menu.back(),
+                        PadInput::Start => menu.resume(),
+                    }
+                    None
+                }
+                (GameState::Title, PadInput::Start | PadInput::Confirm) => Some(Transition::Start),
+                (GameState::GameOver | GameState::Victory, PadInput::Start | PadInput::Confirm) => {
+                    Some(Transition::Start)
+                }
+                (GameState::GameOver | GameState::Victory, PadInput::Back) => {
+                    Some(Transition::ToTitle)
+                }
+                _ => None,
+            };
+            if let Some(transition) = transition {
+                self._state.borrow_mut().request(transition);
+            }
+        }
+    }
 
-        // 主循环
-        loop {
-            if *(*self._is_display).borrow() {
-                // win
-                if self._is_win {
-                    self.game_win();
-                    break;
+    fn keys_path() -> PathBuf {
+        utils::data_dir().join("keys.conf")
+    }
+
+    // 保存暂停菜单里修改的按键
+    fn save_keys(&self) {
+        if let Err(e) = self._settings.borrow().keys.save(&MyApp::keys_path()) {
+            println!("{}", i18n::trf(Msg::SaveKeysFailed, &[&e]));
+        }
+    }
+
+    fn settings_path() -> PathBuf {
+        utils::data_dir().join("settings.conf")
+    }
+
+    // 读取设置文件，切换到里面的语言，返回里面的对手
+    fn load_settings() -> Vec<Behaviour> {
+        let settings_path = MyApp::settings_path();
+        if !settings_path.exists() {
+            return vec![];
+        }
+        match Config::load(&settings_path) {
+            Ok(config) => {
+                if let Some(locale) = config.get("lang").and_then(Locale::from_name) {
+                    i18n::set_locale(locale);
+                }
+                // 对手写错时没有对手
+                match config.get("rivals").map(rival::parse_rivals) {
+                    Some(Ok(behaviours)) => return behaviours,
+                    Some(Err(e)) => println!("{}", i18n::trf(Msg::LoadSettingsFailed, &[&e])),
+                    None => {}
                 }
+            }
+            Err(e) => println!("{}", i18n::trf(Msg::LoadSettingsFailed, &[&e])),
+        }
+        vec![]
+    }
+
+    // 切换语言，内置主题和内置关卡的名字跟着换
+    fn apply_locale(&mut self) {
+        i18n::set_locale(self._settings.borrow().locale);
+        let builtin = Theme::builtin();
+        self._settings.borrow_mut().theme_names = self
+            ._themes
+            .iter()
+            .enumerate()
+            .map(|(idx, theme)| {
+                builtin
+                    .get(idx)
+                    .map_or(theme.name.clone(), |t| t.name.clone())
+            })
+            .collect();
+        for (stage, builtin) in self._stages.iter_mut().zip(Stage::builtin()) {
+            stage.name = builtin.name;
+        }
+    }
+
+    // 保存暂停菜单里修改的语言和对手
+    fn save_settings(&self) {
+        let settings = self._settings.borrow();
+        let mut config = Config::new();
+        config.set("lang", settings.locale.name());
+        config.set("rivals", &rival::rivals_text(&settings.rivals));
+        if let Err(e) = config.save(&MyApp::settings_path()) {
+            println!("{}", i18n::trf(Msg::SaveSettingsFailed, &[&e]));
+        }
+    }
+
+    fn state(&self) -> GameState {
+        self._state.borrow().state()
+    }
+
+    // 当前主题
+    fn theme(&self) -> Rc<Theme> {
+        let idx = self._settings.borrow().theme;
+        Rc::clone(self._themes.get(idx).unwrap_or(&self._themes[0]))
+    }
+
+    // 读取当前主题用到的食物图片
+    fn load_theme_images(&mut self) {
+        MyApp::load_images(&self.theme(), &mut self._images.borrow_mut());
+    }
+
+    fn load_images(theme: &Theme, images: &mut HashMap<PathBuf, Option<Bitmap>>) {
+        for kind in FOOD_KINDS.iter() {
+            if let FoodSprite::Image(path) = theme.food_sprite(*kind) {
+                images.entry(path).or_insert_with_key(|path| {
+                    Bitmap::load(path)
+                        .map_err(|e| {
+                            println!(
+                                "{}",
+                                i18n::trf(Msg::LoadImageFailed, &[&path.display(), &e])
+                            )
+                        })
+                        .ok()
+                });
+            }
+        }
+    }
+
+    // 执行状态转换，并做进入新状态需要做的事
+    fn apply(&mut self, transition: Transition) {
+        if self._state.borrow_mut().apply(transition).is_none() {
+            return;
+        }
+        // 一局结束，停止录制
+        if !matches!(transition, Transition::Pause | Transition::Resume) {
+            self.stop_recording();
+        }
+        match transition {
+            Transition::Start | Transition::Restart => self.new_game(),
+            Transition::Pause => *self._pause.borrow_mut() = PauseMenu::new(),
+            Transition::Resume => self.resume(),
+            Transition::Die => self.game_over(),
+            Transition::Win => self.game_win(),
+            Transition::ToTitle => self.show_title(),
+            Transition::Quit => {}
+        }
+        // 只在游戏中播放背景音乐
+        self._audio.set_music(self.state() == GameState::Playing);
+    }
+
+    // 读取当前主题的图块集，读取失败时使用图形绘制
+    fn load_tiles(&mut self) {
+        let theme_idx = self._settings.borrow().theme;
+        let mut cache = self._tiles.borrow_mut();
+        if cache.theme == Some(theme_idx) {
+            return;
+        }
+        cache.theme = Some(theme_idx);
+        cache.tiles.clear();
+        if let Some(path) = &self.theme().tileset {
+            match MyApp::cut_tiles(path) {
+                Ok(tiles) => cache.tiles = tiles,
+                Err(e) => println!("{}", i18n::trf(Msg::LoadTilesetFailed, &[&e])),
+            }
+        }
+    }
+
+    // 把图块集图片切成一个个图块
+    fn cut_tiles(path: &Path) -> Result<HashMap<Tile, Bitmap>, String> {
+        let tileset = Tileset::load(path)?;
+        let png = image::PngImage::load(&tileset.image)
+            .map_err(|e| format!("{}: {}", tileset.image.display(), e))?;
+        let data = png.to_rgb_data();
+        let depth = png.depth();
+
+        let mut tiles = HashMap::new();
+        for (tile, (x, y)) in tileset.tiles() {
+            let pixels = tileset::crop(&data, png.data_w(), depth as usize, x, y, tileset.size);
+            if pixels.is_empty() {
+                return Err(i18n::trf(Msg::TileOutOfImage, &[&format!("{:?}", tile)]));
+            }
+            let image = Bitmap::new(&pixels, tileset.size, tileset.size, depth as usize)
+                .ok_or_else(|| i18n::trf(Msg::UnsupportedImageAt, &[&tileset.image.display()]))?;
+            tiles.insert(tile.clone(), image);
+        }
+        Ok(tiles)
+    }
+
+    // 开始新的一局
+    fn new_game(&mut self) {
+        if let Some(title) = self._title.as_mut() {
+            // 子控件跟着group一起隐藏
+            title.group.hide();
+        }
+        self._window.set_border(true);
+        self._window
+            .set_color(Color::from_hex(self.theme().background));
+
+        self._score = 0;
+        self._started_at = Instant::now();
+        self._survival = Survival::new();
+        self._speed = self._settings.borrow().speed;
+        self.start_stage();
+        // 结束界面和开始界面换掉了绘制回调
+        self.install_frame_draw();
+
+        if let Some(path) = self._record_path.take() {
+            self.start_recording(path);
+        }
+    }
 
-                // 其他地方的game_over
-                if *(*self._is_game_over).borrow() || self.is_eat_own() {
-                    self.game_over();
+    // 布置这一关：障碍物、传送门和蛇的起点，食物、道具和速度从头算
+    // 无尽模式没有障碍物，蛇随机出现
+    fn start_stage(&mut self) {
+        let stage = self.stage().cloned();
+        self._walls = stage
+            .as_ref()
+            .map_or(vec![], |stage| stage.walls_in(&self._board));
+        self._obstacles.clear();
+        self._portals = stage
+            .as_ref()
+            .map_or(vec![], |stage| stage.portals_in(&self._board));
+        let start = stage
+            .as_ref()
+            .and_then(|stage| stage.start.clone())
+            .filter(|start| {
+                self._board.contains(start.x(), start.y())
+                    && !self.is_obstacle(start)
+                    && !self.is_portal(start)
+            });
+        match (start, stage) {
+            (Some(start), Some(stage)) => self._snake.borrow_mut().reset_at(start, stage.direction),
+            _ => loop {
+                // 清除上一局的数据，不能出现在障碍物和传送门上
+                self._snake.borrow_mut().clear();
+                let head = self._snake.borrow().get_occupied_points()[0].clone();
+                if !self.is_obstacle(&head) && !self.is_portal(&head) {
                     break;
                 }
+            },
+        }
+        self._snake.borrow_mut().set_portals(self._portals.clone());
 
-                let min_duration: f64 = 0.01;
-                let duration =
-                    min_duration.max(0.21 - self._snake.borrow_mut().len() as f64 * 0.005); // sleep 时间决定了speed，长度越长，speed越快
-                app::sleep(duration);
-                let result = self
-                    ._snake
-                    .borrow_mut()
-                    .move_direction(consts::MOVE_STEP, false /*is_direction*/);
-
-                if let Err(_) = result {
-                    self.game_over();
-                    break;
+        self._foods.clear();
+        self._effects.clear();
+        // 对手按设置重新出现，离玩家远一点
+        let behaviours = self._settings.borrow().rivals.clone();
+        self._rivals.clear();
+        for (idx, behaviour) in behaviours.into_iter().enumerate() {
+            if let Some(point) = self.free_cell(consts::RIVAL_SPAWN_DISTANCE) {
+                let mut snake = Snake::new(point.x(), point.y(), self._board);
+                snake.set_portals(self._portals.clone());
+                self._rivals
+                    .push(Rival::new(snake, behaviour, rival::color(idx)));
+            }
+        }
+        self._eaten = 0;
+        self._ticks = 0;
+        self._stage_score = self._score;
+        self._clock = StepClock::new(Instant::now());
+        self.init_food();
+    }
+
+    fn mode(&self) -> GameMode {
+        self._settings.borrow().mode
+    }
+
+    // 闯关模式正在玩的关卡
+    fn stage(&self) -> Option<&Stage> {
+        match self.mode() {
+            GameMode::Campaign(idx) => self._stages.get(idx),
+            _ => None,
+        }
+    }
+
+    // 当前关卡的规则，其他模式使用默认规则
+    // 禅模式撞墙从对面出来，狩猎模式都是猎物
+    fn rules(&self) -> Rules {
+        match (self.stage(), self.mode()) {
+            (Some(stage), _) => stage.rules,
+            (None, GameMode::Zen) => Rules {
+                wrap: true,
+                ..Rules::default()
+            },
+            (None, GameMode::Hunt) => Rules {
+                food_count: consts::PREY_COUNT,
+                prey: true,
+                ..Rules::default()
+            },
+            _ => Rules::default(),
+        }
+    }
+
+    // 闯关模式使用关卡的速度，其他模式使用这局开始时设置里的
+    // 移动间隔、排行榜和录像都用这个，中途改难度等到下一局生效
+    fn speed(&self) -> SpeedSetting {
+        self.stage().map_or(self._speed, |stage| stage.speed)
+    }
+
+    // 当前关卡的进度
+    fn stage_status(&self) -> Option<StageStatus> {
+        let stage = match self.mode() {
+            GameMode::Campaign(idx) => idx,
+            _ => return None,
+        };
+        let goal = self._stages.get(stage)?.goal;
+        let length = self._snake.borrow().len();
+        Some(StageStatus {
+            stage,
+            goal,
+            progress: goal.progress(length, self._score - self._stage_score),
+        })
+    }
+
+    // 达到目标：解锁并进入下一关，最后一关通过就是胜利
+    fn next_stage(&mut self) {
+        let idx = match self.mode() {
+            GameMode::Campaign(idx) => idx,
+            _ => return,
+        };
+        if self._progress.clear(idx) {
+            if let Err(e) = self._progress.save() {
+                println!("{}", i18n::trf(Msg::SaveProgressFailed, &[&e]));
+            }
+        }
+        if idx + 1 >= self._stages.len() {
+            self.apply(Transition::Win);
+            return;
+        }
+        self._settings.borrow_mut().mode = GameMode::Campaign(idx + 1);
+        self._audio.play(Sound::Victory);
+        self.start_stage();
+    }
+
+    // 这局开始后的时间，不含暂停
+    fn elapsed(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self._started_at)
+    }
+
+    // 状态栏的倒计时
+    fn timer(&self, now: Instant) -> Option<Timer> {
+        let elapsed = self.elapsed(now);
+        match self.mode() {
+            GameMode::TimeAttack => Some(Timer::TimeLeft(mode::time_left(elapsed))),
+            GameMode::Survival => self._survival.timer(elapsed, &self._board),
+            _ => None,
+        }
+    }
+
+    // 生存模式：到时间出现新的障碍物，缩小的一圈全部变成障碍物
+    // 被障碍物盖住的食物换个地方
+    fn survive(&mut self, now: Instant) {
+        let elapsed = self.elapsed(now);
+        for _ in 0..self._survival.spawns_due(elapsed) {
+            self.spawn_obstacle();
+        }
+        for ring in self._survival.rings_due(elapsed, &self._board) {
+            for cell in mode::ring(&self._board, ring) {
+                if !self.is_obstacle(&cell) {
+                    self._obstacles.push(cell);
+                }
+            }
+        }
+        let obstacles = &self._obstacles;
+        let food_count = self._foods.len();
+        self._foods
+            .retain(|food| !obstacles.contains(&Point::new(food.x(), food.y())));
+        if self._foods.len() != food_count {
+            self.init_food();
+        }
+    }
+
+    // 在离蛇头远一点的空地上放一个障碍物，没有空地就不放
+    fn spawn_obstacle(&mut self) {
+        if let Some(point) = self.free_cell(consts::SURVIVAL_SPAWN_DISTANCE) {
+            self._obstacles.push(point);
+        }
+    }
+
+    // 所有蛇占的格子，玩家的蛇在前面，死了的对手不算
+    fn snake_points(&self) -> Vec<Point> {
+        let mut points = self._snake.borrow().get_occupied_points().clone();
+        for rival in self._rivals.iter().filter(|rival| rival.is_alive()) {
+            points.extend(rival.snake.get_occupied_points().iter().cloned());
+        }
+        points
+    }
+
+    // 离玩家蛇头至少distance格的随机空地，没有蛇、障碍物和食物
+    fn free_cell(&self, distance: i32) -> Option<Point> {
+        let points = self.snake_points();
+        let head = &points[0];
+        let free = self._board.free_cells(|p| {
+            ((p.x() - head.x()).abs() + (p.y() - head.y()).abs()) / consts::BODY_SIZE < distance
+                || points.contains(p)
+                || self.is_obstacle(p)
+                || self.is_portal(p)
+                || self
+                    ._foods
+                    .iter()
+                    .any(|food| food.x() == p.x() && food.y() == p.y())
+        });
+        if free.is_empty() {
+            return None;
+        }
+        Some(free[utils::rand_range(0, free.len())].clone())
+    }
+
+    // 移动一格的时间，由难度、速度曲线和道具效果决定
+    fn interval(&self) -> f64 {
+        self.speed().interval(self._eaten) * self._effects.speed_factor()
+    }
+
+    // 游戏中的一帧：到时间了就移动一格，每帧都绘制
+    fn tick(&mut self) {
+        // 限时模式时间到了就结束
+        if self.mode() == GameMode::TimeAttack
+            && mode::time_left(self.elapsed(Instant::now())).is_zero()
+        {
+            self.apply(Transition::Win);
+            return;
+        }
+        if self._clock.step(Instant::now(), self.interval()) {
+            self.step();
+            let scene = self.scene(Instant::now());
+            if let Some(recorder) = self._recorder.as_mut() {
+                recorder.add_scene(scene);
+            }
+            if self.state() != GameState::Playing {
+                return;
+            }
+        }
+        self.draw();
+    }
+
+    // 移动一格：移动、吃食物、判定输赢
+    fn step(&mut self) {
+        // 幽灵可以穿过自己，禅模式断掉咬到的那节和后面的尾巴，护盾抵挡一次
+        if let Some(idx) = self.bitten_segment() {
+            if self._effects.is_active(EffectKind::Ghost) {
+                // 穿过去，什么都不做
+            } else if !self.mode().can_die() {
+                // 少了的长度要扣分
+                let lost = self._snake.borrow_mut().cut_at(idx);
+                self._score = (self._score - lost * consts::ZEN_CUT_PENALTY).max(0);
+                self._audio.play(Sound::Death);
+            } else if !self._effects.consume(EffectKind::Shield) {
+                self.apply(Transition::Die);
+                return;
+            }
+        }
+
+        // 道具效果计时
+        let now = Instant::now();
+        self._effects.tick(now);
+
+        // 生存模式按时间出现障碍物、缩小棋盘
+        if self.mode() == GameMode::Survival {
+            self.survive(now);
+        }
+
+        // 过期的食物消失，补充新的食物
+        let food_count = self._foods.len();
+        self._foods.retain(|food| !food.is_expired(now));
+        if self._foods.len() != food_count && !self.init_food() && self.mode().wins_when_full() {
+            self.apply(Transition::Win);
+            return;
+        }
+
+        self.move_prey();
+        self.move_rivals();
+
+        let direction = self._snake.borrow().get_direction().clone();
+        let result = self._snake.borrow_mut().move_direction();
+        if *self._snake.borrow().get_direction() != direction {
+            self._audio.play(Sound::Turn);
+        }
+
+        if let Err(_) = result {
+            // 护盾抵挡撞墙，从对面出来，有的关卡本来就可以穿过边界
+            if !self.rules().wrap && !self._effects.consume(EffectKind::Shield) {
+                self.apply(Transition::Die);
+                return;
+            }
+            self._snake.borrow_mut().move_through_wall();
+        }
+
+        // 撞到障碍物，护盾抵挡一次
+        let head = self._snake.borrow().get_occupied_points()[0].clone();
+        if self.is_obstacle(&head) && !self._effects.consume(EffectKind::Shield) {
+            self.apply(Transition::Die);
+            return;
+        }
+
+        // 撞到对手，幽灵可以穿过，禅模式不会死，护盾抵挡一次
+        if self.is_rival_at(&head)
+            && !self._effects.is_active(EffectKind::Ghost)
+            && self.mode().can_die()
+            && !self._effects.consume(EffectKind::Shield)
+        {
+            self.apply(Transition::Die);
+            return;
+        }
+
+        if self._effects.is_active(EffectKind::Magnet) {
+            self.attract_food();
+        }
+
+        // 吃到食物，根据种类生效,init_food
+        if let Some(idx) = self.is_eat_food() {
+            let food = self._foods.remove(idx);
+            self.eat_food(food.kind());
+            // 闯关模式达到这一关的目标
+            if let Some(status) = self.stage_status() {
+                let length = self._snake.borrow().len();
+                if status
+                    .goal
+                    .is_reached(length, self._score - self._stage_score)
+                {
+                    self.next_stage();
+                    return;
                 }
+            }
+            // 没有空位了，无尽模式和闯关模式就是胜利
+            if !self.init_food() && self.mode().wins_when_full() {
+                self.apply(Transition::Win);
+            }
+        }
+    }
+
+    // 猎物每隔几格逃一步，只走到空着的格子，在蛇移动之前
+    // 蛇头走到猎物刚离开的格子也算吃到
+    fn move_prey(&mut self) {
+        self._ticks += 1;
+        for food in self._foods.iter_mut() {
+            food.settle();
+        }
+        if self._ticks % consts::PREY_MOVE_TICKS != 0 {
+            return;
+        }
+
+        let points = self.snake_points();
+        let head = &points[0];
+        for idx in 0..self._foods.len() {
+            if !self._foods[idx].kind().is_moving() {
+                continue;
+            }
+            let prey = Point::new(self._foods[idx].x(), self._foods[idx].y());
+            let next = food::flee_step(&prey, head, |p| {
+                self._board.contains(p.x(), p.y())
+                    && !points.contains(p)
+                    && !self.is_obstacle(p)
+                    && !self.is_portal(p)
+                    && !self._foods.iter().any(|f| f.x() == p.x() && f.y() == p.y())
+            });
+            if let Some(next) = next {
+                self._foods[idx].step_to(next.x(), next.y());
+            }
+        }
+    }
 
-                // 吃到食物，add_body,init_food
-                if self.is_eat_food() {
-                    // panic!("eat_food");
-                    self._snake.borrow_mut().add_body();
-                    self.init_food();
+    // 对手各走一格，撞到边界、障碍物或者任何一条蛇就死掉，过一会儿在别处重新出现
+    // 吃到的食物只让对手变长，补上新的食物
+    fn move_rivals(&mut self) {
+        let wrap = self.rules().wrap;
+        for idx in 0..self._rivals.len() {
+            if !self._rivals[idx].is_alive() {
+                self._rivals[idx].respawn -= 1;
+                if self._rivals[idx].respawn == 0 {
+                    match self.free_cell(consts::RIVAL_SPAWN_DISTANCE) {
+                        Some(point) => self._rivals[idx].snake.reset_at(point, None),
+                        None => self._rivals[idx].respawn = 1, // 没有空地，下一格再试
+                    }
                 }
+                continue;
+            }
 
-                self.draw();
-            } else {
-                // 交出一点时间片。不然要卡死
-                app::wait();
+            // 移动前所有蛇占的格子都不能走，包括自己
+            let occupied = self.snake_points();
+            let foods: Vec<Point> = self
+                ._foods
+                .iter()
+                .map(|food| Point::new(food.x(), food.y()))
+                .collect();
+            let direction = rival::choose(
+                self._rivals[idx].behaviour,
+                &self._rivals[idx].snake,
+                &foods,
+                &self._snake.borrow(),
+                |p| {
+                    self._board.contains(p.x(), p.y())
+                        && !self.is_obstacle(p)
+                        && !occupied.contains(p)
+                },
+            );
+
+            let snake = &mut self._rivals[idx].snake;
+            let _ = snake.set_direction(direction);
+            let crashed = match snake.move_direction() {
+                Ok(()) => false,
+                Err(_) if wrap => {
+                    snake.move_through_wall();
+                    false
+                }
+                Err(_) => true,
+            };
+            let head = snake.get_occupied_points()[0].clone();
+            if crashed || self.is_obstacle(&head) || occupied.contains(&head) {
+                self._rivals[idx].respawn = consts::RIVAL_RESPAWN_TICKS;
+                continue;
+            }
+
+            if let Some(food) = self
+                ._foods
+                .iter()
+                .position(|food| food.is_caught(head.x(), head.y()))
+            {
+                let growth = self._foods.remove(food).kind().growth();
+                let snake = &mut self._rivals[idx].snake;
+                if growth > 0 {
+                    snake.grow(growth);
+                } else {
+                    snake.remove_body(-growth);
+                }
+                self.init_food();
             }
         }
     }
 
-    // 🐍身体是否有交叉，判定是否吃到自己
-    fn is_eat_own(&self) -> bool {
-        let len_ = (*self._snake).borrow().get_occupied_points().len();
-        let mut points = (*self._snake).borrow().get_occupied_points().to_vec();
-        let set: HashSet<_> = points.drain(..).collect();
-        let set_points: Vec<_> = set.into_iter().collect();
-        set_points.len() != len_
+    // 这个格子上有障碍物
+    fn is_obstacle(&self, point: &Point) -> bool {
+        self._walls.contains(point) || self._obstacles.contains(point)
+    }
+
+    // 这个格子是传送门
+    fn is_portal(&self, point: &Point) -> bool {
+        self._portals.iter().any(|(a, b)| a == point || b == point)
+    }
+
+    // 这个格子上有活着的对手
+    fn is_rival_at(&self, point: &Point) -> bool {
+        self._rivals
+            .iter()
+            .any(|rival| rival.is_alive() && rival.snake.get_occupied_points().contains(point))
+    }
+
+    // 继续游戏，暂停的时间不计入道具和食物的计时
+    fn resume(&mut self) {
+        let paused = self._pause.borrow().paused_for();
+        self._effects.postpone(paused);
+        self._clock.postpone(paused);
+        self._started_at += paused;
+        for food in self._foods.iter_mut() {
+            food.postpone(paused);
+        }
     }
 
-    // 根据头节点判断是否吃到食物
-    fn is_eat_food(&self) -> bool {
+    // 显示开始界面
+    fn show_title(&mut self) {
+        let theme = self.theme();
+        let modes = self.mode_labels();
+        if let Some(title) = self._title.as_mut() {
+            // 暂停菜单里可能修改过设置和语言，可能解锁了新的关卡
+            MyApp::apply_title_text(title, &self._settings.borrow(), &modes);
+            MyApp::apply_title_theme(title, &theme);
+            title.group.show();
+        }
+        self._window
+            .set_color(Color::from_hex(theme.title_background));
+        self.draw_title_text();
+        self._window.redraw();
+    }
+
+    // 🐍头碰到的身体节的下标，判定是否吃到自己
+    // 只看蛇头，穿过身体后留下的交叉不算
+    fn bitten_segment(&self) -> Option<usize> {
+        let _snake = (*self._snake).borrow();
+        let points = _snake.get_occupied_points();
+        points[1..]
+            .iter()
+            .position(|point| *point == points[0])
+            .map(|idx| idx + 1)
+    }
+
+    // 根据头节点判断是否吃到食物，返回吃到的食物下标
+    fn is_eat_food(&self) -> Option<usize> {
         let _snake = (*self._snake).borrow();
 
         let head = _snake.get_occupied_points().first().unwrap();
 
-        // 间隔小于等于2倍body就是穿过了
-        let x_space = max(
-            head.x() + consts::BODY_SIZE - self._food.x(),
-            self._food.x() + consts::BODY_SIZE - head.x(),
-        );
-        let y_space = max(
-            head.y() + consts::BODY_SIZE - self._food.y(),
-            self._food.y() + consts::BODY_SIZE - head.y(),
-        );
-        println!(
-            "x_space:{},y_space:{},head.x:{},head.y:{},_food.x:{},food.y:{}",
-            x_space,
-            y_space,
-            head.x(),
-            head.y(),
-            self._food.x(),
-            self._food.y()
-        );
+        self._foods.iter().position(|food| {
+            // 间隔小于等于2倍body就是穿过了
+            let x_space = max(
+                head.x() + consts::BODY_SIZE - food.x(),
+                food.x() + consts::BODY_SIZE - head.x(),
+            );
+            let y_space = max(
+                head.y() + consts::BODY_SIZE - food.y(),
+                food.y() + consts::BODY_SIZE - head.y(),
+            );
 
-        (head.x() == self._food.x() || head.y() == self._food.y()/*在同一条线*/)
-            && (x_space < 2 * consts::BODY_SIZE && y_space < 2 * consts::BODY_SIZE/*有交叉*/)
+            (head.x() == food.x() || head.y() == food.y()/*在同一条线*/)
+                && (x_space < 2 * consts::BODY_SIZE && y_space < 2 * consts::BODY_SIZE/*有交叉*/)
+                // 猎物这一格刚从蛇头的位置逃走
+                || food.is_caught(head.x(), head.y())
+        })
+    }
+
+    // 吃到食物后根据种类生效：长度、得分、速度
+    fn eat_food(&mut self, kind: FoodKind) {
+        self._score += kind.score();
+        self._eaten += 1;
+
+        let growth = kind.growth();
+        if growth > 0 {
+            self._snake.borrow_mut().grow(growth);
+        } else {
+            self._snake.borrow_mut().remove_body(-growth);
+        }
+
+        if let Some(effect) = kind.effect() {
+            self._effects.add(effect, Instant::now());
+            self._audio.play(Sound::PowerUp);
+        } else {
+            self._audio.play(Sound::Eat);
+        }
+    }
+
+    // 磁铁：范围内的食物向蛇头靠近一格
+    fn attract_food(&mut self) {
+        let points = self.snake_points();
+        let head = points.first().unwrap().clone();
+
+        for idx in 0..self._foods.len() {
+            let food = &self._foods[idx];
+            let dx = (head.x() - food.x()) / consts::BODY_SIZE;
+            let dy = (head.y() - food.y()) / consts::BODY_SIZE;
+            if dx.abs() + dy.abs() > consts::MAGNET_RADIUS {
+                continue;
+            }
+
+            // 沿距离更远的方向靠近
+            let (step_x, step_y) = if dx.abs() >= dy.abs() {
+                (dx.signum(), 0)
+            } else {
+                (0, dy.signum())
+            };
+            let target = Point::new(
+                food.x() + step_x * consts::BODY_SIZE,
+                food.y() + step_y * consts::BODY_SIZE,
+            );
+
+            // 目标是蛇头就等着被吃，被蛇、障碍物、传送门或者其他食物占用就不动
+            if (target != head && points.contains(&target))
+                || self.is_obstacle(&target)
+                || self.is_portal(&target)
+            {
+                continue;
+            }
+            if self
+                ._foods
+                .iter()
+                .any(|f| f.x() == target.x() && f.y() == target.y())
+            {
+                continue;
+            }
+            self._foods[idx].set_position(target.x(), target.y());
+        }
+    }
+
+    // 记录得分到排行
+    fn record_score(&mut self) {
+        let record = ScoreRecord {
+            score: self._score,
+            length: self._snake.borrow().len(),
+            speed: self.speed(),
+            time: utils::timestamp(),
+        };
+        // 记到这种模式的排行榜
+        if let Some(high_scores) = self._high_scores.get_mut(self.mode().name()) {
+            high_scores.add(record);
+            if let Err(e) = high_scores.save() {
+                println!("{}", i18n::trf(Msg::SaveScoresFailed, &[&e]));
+            }
+        }
     }
 
     fn game_win(&mut self) {
-        *self._is_display.borrow_mut() = false;
-        *self._is_game_over.borrow_mut() = false;
-        (*self._snake).borrow_mut().clear(); // 清除数据，重启的话重新开始
-        self._is_win = false;
+        self.record_score();
+        self._audio.play(Sound::Victory);
 
         // 绘画结束ui
-        let width = self._window.width();
-        let height = self._window.height();
+        let board = self._board;
+        let theme = self.theme();
+        // 限时模式是时间到了
+        let text = match self.mode() {
+            GameMode::TimeAttack => i18n::tr(Msg::TimeUp),
+            _ => i18n::tr(Msg::Victory),
+        };
+        let hint = i18n::trf(Msg::EndHint, &[&self.key_label(Action::Pause)]);
         app::awake(); // 唤醒ui线程
         self._window.draw(move |f| {
-            // 在 draw 中实现绘制逻辑，此处是根据缓存绘制
-            // 绘制背景
-            draw::set_draw_color(Color::Dark3);
-            draw::draw_rectf(0, 0, width, height);
-            // 设置字体和颜色
-            draw::set_font(Font::HelveticaBold, 30);
-            draw::set_draw_color(Color::White);
-            let text = "Victory";
-            let (text_width, text_height) = draw::measure(text, true);
-            let x = (width - text_width) / 2;
-            let y = (height + text_height) / 2;
-            draw::draw_text(text, x, y);
+            render::draw_end_screen(&mut FltkCanvas, text, &hint, &board, &theme, f.w(), f.h());
         });
         self._window.redraw();
         app::wait();
     }
 
     fn game_over(&mut self) {
-        *self._is_display.borrow_mut() = false;
-        *self._is_game_over.borrow_mut() = false;
-        (*self._snake).borrow_mut().clear(); // 清除数据，重启的话重新开始
+        self.record_score();
+        self._audio.play(Sound::Death);
 
         // 绘画结束ui
-        let width = self._window.width();
-        let height = self._window.height();
+        let board = self._board;
+        let theme = self.theme();
+        let text = i18n::tr(Msg::GameOver);
+        let hint = i18n::trf(Msg::EndHint, &[&self.key_label(Action::Pause)]);
         app::awake(); // 唤醒ui线程
         self._window.draw(move |f| {
-            // 在 draw 中实现绘制逻辑，此处是根据缓存绘制
-            // 绘制背景
-            draw::set_draw_color(Color::Dark3);
-            draw::draw_rectf(0, 0, width, height);
-            // 设置字体和颜色
-            draw::set_font(Font::HelveticaBold, 30);
-            draw::set_draw_color(Color::White);
-            // 在屏幕中央绘制 "Game Over" 文字
-            let text = "Game Over";
-            let (text_width, text_height) = draw::measure(text, true);
-            let x = (width - text_width) / 2;
-            let y = (height + text_height) / 2;
-            draw::draw_text(text, x, y);
+            render::draw_end_screen(&mut FltkCanvas, text, &hint, &board, &theme, f.w(), f.h());
         });
         self._window.redraw();
         app::wait();
     }
 
     // 绘画统一在这里处理
+    // 更新这一帧的数据，只重画和上一帧不同的区域
     fn draw(&mut self) {
-        // 获取snake 点位
-        let points = (*self._snake).borrow().get_occupied_points().to_vec();
-
-        // 获取food点位
-        let food = self._food.clone();
-
-        // 获取snake 方向
-        let direction = (*self._snake).borrow().get_direction().clone();
+        let frame = self.snapshot();
+        // 棋盘和状态栏的摆法由窗口大小决定
+        let (vp, placement) = hud::fit(&self._board, self._window.w(), self._window.h());
+        let sprites = frame.sprites(&self._board, placement);
+        // 换主题或者换关卡的墙时背景变了，全部重画
+        let scene = damage::hash_of(&(self._settings.borrow().theme, &frame.walls));
+        *self._frame.borrow_mut() = Some(frame);
 
         // draw
         app::awake(); // 唤醒ui线程
-        self._window.draw(move |f| {
-            // 绘制得分
-            draw::set_draw_color(Color::Black);
-            draw::draw_text2(
-                format!("当前得分: {}", points.len()).as_str(),
-                f.w() * 3 / 4,
-                f.h() / 15,
-                f.w() / 6,
-                f.h() / 15,
-                Align::Center,
-            );
+        match self._damage.update(scene, sprites) {
+            None => self._window.redraw(),
+            Some(rects) => {
+                // 逻辑坐标换算成窗口坐标，多留一点边防止缩放取整漏掉
+                for (x, y, w, h) in rects {
+                    let (x, y, w, h) = vp.rect(x, y, w, h);
+                    self._window
+                        .set_damage_area(Damage::User1, x - 2, y - 2, w + 4, h + 4);
+                }
+            }
+        }
+        app::wait();
+    }
 
-            // 绘制食物
-            draw::draw_text2(
-                "💩",
-                food.x(),
-                food.y(),
-                consts::BODY_SIZE,
-                consts::BODY_SIZE,
-                Align::all(),
-            );
+    // 获取这一帧需要的数据
+    fn snapshot(&mut self) -> Frame {
+        // 暂停时画面停在暂停的时刻
+        let now = if self.state() == GameState::Paused {
+            self._pause.borrow().opened_at()
+        } else {
+            Instant::now()
+        };
+        // 蛇头和尾巴根据这一格走了多少插值
+        let progress = self._clock.progress(now, self.interval());
 
-            // 绘制蛇
-            for (idx, point) in points.iter().enumerate() {
-                if idx == 0 {
-                    // 蛇头。绘制一个半圆，一个方形
-                    let (mut x, mut y, mut w, mut h) =
-                        (point.x(), point.y(), consts::BODY_SIZE, consts::BODY_SIZE);
-                    let eye1: Point;
-                    let eye2: Point;
-
-                    match direction {
-                        Direction::Left => {
-                            x += consts::BODY_SIZE / 2;
-                            w = consts::BODY_SIZE / 2;
-
-                            eye1 = Point::new(
-                                point.x() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
-                                point.y() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
-                            );
-                            eye2 = Point::new(
-                                point.x() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
-                                point.y() + 3 * consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
-                            )
-                        }
-                        Direction::Up => {
-                            y += consts::BODY_SIZE / 2;
-                            h = consts::BODY_SIZE / 2;
-
-                            eye1 = Point::new(
-                                point.x() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
-                                point.y() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
-                            );
-                            eye2 = Point::new(
-                                point.x() + 3 * consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
-                                point.y() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
-                            )
-                        }
-                        Direction::Right => {
-                            w = consts::BODY_SIZE / 2;
-
-                            eye1 = Point::new(
-                                point.x() + consts::BODY_SIZE * 3 / 4 - consts::EYE_SIZE / 2,
-                                point.y() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
-                            );
-                            eye2 = Point::new(
-                                point.x() + consts::BODY_SIZE * 3 / 4 - consts::EYE_SIZE / 2,
-                                point.y() + consts::BODY_SIZE * 3 / 4 - consts::EYE_SIZE / 2,
-                            );
-                        }
-                        Direction::Down => {
-                            h = consts::BODY_SIZE / 2;
-
-                            eye1 = Point::new(
-                                point.x() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
-                                point.y() + consts::BODY_SIZE * 3 / 4 - consts::EYE_SIZE / 2,
-                            );
-                            eye2 = Point::new(
-                                point.x() + consts::BODY_SIZE * 3 / 4 - consts::EYE_SIZE / 2,
-                                point.y() + consts::BODY_SIZE * 3 / 4 - consts::EYE_SIZE / 2,
-                            );
-                        }
-                    }
+        // 获取暂停菜单
+        let pause = if self.state() == GameState::Paused {
+            let menu = self._pause.borrow().clone();
+            let labels = menu.labels(&self._settings.borrow());
+            Some((menu, labels))
+        } else {
+            None
+        };
 
-                    // 眼白信息
-                    let (eye1_centerx, eye1_centery) = (
-                        eye1.x() + consts::EYE_SIZE / 2,
-                        eye1.y() + consts::EYE_SIZE / 2,
-                    );
-                    let (eye2_centerx, eye2_centery) = (
-                        eye2.x() + consts::EYE_SIZE / 2,
-                        eye2.y() + consts::EYE_SIZE / 2,
-                    );
+        // 获取主题和图片
+        self.load_theme_images();
+        self.load_tiles();
 
-                    // 绘制头半圆
-                    draw::draw_circle_fill(
-                        point.x(),
-                        point.y(),
-                        consts::BODY_SIZE,
-                        Color::from_hex_str("#00A4E9").unwrap(),
-                    );
-                    // 绘制眼睛
-                    draw::draw_circle_fill(eye1.x(), eye1.y(), consts::EYE_SIZE, Color::Black);
-                    draw::draw_circle_fill(eye2.x(), eye2.y(), consts::EYE_SIZE, Color::Black);
-                    // 绘制眼白
-                    draw::draw_circle_fill(
-                        eye1_centerx,
-                        eye1_centery,
-                        consts::EYE_WHITE_SIZE,
-                        Color::White,
-                    );
-                    draw::draw_circle_fill(
-                        eye2_centerx,
-                        eye2_centery,
-                        consts::EYE_WHITE_SIZE,
-                        Color::White,
-                    );
-                    // 绘制头的方形部分
-                    draw::draw_rect_fill(x, y, w, h, Color::from_hex_str("#00A4E9").unwrap());
-                } else if idx % 2 == 1 {
-                    draw::draw_rect_fill(
-                        point.x(),
-                        point.y(),
-                        consts::BODY_SIZE,
-                        consts::BODY_SIZE,
-                        Color::Red,
-                    );
+        Frame::new(
+            &self.scene(now),
+            progress,
+            pause,
+            self.theme(),
+            self.best_score(),
+        )
+    }
+
+    // 当前的游戏状态
+    fn scene(&self, now: Instant) -> Scene {
+        let _snake = self._snake.borrow();
+        Scene {
+            points: _snake.get_occupied_points().clone(),
+            last_tail: _snake.last_tail_point().clone(),
+            direction: _snake.get_direction().clone(),
+            foods: self
+                ._foods
+                .iter()
+                .map(|food| (Point::new(food.x(), food.y()), food.kind()))
+                .collect(),
+            score: self._score,
+            eaten: self._eaten,
+            elapsed: self.elapsed(now),
+            interval: self.interval(),
+            effects: self
+                ._effects
+                .effects()
+                .iter()
+                .map(|e| (e.kind(), e.remaining(now)))
+                .collect(),
+            obstacles: self._obstacles.clone(),
+            walls: self._walls.clone(),
+            stage: self.stage_status(),
+            timer: self.timer(now),
+            rivals: self
+                ._rivals
+                .iter()
+                .filter(|rival| rival.is_alive())
+                .map(|rival| RivalScene {
+                    points: rival.snake.get_occupied_points().clone(),
+                    last_tail: rival.snake.last_tail_point().clone(),
+                    direction: rival.snake.get_direction().clone(),
+                    color: rival.color,
+                })
+                .collect(),
+            portals: self._portals.clone(),
+        }
+    }
+
+    // 这种模式的排行榜里的最高分
+    fn best_score(&self) -> i32 {
+        self._high_scores
+            .get(self.mode().name())
+            .and_then(|high_scores| high_scores.best())
+            .map_or(0, |record| record.score)
+    }
+
+    // 命令行指定录制文件，开始游戏后马上录制
+    pub fn record_to(&mut self, path: PathBuf) {
+        self._record_path = Some(path);
+    }
+
+    // 命令行决定声音的输出，默认静音
+    pub fn set_audio(&mut self, audio: Audio) {
+        self._audio = audio;
+    }
+
+    // 截图和录制的大小：逻辑坐标1:1，状态栏在上方
+    fn capture_size(board: &Board) -> (i32, i32) {
+        (board.width(), board.height() + consts::HUD_HEIGHT)
+    }
+
+    // 在离屏的图片上绘制一帧，不截取屏幕，返回 RGB 像素
+    // 用 FLTK 绘制，文字和窗口里一样，中文也能正常显示
+    fn render_image(&self, frame: &Frame) -> Option<Vec<u8>> {
+        let (w, h) = MyApp::capture_size(&self._board);
+        let surface = surface::ImageSurface::new(w, h, false);
+        surface::ImageSurface::push_current(&surface);
+        render::draw_all(
+            &mut FltkCanvas,
+            frame,
+            &self._board,
+            &self._images.borrow(),
+            &self._tiles.borrow().tiles,
+            w,
+            h,
+        );
+        surface::ImageSurface::pop_current();
+
+        let image = surface.image()?.convert(ColorDepth::Rgb8).ok()?;
+        Some(image.to_rgb_data())
+    }
+
+    // 画在内存里的 RGBA 图片上，不需要显示器，命令行导出录像使用
+    // 文字是内置的点阵字体，中文画成方框
+    fn render_rgb(
+        frame: &Frame,
+        board: &Board,
+        images: &HashMap<PathBuf, Option<Bitmap>>,
+        tiles: &HashMap<Tile, Bitmap>,
+    ) -> Vec<u8> {
+        let (w, h) = MyApp::capture_size(board);
+        let mut canvas = RgbaCanvas::new(w, h);
+        render::draw_all(&mut canvas, frame, board, images, tiles, w, h);
+        canvas.to_rgb()
+    }
+
+    // 处理截图和录制的快捷键，录制中按间隔录一帧
+    fn capture(&mut self) {
+        let request = self._capture.borrow_mut().take();
+        match request {
+            Some(Capture::Screenshot) => {
+                let (w, h) = MyApp::capture_size(&self._board);
+                let path = record::capture_path("screenshots", "png");
+                let result = match self._frame.borrow().as_ref() {
+                    Some(frame) => self
+                        .render_image(frame)
+                        .ok_or_else(|| i18n::tr(Msg::RenderFailed).to_string()),
+                    None => Err(i18n::tr(Msg::NothingToCapture).to_string()),
+                }
+                .and_then(|rgb| record::save_png(&path, w as u32, h as u32, &rgb));
+                match result {
+                    Ok(()) => println!("{}", i18n::trf(Msg::ScreenshotSaved, &[&path.display()])),
+                    Err(e) => println!("{}", i18n::trf(Msg::ScreenshotFailed, &[&e])),
+                }
+            }
+            Some(Capture::ToggleRecording) => {
+                if self._recorder.is_some() {
+                    self.stop_recording();
                 } else {
-                    draw::draw_rect_fill(
-                        point.x(),
-                        point.y(),
-                        consts::BODY_SIZE,
-                        consts::BODY_SIZE,
-                        Color::from_hex_str("#00A4E9").unwrap(),
-                    );
+                    self.start_recording(record::capture_path("recordings", "gif"));
                 }
             }
+            None => {}
+        }
+
+        let now = Instant::now();
+        if !self._recorder.as_ref().is_some_and(|r| r.is_due(now)) {
+            return;
+        }
+        let rgb = match self._frame.borrow().as_ref() {
+            Some(frame) => self.render_image(frame),
+            None => return,
+        };
+        let result = match (rgb, self._recorder.as_mut()) {
+            (Some(rgb), Some(recorder)) => recorder.add_frame(&rgb, now),
+            (None, _) => Err(i18n::tr(Msg::RenderFailed).to_string()),
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            println!("{}", i18n::trf(Msg::RecordFailed, &[&e]));
+            self._recorder = None;
+        }
+    }
+
+    // 开始录制，先记下当前的状态
+    fn start_recording(&mut self, path: PathBuf) {
+        let (w, h) = MyApp::capture_size(&self._board);
+        let mut replay = Replay::new(self.speed(), self._board);
+        replay.scenes.push(self.scene(Instant::now()));
+        match Recorder::start(&path, w as u16, h as u16, replay) {
+            Ok(recorder) => {
+                println!("{}", i18n::trf(Msg::RecordStarted, &[&path.display()]));
+                self._recorder = Some(recorder);
+            }
+            Err(e) => println!("{}", i18n::trf(Msg::RecordFailed, &[&e])),
+        }
+    }
+
+    fn stop_recording(&mut self) {
+        if let Some(recorder) = self._recorder.take() {
+            match recorder.finish() {
+                Ok(path) => println!("{}", i18n::trf(Msg::RecordSaved, &[&path.display()])),
+                Err(e) => println!("{}", i18n::trf(Msg::RecordFailed, &[&e])),
+            }
+        }
+    }
+
+    // 把录像画成gif或者截图，命令行使用
+    // 不创建窗口、不启动手柄线程，没有显示器也能用
+    pub fn export_replay(
+        replay: &Replay,
+        gif: Option<&Path>,
+        screenshot: Option<&Path>,
+        step: Option<usize>,
+    ) -> Result<(), String> {
+        MyApp::load_settings();
+        // 和游戏里一样使用默认的主题和无尽模式的最高分
+        let theme = Rc::new(Theme::builtin().swap_remove(0));
+        let mut images = HashMap::new();
+        MyApp::load_images(&theme, &mut images);
+        let tiles = match &theme.tileset {
+            Some(path) => MyApp::cut_tiles(path).unwrap_or_else(|e| {
+                println!("{}", i18n::trf(Msg::LoadTilesetFailed, &[&e]));
+                HashMap::new()
+            }),
+            None => HashMap::new(),
+        };
+        let best = HighScores::load(utils::data_dir().join(GameMode::Endless.scores_file()))
+            .best()
+            .map_or(0, |record| record.score);
+
+        let board = replay.board;
+        let (w, h) = MyApp::capture_size(&board);
+        let render = |scene: &Scene| {
+            let frame = Frame::new(scene, 1.0, None, Rc::clone(&theme), best);
+            MyApp::render_rgb(&frame, &board, &images, &tiles)
+        };
+
+        if let Some(path) = gif {
+            record::replay_to_gif(path, replay, w as u16, h as u16, render)?;
+            println!("{}", i18n::trf(Msg::ReplaySaved, &[&path.display()]));
+        }
+        if let Some(path) = screenshot {
+            let idx = step.unwrap_or(replay.scenes.len().saturating_sub(1));
+            let scene = replay
+                .scenes
+                .get(idx)
+                .ok_or_else(|| i18n::trf(Msg::ReplayTooShort, &[&replay.scenes.len()]))?;
+            let rgb = render(scene);
+            record::save_png(path, w as u32, h as u32, &rgb)?;
+            println!("{}", i18n::trf(Msg::ScreenshotSaved, &[&path.display()]));
+        }
+        Ok(())
+    }
+
+    // 设置游戏画面的绘制回调，每局开始时设置一次
+    // 之后每帧只更新 _frame，由 FLTK 在需要重画的区域里调用
+    fn install_frame_draw(&mut self) {
+        self._damage.invalidate();
+        let frame = Rc::clone(&self._frame);
+        let board = self._board;
+        let images = Rc::clone(&self._images);
+        let tiles = Rc::clone(&self._tiles);
+        // 边框、棋盘背景和关卡的墙不常变，画在离屏缓冲里，窗口大小、主题或者墙变化时重画
+        type BackgroundKey = (i32, i32, u32, u32, Option<usize>, u64);
+        let mut background: Option<(draw::Offscreen, BackgroundKey)> = None;
+        self._window.draw(move |f| {
+            let frame = frame.borrow();
+            let frame = match frame.as_ref() {
+                Some(frame) => frame,
+                None => return,
+            };
+            // 逻辑坐标缩放到当前窗口，保持宽高比，多余部分留黑边
+            let (vp, placement) = hud::fit(&board, f.w(), f.h());
+            let theme = &frame.theme;
+            let (images, tiles) = (images.borrow(), tiles.borrow());
+            let key = (
+                f.w(),
+                f.h(),
+                theme.border,
+                theme.background,
+                tiles.theme,
+                damage::hash_of(&frame.walls),
+            );
+            if background.as_ref().map(|(_, k)| *k) != Some(key) {
+                background = draw::Offscreen::new(f.w(), f.h()).map(|offscreen| {
+                    offscreen.begin();
+                    render::draw_background(
+                        &mut FltkCanvas,
+                        frame,
+                        &board,
+                        &tiles.tiles,
+                        f.w(),
+                        f.h(),
+                    );
+                    offscreen.end();
+                    (offscreen, key)
+                });
+            }
+            match &background {
+                Some((offscreen, _)) => offscreen.copy(0, 0, f.w(), f.h(), 0, 0),
+                None => render::draw_background(
+                    &mut FltkCanvas,
+                    frame,
+                    &board,
+                    &tiles.tiles,
+                    f.w(),
+                    f.h(),
+                ),
+            }
+            let canvas = &mut FltkCanvas;
+            render::draw_hud(canvas, &frame.hud, placement, &board, &vp, theme);
+            render::draw_frame(canvas, frame, &board, &vp, &images, &tiles.tiles);
+        });
+    }
+
+    // 按玩家1当前的按键显示
+    fn key_label(&self, action: Action) -> String {
+        self._settings
+            .borrow()
+            .keys
+            .keys(Slot::new(0, action))
+            .first()
+            .map_or(String::from("-"), |key| keymap::key_label(*key))
+    }
+
+    // 开始界面的操作说明
+    fn draw_title_text(&mut self) {
+        let theme = self.theme();
+        let label = |action: Action| self.key_label(action);
+        let text = i18n::trf(
+            Msg::TitleHelp,
+            &[
+                &label(Action::Up),
+                &label(Action::Down),
+                &label(Action::Left),
+                &label(Action::Right),
+                &label(Action::Pause),
+            ],
+        );
+        self._window.draw(move |w| {
+            draw::set_font(canvas::fltk_font(&theme.font, false), 14);
+            draw::set_draw_color(Color::from_hex(theme.title_text));
+            draw::draw_text2(&text, 0, w.h() / 2, w.w(), w.h() / 6, Align::Center);
         });
-        self._window.redraw();
-        app::wait();
     }
 
     // 绘制开机动画
     fn draw_window(&mut self) {
         let mut group = group::Group::new(0, 0, self._window.w(), self._window.h(), "");
         group.set_frame(FrameType::FlatBox);
-        group.set_color(Color::from_u32(0xECECEC));
 
         let mut title = button::Button::new(
             0,
             self._window.h() / 6,
             self._window.w(),
             self._window.h() / 3,
-            "贪吃蛇游戏",
+            "",
         );
         title.set_frame(FrameType::FlatBox);
-        title.set_color(Color::from_u32(0xECECEC));
-        title.set_label_font(Font::HelveticaBold);
         title.set_label_size((self._window.h() / 6).min(24));
-        title.set_label_color(Color::from_u32(0x333333));
         title.set_label_type(fltk::enums::LabelType::Normal);
 
-        // let mut w = self._window.clone();
-        self._window.draw(|w| {
-            draw::draw_text2(
-                "↑ ↓ ← → 控制移动方向\n空格键暂停/重启",
-                0,
-                w.h() / 2,
-                w.w(),
-                w.h() / 6,
-                Align::Center,
-            );
-        });
+        self.draw_title_text();
 
         let mut start_button = button::Button::new(
             self._window.w() / 4,
             2 * self._window.h() / 3,
             self._window.w() / 2,
             self._window.h() / 6,
-            "开始游戏",
+            "",
         );
         start_button.set_frame(FrameType::FlatBox);
-        start_button.set_color(Color::from_u32(0x4CAF50));
-        start_button.set_label_font(Font::HelveticaBold);
         start_button.set_label_size((self._window.h() / 12).min(16));
-        start_button.set_label_color(Color::White);
         start_button.set_label_type(fltk::enums::LabelType::Normal);
 
-        let _display = Rc::clone(&self._is_display);
-        start_button.handle(move |btn, ev| {
+        // 难度和速度曲线选择
+        let choice_y = 5 * self._window.h() / 6 + 10;
+        let mut difficulty_choice = menu::Choice::new(
+            self._window.w() / 4,
+            choice_y,
+            self._window.w() / 4 - 5,
+            30,
+            "",
+        );
+        let _settings = Rc::clone(&self._settings);
+        difficulty_choice.set_callback(move |c| {
+            if let Some(difficulty) = DIFFICULTIES.get(c.value() as usize) {
+                _settings.borrow_mut().speed.difficulty = *difficulty;
+            }
+        });
+
+        let mut curve_choice = menu::Choice::new(
+            self._window.w() / 2 + 5,
+            choice_y,
+            self._window.w() / 4 - 5,
+            30,
+            "",
+        );
+        let _settings = Rc::clone(&self._settings);
+        curve_choice.set_callback(move |c| {
+            if let Some(curve) = SPEED_CURVES.get(c.value() as usize) {
+                _settings.borrow_mut().speed.curve = *curve;
+            }
+        });
+
+        // 选择模式或者已经解锁的关卡
+        let mut mode_choice = menu::Choice::new(
+            self._window.w() / 4,
+            choice_y + 35,
+            self._window.w() / 2,
+            30,
+            "",
+        );
+        let _settings = Rc::clone(&self._settings);
+        mode_choice.set_callback(move |c| {
+            let idx = c.value().max(0) as usize;
+            _settings.borrow_mut().mode = GameMode::from_choice(idx);
+        });
+
+        let theme = self.theme();
+        let mut title_screen = TitleScreen {
+            group: group.clone(),
+            title,
+            start_button: start_button.clone(),
+            difficulty_choice,
+            curve_choice,
+            mode_choice,
+        };
+        let modes = self.mode_labels();
+        MyApp::apply_title_text(&mut title_screen, &self._settings.borrow(), &modes);
+        MyApp::apply_title_theme(&mut title_screen, &theme);
+        self._title = Some(title_screen);
+
+        let _state = Rc::clone(&self._state);
+        start_button.handle(move |_, ev| {
             match ev {
                 fltk::enums::Event::Released => {
-                    btn.hide();
-                    title.hide();
-                    group.hide();
-                    btn.window().unwrap().set_border(true); // 无边框
-                    btn.window().unwrap().set_color(Color::White);
                     // 启动游戏
-                    *_display.borrow_mut() = true;
-
+                    _state.borrow_mut().request(Transition::Start);
                     true
                 }
                 _ => false,
             }
         });
-        self._window.set_color(Color::White);
+        self._window
+            .set_color(Color::from_hex(theme.title_background));
         self._window.end();
         self._window.show();
     }
 
+    // 开始界面可以选的模式，后面是解锁了的关卡
+    fn mode_labels(&self) -> Vec<String> {
+        let stages = self
+            ._stages
+            .iter()
+            .take(self._progress.unlocked())
+            .enumerate()
+            .map(|(idx, stage)| i18n::trf(Msg::StageLabel, &[&(idx + 1), &stage.name]));
+        MODES
+            .iter()
+            .map(|mode| mode.label().to_string())
+            .chain(stages)
+            .collect()
+    }
+
+    // 开始界面的文字使用当前语言，选项显示当前设置
+    fn apply_title_text(title: &mut TitleScreen, settings: &Settings, modes: &[String]) {
+        title.title.set_label(i18n::tr(Msg::GameTitle));
+        title.start_button.set_label(i18n::tr(Msg::StartGame));
+
+        let speed = settings.speed;
+        title.difficulty_choice.clear();
+        for difficulty in DIFFICULTIES.iter() {
+            title.difficulty_choice.add_choice(difficulty.label());
+        }
+        let difficulty = DIFFICULTIES
+            .iter()
+            .position(|d| *d == speed.difficulty)
+            .unwrap_or(0);
+        title.difficulty_choice.set_value(difficulty as i32);
+
+        title.curve_choice.clear();
+        for curve in SPEED_CURVES.iter() {
+            title.curve_choice.add_choice(curve.label());
+        }
+        let curve = SPEED_CURVES
+            .iter()
+            .position(|c| c.name() == speed.curve.name())
+            .unwrap_or(0);
+        title.curve_choice.set_value(curve as i32);
+
+        title.mode_choice.clear();
+        for mode in modes {
+            title.mode_choice.add_choice(mode);
+        }
+        let mode = settings.mode.choice();
+        let mode = if mode < modes.len() { mode } else { 0 };
+        title.mode_choice.set_value(mode as i32);
+    }
+
+    // 开始界面的控件使用主题颜色
+    fn apply_title_theme(title: &mut TitleScreen, theme: &Theme) {
+        let background = Color::from_hex(theme.title_background);
+        title.group.set_color(background);
+        title.title.set_color(background);
+        title
+            .title
+            .set_label_font(canvas::fltk_font(&theme.font, true));
+        title
+            .title
+            .set_label_color(Color::from_hex(theme.title_text));
+        title.start_button.set_color(Color::from_hex(theme.accent));
+        title
+            .start_button
+            .set_label_font(canvas::fltk_font(&theme.font, true));
+        title
+            .start_button
+            .set_label_color(Color::from_hex(theme.panel_text));
+    }
+
     fn new_window(x: i32, y: i32, w: i32, h: i32) -> window::DoubleWindow {
         // init
         let mut wind: window::DoubleWindow = window::Window::new(x, y, w, h, "Rust_snake");
         wind.set_border(false); // 无边框
+                                // 可以缩放，最小为初始大小的一半，棋盘按比例缩放
+        wind.make_resizable(true);
+        wind.size_range(w / 2, h / 2, 0, 0);
 
         wind
     }
 
-    // 初始化食物
-    fn init_food(&mut self) {
-        let occupied_points = (*self._snake).borrow().get_occupied_points().to_vec();
-        // 分成对应的份数
-        let max_x = (self._window.w() - consts::BODY_SIZE) / consts::BODY_SIZE;
-        let max_y = (self._window.h() - consts::BODY_SIZE) / consts::BODY_SIZE;
-
+    // 初始化食物，没有空位时返回false
+    fn init_food(&mut self) -> bool {
+        let occupied_points = self.snake_points();
         // 剩下的坐标点
-        let all_points: Vec<Point> = (0..max_x)
-            .flat_map(|x| {
-                (0..max_y)
-                    .map(move |y| snake::Point::new(x * consts::BODY_SIZE, y * consts::BODY_SIZE))
+        let all_points = self._board.free_cells(|point| {
+            occupied_points.contains(point) || self.is_obstacle(point) || self.is_portal(point)
+        });
+        if all_points.len() == 0 {
+            return false;
+        }
+
+        // 去掉已经有食物的点，补足场上的食物
+        let mut free_points: Vec<Point> = all_points
+            .into_iter()
+            .filter(|point| {
+                !self
+                    ._foods
+                    .iter()
+                    .any(|food| food.x() == point.x() && food.y() == point.y())
             })
-            .filter(|point| !occupied_points.contains(point))
             .collect();
-        if all_points.len() == 0 {
-            self._is_win = true;
-            return;
+        // 有的关卡只有普通食物，狩猎模式全是猎物
+        let rules = self.rules();
+        while self._foods.len() < rules.food_count && !free_points.is_empty() {
+            let food_point = free_points.swap_remove(utils::rand_range(0, free_points.len()));
+            let kind = if rules.prey {
+                FoodKind::Prey
+            } else if rules.power_ups {
+                FoodKind::random()
+            } else {
+                FoodKind::Normal
+            };
+            self._foods
+                .push(Food::new(food_point.x(), food_point.y(), kind));
         }
-        let food_point = all_points
-            .get(utils::rand_range(0, all_points.len()))
-            .unwrap();
-        self._food = Food::new(food_point.x(), food_point.y());
+        true
     }
 
     fn watch_key(&mut self) {
         let _snake = Rc::clone(&self._snake);
-        let _display = Rc::clone(&self._is_display);
-        let _game_over = Rc::clone(&self._is_game_over);
-        let _food = self._food.clone();
+        let _state = Rc::clone(&self._state);
+        let _pause = Rc::clone(&self._pause);
+        let _settings = Rc::clone(&self._settings);
+        let _capture = Rc::clone(&self._capture);
+        let board = self._board;
+        let mut gesture = Gesture::new(consts::SWIPE_DISTANCE);
 
         self._window.handle(move |w, ev| {
+            let state = _state.borrow().state();
             match ev {
                 Event::KeyDown => {
                     let key = app::event_key();
-                    let mut is_change = true;
-
-                    let result = match key {
-                        Key::Up => _snake.borrow_mut().set_direction(snake::Direction::Up),
-                        Key::Down => _snake.borrow_mut().set_direction(snake::Direction::Down),
-                        Key::Left => _snake.borrow_mut().set_direction(snake::Direction::Left),
-                        Key::Right => _snake.borrow_mut().set_direction(snake::Direction::Right),
-                        other_key => {
+                    // 第二个玩家的按键留给双人模式，现在只有玩家1控制蛇
+                    let slot = _settings.borrow().keys.lookup(key);
+                    let action = slot.filter(|slot| slot.player == 0).map(|slot| slot.action);
+
+                    // 暂停菜单等待按键时，按下的键都用来重新绑定
+                    if state == GameState::Paused && _pause.borrow().is_capturing() {
+                        _pause
+                            .borrow_mut()
+                            .capture(&mut _settings.borrow_mut(), key);
+                        return true;
+                    }
+
+                    // F11 全屏切换，任何状态下都可以
+                    if key == Key::F11 {
+                        let fullscreen = w.fullscreen_active();
+                        w.fullscreen(!fullscreen);
+                        return true;
+                    }
+
+                    // F12 截图，F9 开始/停止录制，游戏中和暂停时可以
+                    if matches!(state, GameState::Playing | GameState::Paused) {
+                        let capture = match key {
+                            Key::F12 => Some(Capture::Screenshot),
+                            Key::F9 => Some(Capture::ToggleRecording),
+                            _ => None,
+                        };
+                        if capture.is_some() {
+                            *_capture.borrow_mut() = capture;
+                            return true;
+                        }
+                    }
+
+                    match state {
+                        // 暂停菜单打开时，按键用来操作菜单
+                        GameState::Paused => {
+                            // 方向键和绑定的方向都可以操作菜单
+                            let mut menu = _pause.borrow_mut();
+                            match (key, action) {
+                                (Key::Up, _) | (_, Some(Action::Up)) => menu.select_prev(),
+                                (Key::Down, _) | (_, Some(Action::Down)) => menu.select_next(),
+                                (Key::Left, _) | (_, Some(Action::Left)) => {
+                                    menu.change(&mut _settings.borrow_mut(), -1)
+                                }
+                                (Key::Right, _) | (_, Some(Action::Right)) => {
+                                    menu.change(&mut _settings.borrow_mut(), 1)
+                                }
+                                (Key::Enter | Key::KPEnter, _) => {
+                                    menu.activate(&mut _settings.borrow_mut())
+                                }
+                                (Key::Escape, _) => menu.back(),
+                                (_, Some(Action::Pause)) => menu.resume(),
+                                _ => {}
+                            }
+                            return true;
+                        }
+                        // 暂停键开始游戏，Esc 不让窗口被关闭
+                        GameState::Title => {
+                            let is_pause = action == Some(Action::Pause);
+                            if is_pause {
+                                _state.borrow_mut().request(Transition::Start);
+                            }
+                            return is_pause || key == Key::Escape;
+                        }
+                        // 暂停键再来一局，回车返回标题
+                        GameState::GameOver | GameState::Victory => {
+                            if action == Some(Action::Pause) {
+                                _state.borrow_mut().request(Transition::Start);
+                            } else if key == Key::Enter || key == Key::KPEnter || key == Key::Escape
+                            {
+                                _state.borrow_mut().request(Transition::ToTitle);
+                            }
+                            return true;
+                        }
+                        GameState::Playing => {}
+                    }
+
+                    let result = match action.and_then(|action| action.direction()) {
+                        Some(direction) => _snake.borrow_mut().set_direction(direction),
+                        None => {
                             // pause
-                            is_change = false;
-                            if other_key.bits() == 0x20 {
-                                let mut is_display = _display.borrow_mut();
-                                *is_display = !*is_display;
-                                return true;
+                            if action == Some(Action::Pause) || key == Key::Escape {
+                                _state.borrow_mut().request(Transition::Pause);
                             }
-                            Ok(())
+                            return true;
                         }
                     };
 
-                    // 移动方向game_over
+                    // 移动方向game_over，禅模式不理会
                     if let Err(_) = result {
-                        *((*_game_over).borrow_mut()) = true;
+                        if _settings.borrow().mode.can_die() {
+                            _state.borrow_mut().request(Transition::Die);
+                        }
                         return false;
                     }
 
-                    if is_change {
-                        // 移动完马上渲染一次，主要渲染方向的改变
-                        // 移动优先
-                        _snake
-                            .borrow_mut()
-                            .move_direction(consts::MOVE_STEP, true /*is_direction*/)
-                            .unwrap();
+                    true
+                }
+                // 鼠标移动选中菜单项，点击确认
+                Event::Move | Event::Push if state == GameState::Paused => {
+                    let mut menu = _pause.borrow_mut();
+                    // 鼠标坐标转换成逻辑坐标
+                    let (vp, _) = hud::fit(&board, w.w(), w.h());
+                    let (x, y) = vp.logical_point(app::event_x(), app::event_y());
+                    if let Some(idx) = menu.item_at(x, y, board.width(), board.height()) {
+                        menu.select(idx);
+                        if ev == Event::Push {
+                            menu.activate(&mut _settings.borrow_mut());
+                        }
                     }
-
+                    true
+                }
+                // 游戏中用鼠标或者触摸屏控制方向：滑动，或者点击蛇头的一侧
+                Event::Push | Event::Drag | Event::Released if state == GameState::Playing => {
+                    let (vp, _) = hud::fit(&board, w.w(), w.h());
+                    let (x, y) = vp.logical_point(app::event_x(), app::event_y());
+                    let direction = match ev {
+                        Event::Push => {
+                            gesture.press(x, y);
+                            None
+                        }
+                        Event::Drag => gesture.drag(x, y),
+                        _ => {
+                            let snake = _snake.borrow();
+                            let head = gesture::cell_center(&snake.get_occupied_points()[0]);
+                            gesture.release(x, y, head, snake.get_direction())
+                        }
+                    };
+                    // 和按键一样，反向移动game_over
+                    if let Some(direction) = direction {
+                        if _snake.borrow_mut().set_direction(direction).is_err()
+                            && _settings.borrow().mode.can_die()
+                        {
+                            _state.borrow_mut().request(Transition::Die);
+                        }
+                    }
+                    true
+                }
+                // 结束界面点击返回标题
+                Event::Push if state == GameState::GameOver || state == GameState::Victory => {
+                    _state.borrow_mut().request(Transition::ToTitle);
                     true
                 }
                 _ => false, // 返回 false 表示未处理其他事件
@@ -0,0 +1,445 @@
+use std::time::{Duration, Instant};
+
+use fltk::enums::Key;
+
+use crate::{
+    difficulty::{DIFFICULTIES, SPEED_CURVES},
+    i18n::{self, Msg},
+    keymap::{self, Action, KeyMap, Slot, PLAYERS, PRESETS},
+    rival::{self, RIVAL_CHOICES},
+    settings::Settings,
+};
+
+// 暂停菜单项
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum MenuItem {
+    Resume,       // 继续
+    Restart,      // 重新开始
+    Settings,     // 设置
+    Title,        // 返回标题
+    Quit,         // 退出
+    Difficulty,   // 设置：难度
+    Curve,        // 设置：速度曲线
+    Theme,        // 设置：主题
+    Language,     // 设置：语言
+    Rivals,       // 设置：对手
+    Keys,         // 设置：按键
+    Back,         // 设置、按键：返回
+    KeyPlayer,    // 按键：正在修改的玩家
+    KeyPreset,    // 按键：方向键预设
+    Bind(Action), // 按键：按下新的键重新绑定
+    KeyDefaults,  // 按键：恢复默认
+}
+
+// 菜单项被选中后需要游戏主循环处理的动作
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum MenuAction {
+    Resume,
+    Restart,
+    Title,
+    Quit,
+    SaveKeys,     // 按键绑定改了，需要保存
+    SaveSettings, // 语言或者对手改了，需要切换并保存
+}
+
+// 当前显示的页面
+#[derive(PartialEq, Debug, Clone, Copy)]
+enum Page {
+    Main,
+    Settings,
+    Keys,
+}
+
+const MAIN_ITEMS: [MenuItem; 5] = [
+    MenuItem::Resume,
+    MenuItem::Restart,
+    MenuItem::Settings,
+    MenuItem::Title,
+    MenuItem::Quit,
+];
+
+const SETTINGS_ITEMS: [MenuItem; 7] = [
+    MenuItem::Difficulty,
+    MenuItem::Curve,
+    MenuItem::Theme,
+    MenuItem::Language,
+    MenuItem::Rivals,
+    MenuItem::Keys,
+    MenuItem::Back,
+];
+
+const KEYS_ITEMS: [MenuItem; 9] = [
+    MenuItem::KeyPlayer,
+    MenuItem::KeyPreset,
+    MenuItem::Bind(Action::Up),
+    MenuItem::Bind(Action::Down),
+    MenuItem::Bind(Action::Left),
+    MenuItem::Bind(Action::Right),
+    MenuItem::Bind(Action::Pause),
+    MenuItem::KeyDefaults,
+    MenuItem::Back,
+];
+
+#[derive(Clone, Debug)]
+pub struct PauseMenu {
+    page: Page,
+    selected: usize,
+    opened_at: Instant,         // 暂停开始时间，恢复后用来顺延各种计时
+    action: Option<MenuAction>, // 等待主循环处理的动作
+    player: usize,              // 按键页面正在修改的玩家
+    capturing: bool,            // 等待按下新的键
+    notice: Option<String>,     // 绑定失败的原因，代替标题显示
+}
+
+impl PauseMenu {
+    pub fn new() -> PauseMenu {
+        PauseMenu {
+            page: Page::Main,
+            selected: 0,
+            opened_at: Instant::now(),
+            action: None,
+            player: 0,
+            capturing: false,
+            notice: None,
+        }
+    }
+
+    pub fn items(&self) -> &'static [MenuItem] {
+        match self.page {
+            Page::Main => &MAIN_ITEMS,
+            Page::Settings => &SETTINGS_ITEMS,
+            Page::Keys => &KEYS_ITEMS,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        if let Some(notice) = &self.notice {
+            return notice;
+        }
+        match self.page {
+            Page::Main => i18n::tr(Msg::Paused),
+            Page::Settings => i18n::tr(Msg::Settings),
+            Page::Keys => i18n::tr(Msg::KeySettings),
+        }
+    }
+
+    // 菜单项显示的文字，设置项带上当前的值
+    pub fn labels(&self, settings: &Settings) -> Vec<String> {
+        let speed = &settings.speed;
+        let keys = &settings.keys;
+        self.items()
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| match item {
+                MenuItem::Resume => i18n::tr(Msg::Resume).to_string(),
+                MenuItem::Restart => i18n::tr(Msg::Restart).to_string(),
+                MenuItem::Settings => i18n::tr(Msg::Settings).to_string(),
+                MenuItem::Title => i18n::tr(Msg::BackToTitle).to_string(),
+                MenuItem::Quit => i18n::tr(Msg::Quit).to_string(),
+                MenuItem::Difficulty => {
+                    i18n::trf(Msg::MenuDifficulty, &[&speed.difficulty.label()])
+                }
+                MenuItem::Curve => i18n::trf(Msg::MenuCurve, &[&speed.curve.label()]),
+                MenuItem::Theme => i18n::trf(Msg::MenuTheme, &[&settings.theme_name()]),
+                MenuItem::Language => i18n::trf(Msg::MenuLanguage, &[&settings.locale.label()]),
+                MenuItem::Rivals => {
+                    i18n::trf(Msg::MenuRivals, &[&rival::rivals_label(&settings.rivals)])
+                }
+                MenuItem::Keys => i18n::tr(Msg::Keys).to_string(),
+                MenuItem::Back => i18n::tr(Msg::Back).to_string(),
+                MenuItem::KeyPlayer => i18n::trf(Msg::MenuPlayer, &[&(self.player + 1)]),
+                MenuItem::KeyPreset => {
+                    let preset = keys.preset(self.player);
+                    let label = preset.map_or(i18n::tr(Msg::PresetCustom), |p| p.label());
+                    i18n::trf(Msg::MenuPreset, &[&label])
+                }
+                MenuItem::Bind(action) if self.capturing && idx == self.selected => {
+                    i18n::trf(Msg::PressKey, &[&action.label()])
+                }
+                MenuItem::Bind(action) => {
+                    let names: Vec<String> = keys
+                        .keys(Slot::new(self.player, *action))
+                        .iter()
+                        .map(|key| keymap::key_label(*key))
+                        .collect();
+                    match names.is_empty() {
+                        true => i18n::trf(Msg::Unbound, &[&action.label()]),
+                        false => format!("{}: {}", action.label(), names.join(" / ")),
+                    }
+                }
+                MenuItem::KeyDefaults => i18n::tr(Msg::KeyDefaults).to_string(),
+            })
+            .collect()
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    // 暂停了多久
+    pub fn paused_for(&self) -> Duration {
+        self.opened_at.elapsed()
+    }
+
+    pub fn opened_at(&self) -> Instant {
+        self.opened_at
+    }
+
+    pub fn select(&mut self, idx: usize) {
+        if idx < self.items().len() && !self.capturing {
+            self.selected = idx;
+        }
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % self.items().len();
+    }
+
+    pub fn select_prev(&mut self) {
+        let len = self.items().len();
+        self.selected = (self.selected + len - 1) % len;
+    }
+
+    // 确认当前选中项
+    pub fn activate(&mut self, settings: &mut Settings) {
+        match self.items()[self.selected] {
+            MenuItem::Resume => self.action = Some(MenuAction::Resume),
+            MenuItem::Restart => self.action = Some(MenuAction::Restart),
+            MenuItem::Title => self.action = Some(MenuAction::Title),
+            MenuItem::Quit => self.action = Some(MenuAction::Quit),
+            MenuItem::Settings => {
+                self.page = Page::Settings;
+                self.selected = 0;
+            }
+            MenuItem::Keys => {
+                self.page = Page::Keys;
+                self.selected = 0;
+                self.player = 0;
+            }
+            MenuItem::Back => self.back(),
+            MenuItem::Difficulty
+            | MenuItem::Curve
+            | MenuItem::Theme
+            | MenuItem::Language
+            | MenuItem::Rivals
+            | MenuItem::KeyPlayer
+            | MenuItem::KeyPreset => self.change(settings, 1),
+            MenuItem::Bind(_) => {
+                self.capturing = true;
+                self.notice = None;
+            }
+            MenuItem::KeyDefaults => {
+                settings.keys = KeyMap::default();
+                self.notice = None;
+                self.action = Some(MenuAction::SaveKeys);
+            }
+        }
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capturing
+    }
+
+    // 等待按键时按下的键，绑定到选中的操作，Esc 取消
+    pub fn capture(&mut self, settings: &mut Settings, key: Key) {
+        self.capturing = false;
+        if key == Key::Escape {
+            return;
+        }
+        if let MenuItem::Bind(action) = self.items()[self.selected] {
+            match settings.keys.bind(Slot::new(self.player, action), key) {
+                Ok(()) => {
+                    self.notice = None;
+                    self.action = Some(MenuAction::SaveKeys);
+                }
+                Err(e) => self.notice = Some(e),
+            }
+        }
+    }
+
+    // 直接继续游戏
+    pub fn resume(&mut self) {
+        self.action = Some(MenuAction::Resume);
+    }
+
+    // 返回上一级，在主页面就是继续游戏
+    pub fn back(&mut self) {
+        match self.page {
+            Page::Main => self.action = Some(MenuAction::Resume),
+            Page::Settings => {
+                self.page = Page::Main;
+                self.selected = 0;
+            }
+            Page::Keys => {
+                self.page = Page::Settings;
+                self.selected = SETTINGS_ITEMS.len() - 2;
+                self.notice = None;
+            }
+        }
+    }
+
+    // 左右切换设置项的值
+    pub fn change(&mut self, settings: &mut Settings, step: i32) {
+        let speed = &mut settings.speed;
+        let cycle = |idx: usize, len: usize| (idx as i32 + step).rem_euclid(len as i32) as usize;
+        match self.items()[self.selected] {
+            MenuItem::Difficulty => {
+                let idx = DIFFICULTIES
+                    .iter()
+                    .position(|d| *d == speed.difficulty)
+                    .unwrap_or(0);
+                speed.difficulty = DIFFICULTIES[cycle(idx, DIFFICULTIES.len())];
+            }
+            MenuItem::Curve => {
+                let idx = SPEED_CURVES
+                    .iter()
+                    .position(|c| c.name() == speed.curve.name())
+                    .unwrap_or(0);
+                speed.curve = SPEED_CURVES[cycle(idx, SPEED_CURVES.len())];
+            }
+            MenuItem::Theme => settings.cycle_theme(step),
+            MenuItem::Language => {
+                settings.locale = settings.locale.cycle(step);
+                self.action = Some(MenuAction::SaveSettings);
+            }
+            MenuItem::Rivals => {
+                // 设置文件里写的组合不在选项里时从第一个开始
+                let idx = RIVAL_CHOICES
+                    .iter()
+                    .position(|choice| *choice == settings.rivals.as_slice())
+                    .map_or(0, |idx| cycle(idx, RIVAL_CHOICES.len()));
+                settings.rivals = RIVAL_CHOICES[idx].to_vec();
+                self.action = Some(MenuAction::SaveSettings);
+            }
+            MenuItem::KeyPlayer => self.player = cycle(self.player, PLAYERS),
+            MenuItem::KeyPreset => {
+                // 不是预设时从第一个开始
+                let preset = match settings.keys.preset(self.player) {
+                    Some(p) => {
+                        PRESETS[cycle(PRESETS.iter().position(|x| *x == p).unwrap(), PRESETS.len())]
+                    }
+                    None => PRESETS[0],
+                };
+                match settings.keys.try_preset(self.player, preset) {
+                    Ok(()) => {
+                        self.notice = None;
+                        self.action = Some(MenuAction::SaveKeys);
+                    }
+                    Err(e) => self.notice = Some(e),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn take_action(&mut self) -> Option<MenuAction> {
+        self.action.take()
+    }
+
+    // 菜单面板的位置，窗口中间
+    pub fn panel_rect(w: i32, h: i32) -> (i32, i32, i32, i32) {
+        (w / 4, h / 5, w / 2, 3 * h / 5)
+    }
+
+    // 第idx个菜单项的位置
+    pub fn item_rect(&self, idx: usize, w: i32, h: i32) -> (i32, i32, i32, i32) {
+        let (px, py, pw, ph) = PauseMenu::panel_rect(w, h);
+        let title_h = ph / 5;
+        // 项目多的页面每项矮一些
+        let rows = self.items().len().max(MAIN_ITEMS.len());
+        let item_h = (ph - title_h) / rows as i32;
+        (px, py + title_h + idx as i32 * item_h, pw, item_h)
+    }
+
+    // 鼠标位置对应的菜单项
+    pub fn item_at(&self, x: i32, y: i32, w: i32, h: i32) -> Option<usize> {
+        (0..self.items().len()).find(|idx| {
+            let (ix, iy, iw, ih) = self.item_rect(*idx, w, h);
+            x >= ix && x < ix + iw && y >= iy && y < iy + ih
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::difficulty::SpeedSetting;
+
+    #[test]
+    fn test_navigate() {
+        let mut settings = Settings::new(vec![String::from("经典"), String::from("暗色")]);
+        let mut menu = PauseMenu::new();
+        menu.select_prev();
+        assert_eq!(menu.items()[menu.selected()], MenuItem::Quit);
+        menu.select_next();
+        assert_eq!(menu.items()[menu.selected()], MenuItem::Resume);
+
+        // 进入设置，修改难度，返回
+        menu.select(2);
+        menu.activate(&mut settings);
+        assert_eq!(menu.items()[menu.selected()], MenuItem::Difficulty);
+        menu.activate(&mut settings);
+        assert_ne!(settings.speed, SpeedSetting::default());
+
+        // 切换主题
+        menu.select(2);
+        menu.change(&mut settings, -1);
+        assert_eq!(settings.theme_name(), "暗色");
+
+        // 切换语言交给主循环处理
+        menu.select(3);
+        menu.change(&mut settings, 1);
+        assert_eq!(settings.locale, crate::i18n::Locale::En);
+        assert_eq!(menu.take_action(), Some(MenuAction::SaveSettings));
+
+        // 切换对手
+        menu.select(4);
+        menu.change(&mut settings, -1);
+        assert_eq!(settings.rivals.len(), 3);
+        assert_eq!(menu.labels(&settings)[4], "对手: ◀ 贪吃+谨慎+拦截 ▶");
+        assert_eq!(menu.take_action(), Some(MenuAction::SaveSettings));
+        menu.back();
+        assert_eq!(menu.take_action(), None);
+        menu.back();
+        assert_eq!(menu.take_action(), Some(MenuAction::Resume));
+    }
+
+    #[test]
+    fn test_remap_keys() {
+        let mut settings = Settings::new(vec![String::from("经典")]);
+        let mut menu = PauseMenu::new();
+        menu.select(2);
+        menu.activate(&mut settings);
+        menu.select(5);
+        menu.activate(&mut settings);
+        assert_eq!(menu.title(), "按键设置");
+
+        // 选中“上”，按下 W 重新绑定
+        menu.select(2);
+        menu.activate(&mut settings);
+        assert!(menu.is_capturing());
+        assert_eq!(menu.labels(&settings)[2], "上: 请按键，Esc取消");
+        menu.capture(&mut settings, Key::from_char('w'));
+        assert_eq!(menu.labels(&settings)[2], "上: W");
+        assert_eq!(menu.take_action(), Some(MenuAction::SaveKeys));
+
+        // 和“下”冲突，不修改，标题显示原因
+        menu.activate(&mut settings);
+        menu.capture(&mut settings, Key::Down);
+        assert_eq!(menu.title(), "↓ 已用于 P1 下");
+        assert_eq!(menu.labels(&settings)[2], "上: W");
+
+        // 返回设置页面时选中按键
+        menu.back();
+        assert_eq!(menu.items()[menu.selected()], MenuItem::Keys);
+        assert_eq!(menu.title(), "设置");
+    }
+
+    #[test]
+    fn test_item_at() {
+        let menu = PauseMenu::new();
+        let (x, y, w, h) = menu.item_rect(1, 500, 500);
+        assert_eq!(menu.item_at(x + w / 2, y + h / 2, 500, 500), Some(1));
+        assert_eq!(menu.item_at(0, 0, 500, 500), None);
+    }
+}
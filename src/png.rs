@@ -0,0 +1,231 @@
+// PNG 编码，只支持8位RGB，截图使用
+// 压缩用固定哈夫曼表的 deflate，画面大多是纯色，压缩率足够
+
+// 编码一张图片，rgb 为逐行排列的像素数据，每个像素3个字节
+pub fn encode(width: u32, height: u32, rgb: &[u8]) -> Vec<u8> {
+    let row_len = width as usize * 3;
+
+    // 每行使用 Sub 过滤：和左边像素的差值，纯色区域变成0
+    let mut raw = Vec::with_capacity((row_len + 1) * height as usize);
+    for row in rgb.chunks(row_len).take(height as usize) {
+        raw.push(1);
+        for (i, byte) in row.iter().enumerate() {
+            let left = if i >= 3 { row[i - 3] } else { 0 };
+            raw.push(byte.wrapping_sub(left));
+        }
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[8, 2, 0, 0, 0]); // 8位，RGB，无隔行
+
+    // zlib：头、deflate 数据、adler32 校验
+    let mut idat = vec![0x78, 0x01];
+    idat.extend(deflate(&raw));
+    idat.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut png = vec![0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+    write_chunk(&mut png, b"IHDR", &ihdr);
+    write_chunk(&mut png, b"IDAT", &idat);
+    write_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+// 按位写入，低位在前
+struct BitWriter {
+    bytes: Vec<u8>,
+    bit: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn new() -> BitWriter {
+        BitWriter {
+            bytes: vec![],
+            bit: 0,
+            bits: 0,
+        }
+    }
+
+    fn write(&mut self, value: u32, len: u32) {
+        self.bit |= value << self.bits;
+        self.bits += len;
+        while self.bits >= 8 {
+            self.bytes.push(self.bit as u8);
+            self.bit >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    // 哈夫曼码高位在前
+    fn write_code(&mut self, code: u32, len: u32) {
+        let reversed = code.reverse_bits() >> (32 - len);
+        self.write(reversed, len);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.bit as u8);
+        }
+        self.bytes
+    }
+}
+
+const LEN_BASE: [u32; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LEN_EXTRA: [u32; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u32; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u32; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+const WINDOW: usize = 32768;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 32; // 查找重复时最多比较的位置数
+const HASH_BITS: u32 = 15;
+
+// 固定哈夫曼表的字面量/长度码
+fn write_symbol(out: &mut BitWriter, symbol: u32) {
+    match symbol {
+        0..=143 => out.write_code(0x30 + symbol, 8),
+        144..=255 => out.write_code(0x190 + symbol - 144, 9),
+        256..=279 => out.write_code(symbol - 256, 7),
+        _ => out.write_code(0xC0 + symbol - 280, 8),
+    }
+}
+
+// 找到 base 表里不大于 value 的最后一项
+fn find_code(base: &[u32], value: u32) -> usize {
+    base.iter().rposition(|b| *b <= value).unwrap_or(0)
+}
+
+fn hash(data: &[u8], i: usize) -> usize {
+    let v = (data[i] as u32) << 16 | (data[i + 1] as u32) << 8 | data[i + 2] as u32;
+    (v.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize
+}
+
+// 记录位置i，之后可以在这里找重复
+fn insert(data: &[u8], i: usize, head: &mut [usize], prev: &mut [usize]) {
+    if i + 3 <= data.len() {
+        let h = hash(data, i);
+        prev[i] = head[h];
+        head[h] = i;
+    }
+}
+
+// deflate 压缩，一个固定哈夫曼表的块
+pub fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter::new();
+    out.write(1, 1); // 最后一个块
+    out.write(1, 2); // 固定哈夫曼表
+
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; data.len()];
+
+    let mut i = 0;
+    while i < data.len() {
+        // 在窗口里找最长的重复
+        let (mut best_len, mut best_dist) = (0, 0);
+        if i + 3 <= data.len() {
+            let mut candidate = head[hash(data, i)];
+            let mut chain = 0;
+            let max_len = MAX_MATCH.min(data.len() - i);
+            while candidate != usize::MAX && i - candidate <= WINDOW && chain < MAX_CHAIN {
+                let len = (0..max_len)
+                    .take_while(|k| data[candidate + k] == data[i + k])
+                    .count();
+                if len > best_len {
+                    best_len = len;
+                    best_dist = i - candidate;
+                    if len == max_len {
+                        break;
+                    }
+                }
+                candidate = prev[candidate];
+                chain += 1;
+            }
+        }
+
+        if best_len >= 3 {
+            let code = find_code(&LEN_BASE, best_len as u32);
+            write_symbol(&mut out, 257 + code as u32);
+            out.write(best_len as u32 - LEN_BASE[code], LEN_EXTRA[code]);
+            let code = find_code(&DIST_BASE, best_dist as u32);
+            out.write_code(code as u32, 5);
+            out.write(best_dist as u32 - DIST_BASE[code], DIST_EXTRA[code]);
+            for k in i..i + best_len {
+                insert(data, k, &mut head, &mut prev);
+            }
+            i += best_len;
+        } else {
+            write_symbol(&mut out, data[i] as u32);
+            insert(data, i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+    write_symbol(&mut out, 256); // 块结束
+    out.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"123456789"), 0xCBF43926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+    }
+
+    #[test]
+    fn test_encode() {
+        let png = encode(2, 1, &[255, 0, 0, 0, 0, 255]);
+        assert_eq!(&png[1..4], b"PNG");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+        // 空数据只有块头和结束符
+        assert_eq!(deflate(&[]), vec![0x03, 0x00]);
+    }
+}
@@ -0,0 +1,364 @@
+use crate::canvas::{Align, Bitmap, Canvas, TextStyle};
+
+// 3x5 的点阵字体，每行3位，高位在左，小写字母画成大写
+// 其他字符（中文、表情）画成一个方框
+const FONT: [(char, [u8; 5]); 63] = [
+    (' ', [0b000, 0b000, 0b000, 0b000, 0b000]),
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('A', [0b010, 0b101, 0b111, 0b101, 0b101]),
+    ('B', [0b110, 0b101, 0b110, 0b101, 0b110]),
+    ('C', [0b011, 0b100, 0b100, 0b100, 0b011]),
+    ('D', [0b110, 0b101, 0b101, 0b101, 0b110]),
+    ('E', [0b111, 0b100, 0b110, 0b100, 0b111]),
+    ('F', [0b111, 0b100, 0b110, 0b100, 0b100]),
+    ('G', [0b011, 0b100, 0b101, 0b101, 0b011]),
+    ('H', [0b101, 0b101, 0b111, 0b101, 0b101]),
+    ('I', [0b111, 0b010, 0b010, 0b010, 0b111]),
+    ('J', [0b001, 0b001, 0b001, 0b101, 0b010]),
+    ('K', [0b101, 0b101, 0b110, 0b101, 0b101]),
+    ('L', [0b100, 0b100, 0b100, 0b100, 0b111]),
+    ('M', [0b101, 0b111, 0b111, 0b101, 0b101]),
+    ('N', [0b110, 0b101, 0b101, 0b101, 0b101]),
+    ('O', [0b010, 0b101, 0b101, 0b101, 0b010]),
+    ('P', [0b110, 0b101, 0b110, 0b100, 0b100]),
+    ('Q', [0b010, 0b101, 0b101, 0b110, 0b011]),
+    ('R', [0b110, 0b101, 0b110, 0b101, 0b101]),
+    ('S', [0b011, 0b100, 0b010, 0b001, 0b110]),
+    ('T', [0b111, 0b010, 0b010, 0b010, 0b010]),
+    ('U', [0b101, 0b101, 0b101, 0b101, 0b111]),
+    ('V', [0b101, 0b101, 0b101, 0b101, 0b010]),
+    ('W', [0b101, 0b101, 0b111, 0b111, 0b101]),
+    ('X', [0b101, 0b101, 0b010, 0b101, 0b101]),
+    ('Y', [0b101, 0b101, 0b010, 0b010, 0b010]),
+    ('Z', [0b111, 0b001, 0b010, 0b100, 0b111]),
+    ('.', [0b000, 0b000, 0b000, 0b000, 0b010]),
+    (',', [0b000, 0b000, 0b000, 0b010, 0b100]),
+    (':', [0b000, 0b010, 0b000, 0b010, 0b000]),
+    (';', [0b000, 0b010, 0b000, 0b010, 0b100]),
+    ('-', [0b000, 0b000, 0b111, 0b000, 0b000]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+    ('/', [0b001, 0b001, 0b010, 0b100, 0b100]),
+    ('%', [0b101, 0b001, 0b010, 0b100, 0b101]),
+    ('(', [0b010, 0b100, 0b100, 0b100, 0b010]),
+    (')', [0b010, 0b001, 0b001, 0b001, 0b010]),
+    ('[', [0b110, 0b100, 0b100, 0b100, 0b110]),
+    (']', [0b011, 0b001, 0b001, 0b001, 0b011]),
+    ('<', [0b001, 0b010, 0b100, 0b010, 0b001]),
+    ('>', [0b100, 0b010, 0b001, 0b010, 0b100]),
+    ('!', [0b010, 0b010, 0b010, 0b000, 0b010]),
+    ('?', [0b110, 0b001, 0b010, 0b000, 0b010]),
+    ('=', [0b000, 0b111, 0b000, 0b111, 0b000]),
+    ('*', [0b000, 0b101, 0b010, 0b101, 0b000]),
+    ('#', [0b101, 0b111, 0b101, 0b111, 0b101]),
+    ('_', [0b000, 0b000, 0b000, 0b000, 0b111]),
+    ('|', [0b010, 0b010, 0b010, 0b010, 0b010]),
+    ('\'', [0b010, 0b010, 0b000, 0b000, 0b000]),
+    ('"', [0b101, 0b101, 0b000, 0b000, 0b000]),
+    ('&', [0b010, 0b101, 0b010, 0b101, 0b011]),
+    ('^', [0b010, 0b101, 0b000, 0b000, 0b000]),
+    ('~', [0b000, 0b011, 0b110, 0b000, 0b000]),
+];
+
+// 字体每个点的大小，字号14时为2
+fn font_unit(size: i32) -> i32 {
+    ((size + 3) / 7).max(1)
+}
+
+// 字符占的宽度（点数，含间隔），中文等宽字符画成宽一些的方框
+fn glyph_advance(c: char) -> i32 {
+    if c.is_ascii() {
+        4
+    } else {
+        6
+    }
+}
+
+// 画在内存里的 RGBA 图片，不需要显示器，命令行导出录像和截图测试使用
+pub struct RgbaCanvas {
+    width: i32,
+    height: i32,
+    data: Vec<u8>,
+}
+
+impl RgbaCanvas {
+    pub fn new(width: i32, height: i32) -> RgbaCanvas {
+        RgbaCanvas {
+            width,
+            height,
+            data: vec![0; (width.max(0) * height.max(0) * 4) as usize],
+        }
+    }
+
+    // 下面三个只有测试里检查画出来的图片时使用
+    #[cfg(test)]
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    #[cfg(test)]
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    // 去掉透明通道，png 和 gif 使用
+    pub fn to_rgb(&self) -> Vec<u8> {
+        self.data
+            .chunks_exact(4)
+            .flat_map(|p| [p[0], p[1], p[2]])
+            .collect()
+    }
+
+    #[cfg(test)]
+    pub fn pixel(&self, x: i32, y: i32) -> Option<u32> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return None;
+        }
+        let i = ((y * self.width + x) * 4) as usize;
+        Some(u32::from_be_bytes([
+            0,
+            self.data[i],
+            self.data[i + 1],
+            self.data[i + 2],
+        ]))
+    }
+
+    // 按透明度混合一个像素，超出范围的忽略
+    fn blend(&mut self, x: i32, y: i32, rgb: [u8; 3], alpha: u8) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height || alpha == 0 {
+            return;
+        }
+        let i = ((y * self.width + x) * 4) as usize;
+        let a = alpha as u32;
+        for (k, c) in rgb.iter().enumerate() {
+            let old = self.data[i + k] as u32;
+            self.data[i + k] = ((*c as u32 * a + old * (255 - a) + 127) / 255) as u8;
+        }
+        self.data[i + 3] = 255;
+    }
+
+    fn set(&mut self, x: i32, y: i32, color: u32) {
+        let [_, r, g, b] = color.to_be_bytes();
+        self.blend(x, y, [r, g, b], 255);
+    }
+
+    // 像素中心在图形里面的就画上，inside 的参数为像素中心
+    fn fill_where(
+        &mut self,
+        (x0, y0, x1, y1): (i32, i32, i32, i32),
+        color: u32,
+        inside: impl Fn(f64, f64) -> bool,
+    ) {
+        for y in y0.max(0)..y1.min(self.height) {
+            for x in x0.max(0)..x1.min(self.width) {
+                if inside(x as f64 + 0.5, y as f64 + 0.5) {
+                    self.set(x, y, color);
+                }
+            }
+        }
+    }
+
+    fn fill_polygon(&mut self, points: &[(f64, f64)], color: u32) {
+        if points.len() < 3 {
+            return;
+        }
+        let min = |f: fn(&(f64, f64)) -> f64| points.iter().map(f).fold(f64::MAX, f64::min);
+        let max = |f: fn(&(f64, f64)) -> f64| points.iter().map(f).fold(f64::MIN, f64::max);
+        let bounds = (
+            min(|p| p.0).floor() as i32,
+            min(|p| p.1).floor() as i32,
+            max(|p| p.0).ceil() as i32,
+            max(|p| p.1).ceil() as i32,
+        );
+        // 奇偶规则：往右的射线和边相交奇数次的在里面
+        self.fill_where(bounds, color, |x, y| {
+            let mut inside = false;
+            let mut j = points.len() - 1;
+            for i in 0..points.len() {
+                let ((xi, yi), (xj, yj)) = (points[i], points[j]);
+                if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+                    inside = !inside;
+                }
+                j = i;
+            }
+            inside
+        });
+    }
+}
+
+impl Canvas for RgbaCanvas {
+    fn rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: u32) {
+        self.fill_where((x, y, x + w, y + h), color, |_, _| true);
+    }
+
+    fn circle(&mut self, x: i32, y: i32, d: i32, color: u32) {
+        let r = d as f64 / 2.0;
+        let (cx, cy) = (x as f64 + r, y as f64 + r);
+        self.fill_where((x, y, x + d, y + d), color, |px, py| {
+            (px - cx).powi(2) + (py - cy).powi(2) <= r * r
+        });
+    }
+
+    fn polygon(&mut self, points: &[(i32, i32)], color: u32) {
+        let points: Vec<(f64, f64)> = points.iter().map(|(x, y)| (*x as f64, *y as f64)).collect();
+        self.fill_polygon(&points, color);
+    }
+
+    // 粗线画成一个长方形
+    fn line(&mut self, from: (i32, i32), to: (i32, i32), width: i32, color: u32) {
+        let (dx, dy) = ((to.0 - from.0) as f64, (to.1 - from.1) as f64);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            return;
+        }
+        let half = width.max(1) as f64 / 2.0;
+        let (nx, ny) = (-dy / len * half, dx / len * half);
+        let (ax, ay) = (from.0 as f64 + 0.5, from.1 as f64 + 0.5);
+        let (bx, by) = (to.0 as f64 + 0.5, to.1 as f64 + 0.5);
+        self.fill_polygon(
+            &[
+                (ax + nx, ay + ny),
+                (bx + nx, by + ny),
+                (bx - nx, by - ny),
+                (ax - nx, ay - ny),
+            ],
+            color,
+        );
+    }
+
+    fn ring(&mut self, rect: (i32, i32, i32, i32), width: i32, dotted: bool, color: u32) {
+        let (x, y, w, h) = rect;
+        let (rx, ry) = (w as f64 / 2.0, h as f64 / 2.0);
+        if rx <= 0.0 || ry <= 0.0 {
+            return;
+        }
+        let (cx, cy) = (x as f64 + rx, y as f64 + ry);
+        let half = width.max(1) as f64 / 2.0;
+        let radius = (rx + ry) / 2.0;
+        let pad = width.max(1);
+        self.fill_where(
+            (x - pad, y - pad, x + w + pad, y + h + pad),
+            color,
+            |px, py| {
+                let (dx, dy) = ((px - cx) / rx, (py - cy) / ry);
+                // 到椭圆边的距离，按平均半径换算成像素
+                let distance = ((dx * dx + dy * dy).sqrt() - 1.0) * radius;
+                // 虚线：沿着边每隔2个像素画2个像素
+                let dash = (dy.atan2(dx) * radius / 2.0).floor() as i64;
+                distance.abs() <= half && !(dotted && dash % 2 != 0)
+            },
+        );
+    }
+
+    fn text(&mut self, text: &str, rect: (i32, i32, i32, i32), align: Align, style: &TextStyle) {
+        let (x, y, w, h) = rect;
+        let unit = font_unit(style.size);
+        let (tw, _) = self.measure(text, style);
+        let mut left = match align {
+            Align::Left => x,
+            Align::Center => x + (w - tw) / 2,
+        };
+        let top = y + (h - 5 * unit) / 2;
+        // 粗体每个点往右多画一点
+        let bold = if style.bold { unit / 2 + 1 } else { 0 };
+        for c in text.chars() {
+            let upper = c.to_ascii_uppercase();
+            match FONT.iter().find(|(g, _)| *g == upper) {
+                Some((_, rows)) => {
+                    for (row, bits) in rows.iter().enumerate() {
+                        for col in 0..3 {
+                            if bits & (0b100 >> col) != 0 {
+                                self.rect(
+                                    left + col * unit,
+                                    top + row as i32 * unit,
+                                    unit + bold,
+                                    unit,
+                                    style.color,
+                                );
+                            }
+                        }
+                    }
+                }
+                None => {
+                    let bw = (glyph_advance(c) - 1) * unit;
+                    self.rect(left, top, bw, unit, style.color);
+                    self.rect(left, top + 4 * unit, bw, unit, style.color);
+                    self.rect(left, top, unit, 5 * unit, style.color);
+                    self.rect(left + bw - unit, top, unit, 5 * unit, style.color);
+                }
+            }
+            left += glyph_advance(c) * unit;
+        }
+    }
+
+    fn measure(&mut self, text: &str, style: &TextStyle) -> (i32, i32) {
+        let unit = font_unit(style.size);
+        let advance: i32 = text.chars().map(glyph_advance).sum();
+        ((advance - 1).max(0) * unit, 7 * unit)
+    }
+
+    // 最近邻缩放，按透明度混合
+    fn image(&mut self, image: &Bitmap, x: i32, y: i32, w: i32, h: i32) {
+        for py in 0..h {
+            for px in 0..w {
+                let sx = (px * image.width / w.max(1)).min(image.width - 1);
+                let sy = (py * image.height / h.max(1)).min(image.height - 1);
+                let i = ((sy * image.width + sx) * 4) as usize;
+                let p = &image.data[i..i + 4];
+                self.blend(x + px, y + py, [p[0], p[1], p[2]], p[3]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shapes() {
+        let mut canvas = RgbaCanvas::new(10, 10);
+        canvas.rect(-5, -5, 8, 8, 0xFF0000);
+        assert_eq!(canvas.pixel(2, 2), Some(0xFF0000));
+        assert_eq!(canvas.pixel(3, 3), Some(0x000000));
+        assert_eq!(canvas.pixel(10, 0), None);
+
+        canvas.circle(4, 4, 6, 0x00FF00);
+        assert_eq!(canvas.pixel(7, 7), Some(0x00FF00));
+        assert_eq!(canvas.pixel(4, 4), Some(0x000000)); // 外接正方形的角不在圆里
+
+        canvas.polygon(&[(0, 9), (9, 9), (9, 10), (0, 10)], 0x0000FF);
+        assert_eq!(canvas.pixel(5, 9), Some(0x0000FF));
+
+        let half = Bitmap::new(&[255, 255, 255, 128], 1, 1, 4).unwrap();
+        canvas.image(&half, 0, 5, 2, 2);
+        assert_eq!(canvas.pixel(1, 6), Some(0x808080));
+        assert_eq!(canvas.to_rgb().len(), 300);
+    }
+
+    #[test]
+    fn test_text() {
+        let style = TextStyle {
+            font: "helvetica",
+            bold: false,
+            size: 7,
+            color: 0xFFFFFF,
+        };
+        let mut canvas = RgbaCanvas::new(20, 7);
+        assert_eq!(canvas.measure("10", &style), (7, 7));
+        assert_eq!(canvas.measure("分", &style), (5, 7));
+        canvas.text("1", (0, 0, 3, 7), Align::Left, &style);
+        // "1" 的第一行是 010
+        assert_eq!(canvas.pixel(0, 1), Some(0x000000));
+        assert_eq!(canvas.pixel(1, 1), Some(0xFFFFFF));
+        assert_eq!(canvas.pixel(1, 5), Some(0xFFFFFF));
+    }
+}
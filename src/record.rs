@@ -0,0 +1,107 @@
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use crate::{
+    consts,
+    gif::GifWriter,
+    png,
+    scene::{Replay, Scene},
+    utils,
+};
+
+// 截图和录制的快捷键，按键时记下，主循环里处理
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Capture {
+    Screenshot,      // 保存当前画面为png
+    ToggleRecording, // 开始/停止录制gif
+}
+
+// 录制中的一局：画面写入gif，每一步的状态写入同名的 .replay
+pub struct Recorder {
+    path: PathBuf,
+    gif: GifWriter<BufWriter<File>>,
+    replay: Replay,
+    last_frame: Option<Instant>,
+}
+
+impl Recorder {
+    pub fn start(path: &Path, width: u16, height: u16, replay: Replay) -> Result<Recorder, String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let gif = GifWriter::new(BufWriter::new(file), width, height).map_err(|e| e.to_string())?;
+        Ok(Recorder {
+            path: path.to_path_buf(),
+            gif,
+            replay,
+            last_frame: None,
+        })
+    }
+
+    // 距离上一帧超过录制间隔时需要录一帧
+    pub fn is_due(&self, now: Instant) -> bool {
+        self.last_frame.map_or(true, |last| {
+            now.duration_since(last) >= consts::RECORD_INTERVAL
+        })
+    }
+
+    pub fn add_frame(&mut self, rgb: &[u8], now: Instant) -> Result<(), String> {
+        self.last_frame = Some(now);
+        let delay = (consts::RECORD_INTERVAL.as_millis() / 10) as u16;
+        self.gif.frame(rgb, delay).map_err(|e| e.to_string())
+    }
+
+    // 记录移动一步后的状态
+    pub fn add_scene(&mut self, scene: Scene) {
+        self.replay.scenes.push(scene);
+    }
+
+    // 写完gif和录像，返回gif的路径
+    pub fn finish(self) -> Result<PathBuf, String> {
+        self.gif.finish().map_err(|e| e.to_string())?;
+        self.replay.save(&self.path.with_extension("replay"))?;
+        Ok(self.path)
+    }
+}
+
+pub fn save_png(path: &Path, width: u32, height: u32, rgb: &[u8]) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, png::encode(width, height, rgb))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// 截图和录像默认保存的位置：存档目录/dir/snake-时间戳.ext
+pub fn capture_path(dir: &str, ext: &str) -> PathBuf {
+    utils::data_dir()
+        .join(dir)
+        .join(format!("snake-{}.{}", utils::timestamp(), ext))
+}
+
+// 把录像画成gif，每一步显示移动一格的时间
+pub fn replay_to_gif(
+    path: &Path,
+    replay: &Replay,
+    width: u16,
+    height: u16,
+    mut render: impl FnMut(&Scene) -> Vec<u8>,
+) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let file = File::create(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut gif = GifWriter::new(BufWriter::new(file), width, height).map_err(|e| e.to_string())?;
+    for scene in replay.scenes.iter() {
+        let rgb = render(scene);
+        let delay = (scene.interval * 100.0).round().max(1.0) as u16;
+        gif.frame(&rgb, delay).map_err(|e| e.to_string())?;
+    }
+    gif.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
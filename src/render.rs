@@ -0,0 +1,970 @@
+use std::{collections::HashMap, path::PathBuf, rc::Rc};
+
+use crate::{
+    board::{Board, Viewport},
+    canvas::{Align, Bitmap, Canvas, TextStyle},
+    consts,
+    damage::{self, Sprite},
+    effect::EffectKind,
+    food::Food,
+    hud::{self, HudPlacement, HudStats},
+    motion,
+    pause::PauseMenu,
+    scene::Scene,
+    shape,
+    snake::{Direction, Point},
+    theme::{FoodSprite, HeadStyle, Theme},
+    tileset::{self, Tile},
+};
+
+// 一条蛇这一帧的样子，玩家的蛇和电脑控制的蛇都这样画
+pub struct SnakeFrame {
+    pub points: Vec<Point>,    // 蛇占的格子
+    pub positions: Vec<Point>, // 插值后每节画的位置
+    pub direction: Direction,
+    pub target: (i32, i32), // 眼睛看向的位置
+    pub color: Option<u32>, // 电脑控制的蛇整条一种颜色，玩家的蛇按主题和图块画
+}
+
+impl SnakeFrame {
+    fn new(
+        points: &[Point],
+        last_tail: &Point,
+        direction: &Direction,
+        scene: &Scene,
+        progress: f64,
+        color: Option<u32>,
+    ) -> SnakeFrame {
+        let (points, positions) =
+            motion::render_segments(points, last_tail, progress, &scene.portals);
+
+        // 眼睛看向最近的食物，没有食物时看前面
+        let (dx, dy) = direction.offset();
+        let half = consts::BODY_SIZE / 2;
+        let (cx, cy) = (positions[0].x() + half, positions[0].y() + half);
+        let target = scene
+            .foods
+            .iter()
+            .map(|(food, _)| (food.x() + half, food.y() + half))
+            .min_by_key(|(fx, fy)| (fx - cx).abs() + (fy - cy).abs())
+            .unwrap_or((cx + dx * consts::BODY_SIZE, cy + dy * consts::BODY_SIZE));
+
+        SnakeFrame {
+            points,
+            positions,
+            direction: direction.clone(),
+            target,
+            color,
+        }
+    }
+}
+
+// 一帧画面需要的数据，每帧更新，窗口的绘制回调从这里读取
+pub struct Frame {
+    pub snake: SnakeFrame,
+    pub rivals: Vec<SnakeFrame>, // 电脑控制的蛇
+    pub walls: Vec<Point>,       // 关卡地图里的障碍物，画在背景里
+    pub obstacles: Vec<Point>,   // 生存模式出现的障碍物，每帧画
+    pub portals: Vec<(Point, Point)>,
+    pub foods: Vec<Food>,
+    pub hud: Vec<String>, // 状态栏每一项的文字
+    pub ghost: bool,
+    pub shield: bool,
+    pub magnet: bool,
+    pub tongue: f64,
+    pub pause: Option<(PauseMenu, Vec<String>)>, // 暂停菜单和选项文字
+    pub theme: Rc<Theme>,
+}
+
+impl Frame {
+    // 从游戏状态计算一帧，progress 为这一格走了多少
+    pub fn new(
+        scene: &Scene,
+        progress: f64,
+        pause: Option<(PauseMenu, Vec<String>)>,
+        theme: Rc<Theme>,
+        best: i32,
+    ) -> Frame {
+        let snake = SnakeFrame::new(
+            &scene.points,
+            &scene.last_tail,
+            &scene.direction,
+            scene,
+            progress,
+            None,
+        );
+        let rivals = scene
+            .rivals
+            .iter()
+            .map(|rival| {
+                SnakeFrame::new(
+                    &rival.points,
+                    &rival.last_tail,
+                    &rival.direction,
+                    scene,
+                    progress,
+                    Some(rival.color),
+                )
+            })
+            .collect();
+
+        // 状态栏：得分、长度、时间、速度、道具剩余时间等
+        let stats = HudStats {
+            score: scene.score,
+            mode: scene
+                .stage
+                .map(|s| s.label())
+                .into_iter()
+                .chain(scene.timer.map(|t| t.label()))
+                .collect(),
+            length: scene.points.len(),
+            elapsed: scene.elapsed,
+            speed: 1.0 / scene.interval,
+            eaten: scene.eaten,
+            best: best.max(scene.score),
+            effects: scene
+                .effects
+                .iter()
+                .map(|(kind, remaining)| format!("{} {:.1}s", kind.icon(), remaining.as_secs_f64()))
+                .collect(),
+        };
+        let is_active = |kind: EffectKind| scene.effects.iter().any(|(k, _)| *k == kind);
+
+        Frame {
+            snake,
+            rivals,
+            walls: scene.walls.clone(),
+            obstacles: scene.obstacles.clone(),
+            portals: scene.portals.clone(),
+            foods: scene
+                .foods
+                .iter()
+                .map(|(p, kind)| Food::new(p.x(), p.y(), *kind))
+                .collect(),
+            hud: stats.items(),
+            ghost: is_active(EffectKind::Ghost),
+            shield: is_active(EffectKind::Shield),
+            magnet: is_active(EffectKind::Magnet),
+            tongue: shape::tongue(scene.elapsed),
+            pause,
+            theme,
+        }
+    }
+
+    // 这一帧画出来的东西和所在的区域，用来和上一帧比较
+    // 区域要包含画出来的全部内容
+    pub fn sprites(&self, board: &Board, placement: HudPlacement) -> Vec<Sprite> {
+        let cell = |p: &Point| (p.x(), p.y(), consts::BODY_SIZE, consts::BODY_SIZE);
+        let mut sprites = vec![Sprite::new(&(&self.hud, placement), placement.rect(board))];
+
+        for obstacle in self.obstacles.iter() {
+            sprites.push(Sprite::new(&("obstacle", obstacle), cell(obstacle)));
+        }
+
+        for (idx, (a, b)) in self.portals.iter().enumerate() {
+            sprites.push(Sprite::new(&("portal", idx, a), cell(a)));
+            sprites.push(Sprite::new(&("portal", idx, b), cell(b)));
+        }
+
+        for food in self.foods.iter() {
+            sprites.push(Sprite::new(
+                &(food.x(), food.y(), food.kind()),
+                (food.x(), food.y(), consts::BODY_SIZE, consts::BODY_SIZE),
+            ));
+        }
+
+        for rival in self.rivals.iter() {
+            self.snake_sprites(rival, 0.0, false, &mut sprites);
+        }
+        self.snake_sprites(&self.snake, self.tongue, self.ghost, &mut sprites);
+
+        let head = &self.snake.positions[0];
+        if self.shield {
+            sprites.push(Sprite::new(
+                &("shield", head),
+                (
+                    head.x() - 5,
+                    head.y() - 5,
+                    consts::BODY_SIZE + 10,
+                    consts::BODY_SIZE + 10,
+                ),
+            ));
+        }
+        if self.magnet {
+            let radius = consts::MAGNET_RADIUS * consts::BODY_SIZE;
+            sprites.push(Sprite::new(
+                &("magnet", head),
+                (
+                    head.x() + consts::BODY_SIZE / 2 - radius - 1,
+                    head.y() + consts::BODY_SIZE / 2 - radius - 1,
+                    2 * radius + 2,
+                    2 * radius + 2,
+                ),
+            ));
+        }
+        if let Some((menu, labels)) = &self.pause {
+            sprites.push(Sprite::new(
+                &(menu.title(), menu.selected(), labels),
+                PauseMenu::panel_rect(board.width(), board.height()),
+            ));
+        }
+        sprites
+    }
+
+    // 一条蛇每一节的区域
+    fn snake_sprites(
+        &self,
+        snake: &SnakeFrame,
+        tongue: f64,
+        ghost: bool,
+        sprites: &mut Vec<Sprite>,
+    ) {
+        let cell = |p: &Point| (p.x(), p.y(), consts::BODY_SIZE, consts::BODY_SIZE);
+        let positions = &snake.positions;
+        let len = positions.len();
+        for (idx, point) in positions.iter().enumerate() {
+            let tile = tileset::segment_tile(&snake.points, idx, &snake.direction);
+            if idx == 0 {
+                // 舌头会伸出格子外面
+                let half = consts::BODY_SIZE / 2;
+                sprites.push(Sprite::new(
+                    &(
+                        point,
+                        tile,
+                        snake.target,
+                        snake.color,
+                        tongue.to_bits(),
+                        ghost,
+                    ),
+                    (
+                        point.x() - half,
+                        point.y() - half,
+                        2 * consts::BODY_SIZE,
+                        2 * consts::BODY_SIZE,
+                    ),
+                ));
+                continue;
+            }
+            // 身体和前一节之间的连接也画在这一节里
+            let prev = &positions[idx - 1];
+            let rect = damage::bounding_box(&[cell(point), cell(prev)]);
+            sprites.push(Sprite::new(
+                &(
+                    point,
+                    prev,
+                    tile,
+                    snake
+                        .color
+                        .unwrap_or(self.theme.body_color(idx - 1, len - 1)),
+                    shape::taper(idx, len).to_bits(),
+                    shape::taper(idx - 1, len).to_bits(),
+                    ghost,
+                ),
+                if rect.2 > 2 * consts::BODY_SIZE || rect.3 > 2 * consts::BODY_SIZE {
+                    cell(point) // 穿墙时不连起来
+                } else {
+                    rect
+                },
+            ));
+        }
+    }
+}
+
+// 传送门的颜色，按关卡里的顺序循环使用
+const PORTAL_COLORS: [u32; 4] = [0x00BCD4, 0xFF9800, 0x9C27B0, 0x4CAF50];
+
+// 主题字体的文字样式
+fn text_style(theme: &Theme, bold: bool, size: i32, color: u32) -> TextStyle<'_> {
+    TextStyle {
+        font: &theme.font,
+        bold,
+        size,
+        color,
+    }
+}
+
+// 变浅的颜色，和 FLTK 的 lighter 一样和白色按 0.67:0.33 混合
+fn lighter(rgb: u32) -> u32 {
+    let [_, r, g, b] = rgb.to_be_bytes();
+    let mix = |c: u8| (c as f32 * 0.67 + 255.0 * 0.33) as u8;
+    u32::from_be_bytes([0, mix(r), mix(g), mix(b)])
+}
+
+// 画一整帧：背景、状态栏和游戏画面，截图和测试使用
+pub fn draw_all(
+    canvas: &mut impl Canvas,
+    frame: &Frame,
+    board: &Board,
+    images: &HashMap<PathBuf, Option<Bitmap>>,
+    tiles: &HashMap<Tile, Bitmap>,
+    width: i32,
+    height: i32,
+) {
+    let (vp, placement) = hud::fit(board, width, height);
+    draw_background(canvas, frame, board, tiles, width, height);
+    draw_hud(canvas, &frame.hud, placement, board, &vp, &frame.theme);
+    draw_frame(canvas, frame, board, &vp, images, tiles);
+}
+
+// 边框、状态栏、棋盘背景和关卡地图里的障碍物
+pub fn draw_background(
+    canvas: &mut impl Canvas,
+    frame: &Frame,
+    board: &Board,
+    tiles: &HashMap<Tile, Bitmap>,
+    width: i32,
+    height: i32,
+) {
+    let (vp, placement) = hud::fit(board, width, height);
+    let theme = &frame.theme;
+    canvas.rect(0, 0, width, height, theme.border);
+    let (bx, by, bw, bh) = vp.rect(0, 0, board.width(), board.height());
+    canvas.rect(bx, by, bw, bh, theme.background);
+    // 状态栏和棋盘之间留一条边框颜色的线
+    let (hx, hy, hw, hh) = placement.rect(board);
+    let (x, y, w, h) = vp.rect(hx, hy, hw, hh);
+    let line = vp.len(2);
+    match placement {
+        HudPlacement::Top => canvas.rect(x, y, w, h - line, theme.background),
+        HudPlacement::Side => canvas.rect(x + line, y, w - line, h, theme.background),
+    }
+    for wall in frame.walls.iter() {
+        draw_obstacle(canvas, tiles, &vp, theme, wall);
+    }
+}
+
+// 障碍物优先用图块，没有时用边框的颜色，和棋盘外面一样是走不过去的地方
+fn draw_obstacle(
+    canvas: &mut impl Canvas,
+    tiles: &HashMap<Tile, Bitmap>,
+    vp: &Viewport,
+    theme: &Theme,
+    obstacle: &Point,
+) {
+    if draw_tile(
+        canvas,
+        tiles,
+        &Tile::Obstacle,
+        vp,
+        obstacle.x(),
+        obstacle.y(),
+    ) {
+        return;
+    }
+    let (x, y, w, h) = vp.rect(
+        obstacle.x() + 1,
+        obstacle.y() + 1,
+        consts::BODY_SIZE - 2,
+        consts::BODY_SIZE - 2,
+    );
+    canvas.rect(x, y, w, h, theme.border);
+}
+
+// 状态栏，一行（右边时一列）放不下时缩小字号
+pub fn draw_hud(
+    canvas: &mut impl Canvas,
+    items: &[String],
+    placement: HudPlacement,
+    board: &Board,
+    vp: &Viewport,
+    theme: &Theme,
+) {
+    let (hx, hy, hw, hh) = placement.rect(board);
+    let (x, y, w, h) = vp.rect(hx, hy, hw, hh);
+    let pad = vp.len(6);
+    let (x, y, w, h) = (x + pad, y + pad / 2, w - 2 * pad, h - pad);
+    let mut size = vp.len(14);
+    loop {
+        let style = text_style(theme, false, size, theme.text);
+        let widths: Vec<i32> = items
+            .iter()
+            .map(|item| canvas.measure(item, &style).0)
+            .collect();
+        let line = size * 3 / 2;
+        let positions = match placement {
+            HudPlacement::Top => hud::flow(&widths, w, 2 * pad),
+            HudPlacement::Side => (0..items.len()).map(|row| (row, 0)).collect(),
+        };
+        let rows = positions.last().map_or(1, |(row, _)| *row as i32 + 1);
+        let fits = rows * line <= h && widths.iter().all(|iw| *iw <= w);
+        if fits || size <= consts::HUD_MIN_FONT {
+            // 上方的状态栏竖直居中，右边的从上往下排
+            let top = match placement {
+                HudPlacement::Top => y + (h - rows * line).max(0) / 2,
+                HudPlacement::Side => y,
+            };
+            for (item, (iw, (row, ix))) in items.iter().zip(widths.iter().zip(positions)) {
+                let rect = (x + ix, top + row as i32 * line, *iw, line);
+                canvas.text(item, rect, Align::Left, &style);
+            }
+            return;
+        }
+        size -= 1;
+    }
+}
+
+// 在格子(x, y)绘制图块，图块集里没有这个图块时返回false
+fn draw_tile(
+    canvas: &mut impl Canvas,
+    tiles: &HashMap<Tile, Bitmap>,
+    tile: &Tile,
+    vp: &Viewport,
+    x: i32,
+    y: i32,
+) -> bool {
+    match tiles.get(tile) {
+        Some(image) => {
+            let (x, y, w, h) = vp.rect(x, y, consts::BODY_SIZE, consts::BODY_SIZE);
+            canvas.image(image, x, y, w, h);
+            true
+        }
+        None => false,
+    }
+}
+
+// 绘制一帧，不在重画区域里的食物和身体跳过
+pub fn draw_frame(
+    canvas: &mut impl Canvas,
+    frame: &Frame,
+    board: &Board,
+    vp: &Viewport,
+    images: &HashMap<PathBuf, Option<Bitmap>>,
+    tiles: &HashMap<Tile, Bitmap>,
+) {
+    let theme = &frame.theme;
+    let in_damage = |canvas: &mut dyn Canvas, x: i32, y: i32, w: i32, h: i32| {
+        let (x, y, w, h) = vp.rect(x, y, w, h);
+        canvas.is_visible(x, y, w, h)
+    };
+
+    // 关卡地图里的障碍物在背景里，这里只画生存模式出现的
+    for obstacle in frame.obstacles.iter() {
+        if in_damage(
+            canvas,
+            obstacle.x(),
+            obstacle.y(),
+            consts::BODY_SIZE,
+            consts::BODY_SIZE,
+        ) {
+            draw_obstacle(canvas, tiles, vp, theme, obstacle);
+        }
+    }
+
+    // 传送门画成圆圈，一对一种颜色
+    for (idx, pair) in frame.portals.iter().enumerate() {
+        let color = PORTAL_COLORS[idx % PORTAL_COLORS.len()];
+        for portal in [&pair.0, &pair.1] {
+            if !in_damage(
+                canvas,
+                portal.x(),
+                portal.y(),
+                consts::BODY_SIZE,
+                consts::BODY_SIZE,
+            ) {
+                continue;
+            }
+            let rect = vp.rect(
+                portal.x() + 2,
+                portal.y() + 2,
+                consts::BODY_SIZE - 4,
+                consts::BODY_SIZE - 4,
+            );
+            canvas.ring(rect, vp.len(4), false, color);
+        }
+    }
+
+    // 绘制食物，优先使用图块，图片读取失败时用默认字符
+    for food in frame.foods.iter() {
+        if !in_damage(
+            canvas,
+            food.x(),
+            food.y(),
+            consts::BODY_SIZE,
+            consts::BODY_SIZE,
+        ) {
+            continue;
+        }
+        let tile = Tile::Food(food.kind());
+        if draw_tile(canvas, tiles, &tile, vp, food.x(), food.y()) {
+            continue;
+        }
+        let (x, y, w, h) = vp.rect(food.x(), food.y(), consts::BODY_SIZE, consts::BODY_SIZE);
+        let glyph = match theme.food_sprite(food.kind()) {
+            FoodSprite::Image(path) => {
+                if let Some(Some(image)) = images.get(&path) {
+                    canvas.image(image, x, y, w, h);
+                    continue;
+                }
+                food.kind().glyph().to_string()
+            }
+            FoodSprite::Glyph(glyph) => glyph,
+        };
+        let style = text_style(theme, false, vp.len(20), theme.text);
+        canvas.text(&glyph, (x, y, w, h), Align::Center, &style);
+    }
+
+    // 先画电脑控制的蛇，玩家的蛇盖在上面
+    for rival in frame.rivals.iter() {
+        draw_snake(canvas, rival, 0.0, false, theme, vp, tiles);
+    }
+    draw_snake(
+        canvas,
+        &frame.snake,
+        frame.tongue,
+        frame.ghost,
+        theme,
+        vp,
+        tiles,
+    );
+
+    let head = &frame.snake.positions[0];
+    // 护盾：蛇头外面一圈金色
+    if frame.shield {
+        let rect = vp.rect(
+            head.x() - 3,
+            head.y() - 3,
+            consts::BODY_SIZE + 6,
+            consts::BODY_SIZE + 6,
+        );
+        canvas.ring(rect, vp.len(3), false, 0xFFC107);
+    }
+    // 磁铁：蛇头外面一圈虚线，表示吸引范围
+    if frame.magnet {
+        let radius = consts::MAGNET_RADIUS * consts::BODY_SIZE;
+        let rect = vp.rect(
+            head.x() + consts::BODY_SIZE / 2 - radius,
+            head.y() + consts::BODY_SIZE / 2 - radius,
+            2 * radius,
+            2 * radius,
+        );
+        canvas.ring(rect, 1, true, 0xFF00FF);
+    }
+
+    // 暂停菜单盖在最上面
+    if let Some((menu, labels)) = &frame.pause {
+        draw_pause_menu(canvas, menu, labels, board, vp, theme);
+    }
+}
+
+// 画一条蛇，电脑控制的蛇整条用自己的颜色，不用图块
+fn draw_snake(
+    canvas: &mut impl Canvas,
+    snake: &SnakeFrame,
+    tongue: f64,
+    ghost: bool,
+    theme: &Theme,
+    vp: &Viewport,
+    tiles: &HashMap<Tile, Bitmap>,
+) {
+    let positions = &snake.positions;
+    let direction = &snake.direction;
+    let in_damage = |canvas: &mut dyn Canvas, x: i32, y: i32, w: i32, h: i32| {
+        let (x, y, w, h) = vp.rect(x, y, w, h);
+        canvas.is_visible(x, y, w, h)
+    };
+
+    // 幽灵状态下身体变浅
+    let color = |rgb: u32| {
+        if ghost {
+            lighter(rgb)
+        } else {
+            rgb
+        }
+    };
+    let head_color = color(snake.color.unwrap_or(theme.head));
+
+    // 绘制蛇，根据前后两节选择图块，没有图块时绘制图形
+    // 从尾巴开始画，蛇头盖在最上面
+    for (idx, point) in positions.iter().enumerate().rev() {
+        // 和前一节的连接、蛇头的舌头会超出这一格
+        if !in_damage(
+            canvas,
+            point.x() - consts::BODY_SIZE,
+            point.y() - consts::BODY_SIZE,
+            3 * consts::BODY_SIZE,
+            3 * consts::BODY_SIZE,
+        ) {
+            continue;
+        }
+        let drawn = snake.color.is_none()
+            && tileset::segment_tile(&snake.points, idx, direction)
+                .is_some_and(|tile| draw_tile(canvas, tiles, &tile, vp, point.x(), point.y()));
+        if drawn {
+            continue;
+        }
+        if idx == 0 {
+            // 蛇头。绘制一个半圆，一个方形
+            let (mut x, mut y, mut w, mut h) =
+                (point.x(), point.y(), consts::BODY_SIZE, consts::BODY_SIZE);
+            let eye1: Point;
+            let eye2: Point;
+
+            match direction {
+                Direction::Left => {
+                    x += consts::BODY_SIZE / 2;
+                    w = consts::BODY_SIZE / 2;
+
+                    eye1 = Point::new(
+                        point.x() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
+                        point.y() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
+                    );
+                    eye2 = Point::new(
+                        point.x() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
+                        point.y() + 3 * consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
+                    )
+                }
+                Direction::Up => {
+                    y += consts::BODY_SIZE / 2;
+                    h = consts::BODY_SIZE / 2;
+
+                    eye1 = Point::new(
+                        point.x() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
+                        point.y() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
+                    );
+                    eye2 = Point::new(
+                        point.x() + 3 * consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
+                        point.y() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
+                    )
+                }
+                Direction::Right => {
+                    w = consts::BODY_SIZE / 2;
+
+                    eye1 = Point::new(
+                        point.x() + consts::BODY_SIZE * 3 / 4 - consts::EYE_SIZE / 2,
+                        point.y() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
+                    );
+                    eye2 = Point::new(
+                        point.x() + consts::BODY_SIZE * 3 / 4 - consts::EYE_SIZE / 2,
+                        point.y() + consts::BODY_SIZE * 3 / 4 - consts::EYE_SIZE / 2,
+                    );
+                }
+                Direction::Down => {
+                    h = consts::BODY_SIZE / 2;
+
+                    eye1 = Point::new(
+                        point.x() + consts::BODY_SIZE / 4 - consts::EYE_SIZE / 2,
+                        point.y() + consts::BODY_SIZE * 3 / 4 - consts::EYE_SIZE / 2,
+                    );
+                    eye2 = Point::new(
+                        point.x() + consts::BODY_SIZE * 3 / 4 - consts::EYE_SIZE / 2,
+                        point.y() + consts::BODY_SIZE * 3 / 4 - consts::EYE_SIZE / 2,
+                    );
+                }
+            }
+
+            // 前进方向
+            let (dx, dy) = match direction {
+                Direction::Left => (-1, 0),
+                Direction::Up => (0, -1),
+                Direction::Right => (1, 0),
+                Direction::Down => (0, 1),
+            };
+            let half = consts::BODY_SIZE / 2;
+            let (cx, cy) = (point.x() + half, point.y() + half);
+
+            let target = snake.target;
+            let look_max = (consts::EYE_SIZE - consts::EYE_WHITE_SIZE) / 2;
+            let eye_white = |eye: &Point| {
+                let center = (
+                    eye.x() + consts::EYE_SIZE / 2,
+                    eye.y() + consts::EYE_SIZE / 2,
+                );
+                let (ox, oy) = shape::look_offset(center, target, look_max);
+                (
+                    center.0 - consts::EYE_WHITE_SIZE / 2 + ox,
+                    center.1 - consts::EYE_WHITE_SIZE / 2 + oy,
+                )
+            };
+            let (eye1_whitex, eye1_whitey) = eye_white(&eye1);
+            let (eye2_whitex, eye2_whitey) = eye_white(&eye2);
+
+            // 吐舌头，从嘴巴伸出去，末端分叉
+            if tongue > 0.0 {
+                let len = (half as f64 * tongue).round() as i32;
+                let (mx, my) = (cx + dx * half, cy + dy * half);
+                let (tx, ty) = (mx + dx * len, my + dy * len);
+                let fork = len / 3;
+                let width = vp.len(2);
+                let tip = (vp.x(tx), vp.y(ty));
+                canvas.line((vp.x(mx), vp.y(my)), tip, width, theme.tongue);
+                for side in [-1, 1] {
+                    let end = (
+                        vp.x(tx + dx * fork + dy * side * fork),
+                        vp.y(ty + dy * fork + dx * side * fork),
+                    );
+                    canvas.line(tip, end, width, theme.tongue);
+                }
+            }
+
+            match theme.head_style {
+                // 绘制头半圆和方形部分
+                HeadStyle::Round => {
+                    canvas.circle(
+                        vp.x(point.x()),
+                        vp.y(point.y()),
+                        vp.len(consts::BODY_SIZE),
+                        head_color,
+                    );
+                    let (x, y, w, h) = vp.rect(x, y, w, h);
+                    canvas.rect(x, y, w, h, head_color);
+                }
+                HeadStyle::Square => {
+                    let (x, y, w, h) =
+                        vp.rect(point.x(), point.y(), consts::BODY_SIZE, consts::BODY_SIZE);
+                    canvas.rect(x, y, w, h, head_color);
+                }
+            }
+            // 绘制眼睛
+            let eye_size = vp.len(consts::EYE_SIZE);
+            canvas.circle(vp.x(eye1.x()), vp.y(eye1.y()), eye_size, theme.eye);
+            canvas.circle(vp.x(eye2.x()), vp.y(eye2.y()), eye_size, theme.eye);
+            // 绘制眼白
+            let eye_white_size = vp.len(consts::EYE_WHITE_SIZE);
+            canvas.circle(
+                vp.x(eye1_whitex),
+                vp.y(eye1_whitey),
+                eye_white_size,
+                theme.eye_white,
+            );
+            canvas.circle(
+                vp.x(eye2_whitex),
+                vp.y(eye2_whitey),
+                eye_white_size,
+                theme.eye_white,
+            );
+        } else {
+            // 身体：每节中心画一个圆，和前一节之间用梯形连起来
+            // 拐角处是圆的，尾巴逐渐变细
+            let len = positions.len();
+            let color = color(snake.color.unwrap_or(theme.body_color(idx - 1, len - 1)));
+            let width = |i: usize| (consts::BODY_SIZE as f64 * shape::taper(i, len)).round() as i32;
+            let center = |p: &Point| (p.x() + consts::BODY_SIZE / 2, p.y() + consts::BODY_SIZE / 2);
+            let (cx, cy) = center(point);
+            let d = vp.len(width(idx));
+            canvas.circle(vp.x(cx) - d / 2, vp.y(cy) - d / 2, d, color);
+
+            // 穿墙时两节不相邻，不连起来
+            let prev = &positions[idx - 1];
+            if (prev.x() - point.x()).abs() + (prev.y() - point.y()).abs() <= consts::BODY_SIZE {
+                let quad = shape::segment_quad((cx, cy), width(idx), center(prev), width(idx - 1));
+                let quad: Vec<(i32, i32)> = quad.iter().map(|p| (vp.x(p.0), vp.y(p.1))).collect();
+                canvas.polygon(&quad, color);
+            }
+        }
+    }
+}
+
+// 绘制暂停菜单面板，菜单布局使用逻辑坐标
+pub fn draw_pause_menu(
+    canvas: &mut impl Canvas,
+    menu: &PauseMenu,
+    labels: &[String],
+    board: &Board,
+    vp: &Viewport,
+    theme: &Theme,
+) {
+    let (w, h) = (board.width(), board.height());
+    let (px, py, pw, ph) = PauseMenu::panel_rect(w, h);
+    let (x, y, rw, rh) = vp.rect(px, py, pw, ph);
+    canvas.rect(x, y, rw, rh, theme.panel);
+
+    let title = text_style(theme, true, vp.len(24), theme.panel_text);
+    canvas.text(
+        menu.title(),
+        vp.rect(px, py, pw, ph / 5),
+        Align::Center,
+        &title,
+    );
+
+    let item = text_style(theme, false, vp.len(18), theme.panel_text);
+    for (idx, label) in labels.iter().enumerate() {
+        let (ix, iy, iw, ih) = menu.item_rect(idx, w, h);
+        // 选中项高亮
+        if idx == menu.selected() {
+            let (x, y, rw, rh) = vp.rect(ix + 10, iy + 4, iw - 20, ih - 8);
+            canvas.rect(x, y, rw, rh, theme.accent);
+        }
+        canvas.text(label, vp.rect(ix, iy, iw, ih), Align::Center, &item);
+    }
+}
+
+// 结束界面：屏幕中央的大字，下方的操作提示
+pub fn draw_end_screen(
+    canvas: &mut impl Canvas,
+    text: &str,
+    hint: &str,
+    board: &Board,
+    theme: &Theme,
+    width: i32,
+    height: i32,
+) {
+    let vp = Viewport::fit(board, width, height);
+    // 绘制背景
+    canvas.rect(0, 0, width, height, theme.panel);
+    let title = text_style(theme, true, vp.len(30), theme.panel_text);
+    canvas.text(text, (0, 0, width, height), Align::Center, &title);
+    // 操作提示
+    let style = text_style(theme, false, vp.len(16), theme.panel_text);
+    canvas.text(
+        hint,
+        (0, height * 2 / 3, width, height / 6),
+        Align::Center,
+        &style,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path, time::Duration};
+
+    use super::*;
+    use crate::{
+        food::FoodKind,
+        i18n::{self, Msg},
+        png,
+        raster::RgbaCanvas,
+        settings::Settings,
+    };
+
+    // 和 tests/golden 下的图片比较，UPDATE_GOLDEN=1 时重新生成
+    fn assert_golden(name: &str, canvas: &RgbaCanvas) {
+        let data = png::encode(
+            canvas.width() as u32,
+            canvas.height() as u32,
+            &canvas.to_rgb(),
+        );
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/golden")
+            .join(format!("{}.png", name));
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, data).unwrap();
+            return;
+        }
+        let expected = fs::read(&path).unwrap_or_else(|e| {
+            panic!(
+                "{}: {}，用 UPDATE_GOLDEN=1 cargo test 生成",
+                path.display(),
+                e
+            )
+        });
+        if expected != data {
+            let actual = std::env::temp_dir().join(format!("{}.actual.png", name));
+            fs::write(&actual, data).unwrap();
+            panic!(
+                "画面和 {} 不同，实际画面: {}",
+                path.display(),
+                actual.display()
+            );
+        }
+    }
+
+    fn scene() -> Scene {
+        Scene {
+            points: vec![
+                Point::new(120, 90),
+                Point::new(90, 90),
+                Point::new(90, 120),
+                Point::new(60, 120),
+                Point::new(30, 120),
+            ],
+            last_tail: Point::new(0, 120),
+            direction: Direction::Right,
+            foods: vec![
+                (Point::new(210, 60), FoodKind::Normal),
+                (Point::new(30, 30), FoodKind::Golden),
+            ],
+            score: 12,
+            eaten: 4,
+            elapsed: Duration::from_millis(65_100), // 舌头伸出一半
+            interval: 0.2,
+            effects: vec![
+                (EffectKind::Shield, Duration::from_millis(4200)),
+                (EffectKind::Magnet, Duration::from_millis(1500)),
+            ],
+            obstacles: vec![],
+            stage: None,
+            timer: None,
+            rivals: vec![],
+            portals: vec![],
+            walls: vec![],
+        }
+    }
+
+    fn render(frame: &Frame, board: &Board, width: i32, height: i32) -> RgbaCanvas {
+        let mut canvas = RgbaCanvas::new(width, height);
+        draw_all(
+            &mut canvas,
+            frame,
+            board,
+            &HashMap::new(),
+            &HashMap::new(),
+            width,
+            height,
+        );
+        canvas
+    }
+
+    #[test]
+    fn test_golden_playing() {
+        let board = Board::new(300, 240);
+        let frame = Frame::new(&scene(), 0.5, None, Rc::new(Theme::classic()), 20);
+        let canvas = render(&frame, &board, 300, 240 + consts::HUD_HEIGHT);
+        assert_golden("playing", &canvas);
+    }
+
+    #[test]
+    fn test_golden_paused_side_hud() {
+        // 窗口很宽时状态栏在右边
+        let board = Board::new(300, 240);
+        let menu = PauseMenu::new();
+        let labels = menu.labels(&Settings::new(vec![String::from("dark")]));
+        let frame = Frame::new(
+            &scene(),
+            1.0,
+            Some((menu, labels)),
+            Rc::new(Theme::dark()),
+            0,
+        );
+        let canvas = render(&frame, &board, 300 + consts::HUD_WIDTH, 240);
+        assert_golden("paused_side_hud", &canvas);
+    }
+
+    #[test]
+    fn test_golden_game_over() {
+        let mut canvas = RgbaCanvas::new(300, 240);
+        let theme = Theme::classic();
+        draw_end_screen(
+            &mut canvas,
+            "Game Over",
+            &i18n::trf(Msg::EndHint, &[&i18n::tr(Msg::KeySpace)]),
+            &Board::new(300, 240),
+            &theme,
+            300,
+            240,
+        );
+        assert_golden("game_over", &canvas);
+    }
+
+    #[test]
+    fn test_walls_in_background() {
+        // 关卡的墙画在背景里，不算每帧的东西；生存模式的障碍物每帧画
+        let board = Board::new(300, 240);
+        let walled = Scene {
+            walls: vec![Point::new(270, 0)],
+            obstacles: vec![Point::new(270, 30)],
+            ..scene()
+        };
+        let frame = Frame::new(&walled, 1.0, None, Rc::new(Theme::classic()), 0);
+        let count = |frame: &Frame| frame.sprites(&board, HudPlacement::Top).len();
+        let without = Frame::new(&scene(), 1.0, None, Rc::new(Theme::classic()), 0);
+        assert_eq!(count(&frame), count(&without) + 1);
+
+        let height = 240 + consts::HUD_HEIGHT;
+        let mut canvas = RgbaCanvas::new(300, height);
+        draw_background(&mut canvas, &frame, &board, &HashMap::new(), 300, height);
+        let (vp, _) = hud::fit(&board, 300, height);
+        let (x, y, _, _) = vp.rect(285, 15, 0, 0);
+        assert_eq!(canvas.pixel(x, y), Some(frame.theme.border));
+    }
+}
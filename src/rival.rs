@@ -0,0 +1,263 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{
+    consts,
+    i18n::{self, Msg},
+    snake::{Direction, Point, Snake},
+};
+
+// 电脑控制的蛇怎么选方向
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Behaviour {
+    Greedy,     // 贪吃：直奔最近的食物
+    Cautious,   // 谨慎：先保证走过去还有足够的地方，再找食物
+    Aggressive, // 拦截：抢到玩家蛇头前面，挡住去路
+}
+
+pub const BEHAVIOURS: [Behaviour; 3] = [
+    Behaviour::Greedy,
+    Behaviour::Cautious,
+    Behaviour::Aggressive,
+];
+
+// 暂停菜单里可以切换的对手组合，设置文件里可以写任意组合
+pub const RIVAL_CHOICES: [&[Behaviour]; 5] = [
+    &[],
+    &[Behaviour::Greedy],
+    &[Behaviour::Cautious],
+    &[Behaviour::Aggressive],
+    &BEHAVIOURS,
+];
+
+// 对手的颜色，按出场顺序循环使用
+const COLORS: [u32; 4] = [0xE67E22, 0x3498DB, 0xC0392B, 0x8E44AD];
+
+impl Behaviour {
+    // 设置文件里使用的名字
+    pub fn name(&self) -> &'static str {
+        match self {
+            Behaviour::Greedy => "greedy",
+            Behaviour::Cautious => "cautious",
+            Behaviour::Aggressive => "aggressive",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Behaviour> {
+        BEHAVIOURS.iter().copied().find(|b| b.name() == name)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Behaviour::Greedy => i18n::tr(Msg::RivalGreedy),
+            Behaviour::Cautious => i18n::tr(Msg::RivalCautious),
+            Behaviour::Aggressive => i18n::tr(Msg::RivalAggressive),
+        }
+    }
+}
+
+// 设置文件里的对手：逗号分隔的名字，比如 greedy, aggressive，空为没有对手
+pub fn parse_rivals(text: &str) -> Result<Vec<Behaviour>, String> {
+    text.split(',')
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            Behaviour::from_name(name).ok_or_else(|| i18n::trf(Msg::UnknownRival, &[&name]))
+        })
+        .collect()
+}
+
+pub fn rivals_text(rivals: &[Behaviour]) -> String {
+    let names: Vec<&str> = rivals.iter().map(|b| b.name()).collect();
+    names.join(", ")
+}
+
+// 暂停菜单里显示的对手
+pub fn rivals_label(rivals: &[Behaviour]) -> String {
+    if rivals.is_empty() {
+        return i18n::tr(Msg::NoRivals).to_string();
+    }
+    let labels: Vec<&str> = rivals.iter().map(|b| b.label()).collect();
+    labels.join("+")
+}
+
+// 第idx个对手的颜色
+pub fn color(idx: usize) -> u32 {
+    COLORS[idx % COLORS.len()]
+}
+
+// 电脑控制的蛇，和玩家抢食物
+pub struct Rival {
+    pub snake: Snake,
+    pub behaviour: Behaviour,
+    pub color: u32,
+    pub respawn: u32, // 撞死后还要等几格才重新出现，0 为活着
+}
+
+impl Rival {
+    pub fn new(snake: Snake, behaviour: Behaviour, color: u32) -> Rival {
+        Rival {
+            snake,
+            behaviour,
+            color,
+            respawn: 0,
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.respawn == 0
+    }
+}
+
+fn next_cell(point: &Point, direction: &Direction) -> Point {
+    let (dx, dy) = direction.offset();
+    Point::new(
+        point.x() + dx * consts::BODY_SIZE,
+        point.y() + dy * consts::BODY_SIZE,
+    )
+}
+
+// 两个格子之间横竖相加的格子数
+fn distance(a: &Point, b: &Point) -> i32 {
+    ((a.x() - b.x()).abs() + (a.y() - b.y()).abs()) / consts::BODY_SIZE
+}
+
+// 从start开始能走到的空格子数，数到limit为止
+fn space(start: &Point, limit: usize, is_free: &impl Fn(&Point) -> bool) -> usize {
+    let mut seen = HashSet::from([start.clone()]);
+    let mut queue = VecDeque::from([start.clone()]);
+    while let Some(point) = queue.pop_front() {
+        if seen.len() >= limit {
+            break;
+        }
+        for direction in [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ] {
+            let next = next_cell(&point, &direction);
+            if is_free(&next) && seen.insert(next.clone()) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen.len()
+}
+
+// 选下一格走的方向，is_free 判断格子能不能走
+// 不能往回走，一样好时不转弯，没有能走的格子时照原方向撞上去
+pub fn choose(
+    behaviour: Behaviour,
+    snake: &Snake,
+    foods: &[Point],
+    player: &Snake,
+    is_free: impl Fn(&Point) -> bool,
+) -> Direction {
+    let current = snake.get_direction().clone();
+    let moves: Vec<(Direction, Point)> = [
+        current.clone(),
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ]
+    .into_iter()
+    .filter(|d| *d != current.opposite() || snake.len() == 1)
+    .map(|d| {
+        // 经过传送门时是出口前面的格子
+        let next = snake.next_head(&d);
+        (d, next)
+    })
+    .filter(|(_, next)| is_free(next))
+    .collect();
+
+    let nearest = |p: &Point| foods.iter().map(|food| distance(p, food)).min();
+    let best = match behaviour {
+        Behaviour::Greedy => moves.iter().min_by_key(|(_, p)| nearest(p)),
+        Behaviour::Cautious => {
+            // 走过去以后至少要放得下整条蛇
+            let need = snake.len() as usize + consts::RIVAL_SPACE_MARGIN;
+            moves
+                .iter()
+                .min_by_key(|(_, p)| (space(p, need, &is_free) < need, nearest(p)))
+        }
+        Behaviour::Aggressive => {
+            // 玩家蛇头前面几格
+            let player_direction = player.get_direction();
+            let target = (0..consts::RIVAL_CUT_AHEAD)
+                .fold(player.get_occupied_points()[0].clone(), |p, _| {
+                    next_cell(&p, player_direction)
+                });
+            moves.iter().min_by_key(|(_, p)| distance(p, &target))
+        }
+    };
+    best.map_or(current, |(d, _)| d.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Board;
+
+    #[test]
+    fn test_parse_rivals() {
+        assert_eq!(
+            parse_rivals("greedy, aggressive"),
+            Ok(vec![Behaviour::Greedy, Behaviour::Aggressive])
+        );
+        assert_eq!(parse_rivals(""), Ok(vec![]));
+        assert!(parse_rivals("greedy, lazy").is_err());
+        assert_eq!(
+            parse_rivals(&rivals_text(&BEHAVIOURS)),
+            Ok(BEHAVIOURS.to_vec())
+        );
+    }
+
+    #[test]
+    fn test_choose() {
+        let board = Board::new(300, 300); // 10x10格
+                                          // 玩家在(3,9)向上
+        let player = Snake::new(90, 270, board);
+        // 蛇头在(3,3)向右
+        let mut snake = Snake::new(90, 90, board);
+        snake.reset_at(Point::new(90, 90), Some(Direction::Right));
+        let on_board = |p: &Point| board.contains(p.x(), p.y());
+
+        // 贪吃：食物在下面就转下去
+        let foods = [Point::new(90, 240)];
+        assert_eq!(
+            choose(Behaviour::Greedy, &snake, &foods, &player, on_board),
+            Direction::Down
+        );
+        // 没有食物时不转弯
+        assert_eq!(
+            choose(Behaviour::Greedy, &snake, &[], &player, on_board),
+            Direction::Right
+        );
+
+        // 谨慎：下面是死胡同，宁可绕远
+        let dead_end = |p: &Point| {
+            on_board(p) && *p != Point::new(90, 90) && !(p.y() > 90 && p.x() != 90) && p.y() < 180
+        };
+        assert_eq!(
+            choose(Behaviour::Cautious, &snake, &foods, &player, dead_end),
+            Direction::Right
+        );
+        assert_eq!(
+            choose(Behaviour::Greedy, &snake, &foods, &player, dead_end),
+            Direction::Down
+        );
+
+        // 拦截：往玩家蛇头前面去
+        assert_eq!(
+            choose(Behaviour::Aggressive, &snake, &foods, &player, on_board),
+            Direction::Down
+        );
+
+        // 无路可走时照原方向
+        assert_eq!(
+            choose(Behaviour::Greedy, &snake, &foods, &player, |_| false),
+            Direction::Right
+        );
+    }
+}
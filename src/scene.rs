@@ -0,0 +1,325 @@
+use std::{fs, path::Path, time::Duration};
+
+use crate::{
+    board::Board,
+    campaign::StageStatus,
+    difficulty::{Difficulty, SpeedCurve, SpeedSetting},
+    effect::EffectKind,
+    food::FoodKind,
+    i18n::{self, Msg},
+    mode::Timer,
+    snake::{Direction, Point},
+};
+
+// 某一时刻画面需要的游戏状态，截图、录制和录像回放都从这里绘制
+#[derive(PartialEq, Debug, Clone)]
+pub struct Scene {
+    pub points: Vec<Point>, // 蛇占的格子，第一个为蛇头
+    pub last_tail: Point,   // 上一次移动丢掉的尾节点
+    pub direction: Direction,
+    pub foods: Vec<(Point, FoodKind)>,
+    pub score: i32,
+    pub eaten: u32,
+    pub elapsed: Duration,                    // 这局开始后的时间，不含暂停
+    pub interval: f64,                        // 移动一格的时间，秒
+    pub effects: Vec<(EffectKind, Duration)>, // 生效中的道具和剩余时间
+    pub obstacles: Vec<Point>,                // 生存模式出现的障碍物
+    pub stage: Option<StageStatus>,           // 闯关模式的关卡和进度
+    pub timer: Option<Timer>,                 // 限时模式、生存模式的倒计时
+    pub rivals: Vec<RivalScene>,              // 活着的对手
+    pub portals: Vec<(Point, Point)>,         // 关卡的传送门
+    pub walls: Vec<Point>,                    // 关卡地图里的障碍物，这一关里不变
+}
+
+// 电脑控制的蛇，和玩家的蛇一样插值
+#[derive(PartialEq, Debug, Clone)]
+pub struct RivalScene {
+    pub points: Vec<Point>,
+    pub last_tail: Point,
+    pub direction: Direction,
+    pub color: u32,
+}
+
+impl RivalScene {
+    // 颜色/方向/尾节点/蛇，比如 E67E22/right/0,30/60,30;30,30
+    fn to_text(&self) -> String {
+        let points: Vec<String> = self.points.iter().map(point_text).collect();
+        format!(
+            "{:06X}/{}/{}/{}",
+            self.color,
+            self.direction.name(),
+            point_text(&self.last_tail),
+            points.join(";")
+        )
+    }
+
+    fn from_text(text: &str) -> Option<RivalScene> {
+        let fields: Vec<&str> = text.split('/').collect();
+        if fields.len() != 4 {
+            return None;
+        }
+        let points = parse_list(fields[3], parse_point)?;
+        if points.is_empty() {
+            return None;
+        }
+        Some(RivalScene {
+            points,
+            last_tail: parse_point(fields[2])?,
+            direction: Direction::from_name(fields[1])?,
+            color: u32::from_str_radix(fields[0], 16).ok()?,
+        })
+    }
+}
+
+fn point_text(p: &Point) -> String {
+    format!("{},{}", p.x(), p.y())
+}
+
+fn parse_point(text: &str) -> Option<Point> {
+    let (x, y) = text.split_once(',')?;
+    Some(Point::new(x.parse().ok()?, y.parse().ok()?))
+}
+
+// 分号分隔的列表，空字符串为空列表
+fn parse_list<T>(text: &str, parse: impl Fn(&str) -> Option<T>) -> Option<Vec<T>> {
+    text.split(';')
+        .filter(|s| !s.is_empty())
+        .map(parse)
+        .collect()
+}
+
+impl Scene {
+    // 一行一个画面，用\t分隔：蛇 尾节点 方向 食物 得分 食物数 时间(毫秒) 间隔(毫秒) 道具 障碍物 关卡 倒计时 对手 传送门 墙
+    // 蛇和食物是分号分隔的 x,y 列表，食物和道具带上种类的名字
+    // 不是闯关模式时关卡为空，没有倒计时时倒计时为空，对手之间用|分隔，一对传送门写成 x,y/x,y
+    // 没有最后六项、最后四项、最后三项、最后两项或者最后一项的旧录像也能读取，旧录像的墙都在障碍物里
+    pub fn to_line(&self) -> String {
+        let points: Vec<String> = self.points.iter().map(point_text).collect();
+        let foods: Vec<String> = self
+            .foods
+            .iter()
+            .map(|(p, kind)| format!("{},{}", point_text(p), kind.name()))
+            .collect();
+        let effects: Vec<String> = self
+            .effects
+            .iter()
+            .map(|(kind, remaining)| format!("{},{}", kind.name(), remaining.as_millis()))
+            .collect();
+        let obstacles: Vec<String> = self.obstacles.iter().map(point_text).collect();
+        let walls: Vec<String> = self.walls.iter().map(point_text).collect();
+        let rivals: Vec<String> = self.rivals.iter().map(|r| r.to_text()).collect();
+        let portals: Vec<String> = self
+            .portals
+            .iter()
+            .map(|(a, b)| format!("{}/{}", point_text(a), point_text(b)))
+            .collect();
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            points.join(";"),
+            point_text(&self.last_tail),
+            self.direction.name(),
+            foods.join(";"),
+            self.score,
+            self.eaten,
+            self.elapsed.as_millis(),
+            (self.interval * 1000.0).round() as u64,
+            effects.join(";"),
+            obstacles.join(";"),
+            self.stage.map(|s| s.to_text()).unwrap_or_default(),
+            self.timer.map(|t| t.to_text()).unwrap_or_default(),
+            rivals.join("|"),
+            portals.join(";"),
+            walls.join(";"),
+        )
+    }
+
+    pub fn from_line(line: &str) -> Option<Scene> {
+        let fields: Vec<&str> = line.trim_end_matches(['\r', '\n']).split('\t').collect();
+        if ![9, 11, 12, 13, 14, 15].contains(&fields.len()) {
+            return None;
+        }
+        let points = parse_list(fields[0], parse_point)?;
+        if points.is_empty() {
+            return None;
+        }
+        Some(Scene {
+            points,
+            last_tail: parse_point(fields[1])?,
+            direction: Direction::from_name(fields[2])?,
+            foods: parse_list(fields[3], |food| {
+                let (point, kind) = food.rsplit_once(',')?;
+                Some((parse_point(point)?, FoodKind::from_name(kind)?))
+            })?,
+            score: fields[4].parse().ok()?,
+            eaten: fields[5].parse().ok()?,
+            elapsed: Duration::from_millis(fields[6].parse().ok()?),
+            interval: fields[7].parse::<u64>().ok()? as f64 / 1000.0,
+            effects: parse_list(fields[8], |effect| {
+                let (kind, remaining) = effect.split_once(',')?;
+                Some((
+                    EffectKind::from_name(kind)?,
+                    Duration::from_millis(remaining.parse().ok()?),
+                ))
+            })?,
+            obstacles: match fields.get(9) {
+                Some(field) => parse_list(field, parse_point)?,
+                None => vec![],
+            },
+            stage: match fields.get(10) {
+                Some(field) if !field.is_empty() => Some(StageStatus::from_text(field)?),
+                _ => None,
+            },
+            timer: match fields.get(11) {
+                Some(field) if !field.is_empty() => Some(Timer::from_text(field)?),
+                _ => None,
+            },
+            rivals: match fields.get(12) {
+                Some(field) => field
+                    .split('|')
+                    .filter(|s| !s.is_empty())
+                    .map(RivalScene::from_text)
+                    .collect::<Option<_>>()?,
+                None => vec![],
+            },
+            portals: match fields.get(13) {
+                Some(field) => parse_list(field, |pair| {
+                    let (a, b) = pair.split_once('/')?;
+                    Some((parse_point(a)?, parse_point(b)?))
+                })?,
+                None => vec![],
+            },
+            walls: match fields.get(14) {
+                Some(field) => parse_list(field, parse_point)?,
+                None => vec![],
+            },
+        })
+    }
+}
+
+// 录像：每移动一步记录一个画面
+#[derive(PartialEq, Debug)]
+pub struct Replay {
+    pub speed: SpeedSetting, // 这局的难度和速度曲线
+    pub board: Board,
+    pub scenes: Vec<Scene>,
+}
+
+impl Replay {
+    pub fn new(speed: SpeedSetting, board: Board) -> Replay {
+        Replay {
+            speed,
+            board,
+            scenes: vec![],
+        }
+    }
+
+    // 第一行：难度 速度曲线 棋盘宽 棋盘高，之后每行一步
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![format!(
+            "{}\t{}\t{}\t{}",
+            self.speed.difficulty.name(),
+            self.speed.curve.name(),
+            self.board.width(),
+            self.board.height()
+        )];
+        lines.extend(self.scenes.iter().map(|scene| scene.to_line()));
+        lines.join("\n")
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, String> {
+        let mut lines = text.lines();
+        let header = lines.next().unwrap_or_default();
+        let fields: Vec<&str> = header.split('\t').collect();
+        let parse_header = || -> Option<Replay> {
+            if fields.len() != 4 {
+                return None;
+            }
+            Some(Replay::new(
+                SpeedSetting {
+                    difficulty: Difficulty::from_name(fields[0])?,
+                    curve: SpeedCurve::from_name(fields[1])?,
+                },
+                Board::new(fields[2].parse().ok()?, fields[3].parse().ok()?),
+            ))
+        };
+        let mut replay =
+            parse_header().ok_or_else(|| i18n::trf(Msg::LineFormatAt, &[&1, &header]))?;
+
+        for (idx, line) in lines.enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let scene =
+                Scene::from_line(line).ok_or_else(|| i18n::trf(Msg::LineFormat, &[&(idx + 2)]))?;
+            replay.scenes.push(scene);
+        }
+        Ok(replay)
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Replay::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, self.to_text()).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::campaign::Goal;
+
+    #[test]
+    fn test_replay() {
+        let mut replay = Replay::new(SpeedSetting::default(), Board::new(500, 500));
+        replay.scenes.push(Scene {
+            points: vec![Point::new(60, 30), Point::new(30, 30)],
+            last_tail: Point::new(0, 30),
+            direction: Direction::Right,
+            foods: vec![(Point::new(90, 90), FoodKind::Golden)],
+            score: 3,
+            eaten: 2,
+            elapsed: Duration::from_millis(1500),
+            interval: 0.25,
+            effects: vec![(EffectKind::Shield, Duration::from_millis(800))],
+            obstacles: vec![Point::new(120, 0), Point::new(120, 30)],
+            stage: Some(StageStatus {
+                stage: 1,
+                goal: Goal::Score(20),
+                progress: 3,
+            }),
+            timer: Some(Timer::TimeLeft(Duration::from_millis(42_000))),
+            rivals: vec![RivalScene {
+                points: vec![Point::new(150, 150), Point::new(150, 180)],
+                last_tail: Point::new(150, 210),
+                direction: Direction::Up,
+                color: 0xE67E22,
+            }],
+            portals: vec![(Point::new(0, 0), Point::new(240, 240))],
+            walls: vec![Point::new(0, 90)],
+        });
+        replay.scenes.push(Scene {
+            foods: vec![],
+            effects: vec![],
+            obstacles: vec![],
+            stage: None,
+            timer: None,
+            rivals: vec![],
+            portals: vec![],
+            walls: vec![],
+            ..replay.scenes[0].clone()
+        });
+        assert_eq!(Replay::parse(&replay.to_text()), Ok(replay));
+
+        // 旧录像没有障碍物、关卡、倒计时、对手、传送门和墙
+        let old = "easy\tlinear\t500\t500\n60,30\t30,30\tright\t\t0\t0\t0\t250\t";
+        assert_eq!(Replay::parse(old).unwrap().scenes[0].stage, None);
+        assert!(Replay::parse("easy\tlinear\t500\t500\nbad line").is_err());
+        assert!(Replay::parse("").is_err());
+    }
+}
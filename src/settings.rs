@@ -0,0 +1,63 @@
+use crate::{
+    difficulty::SpeedSetting,
+    i18n::{self, Locale},
+    keymap::KeyMap,
+    mode::GameMode,
+    rival::Behaviour,
+};
+
+// 玩家可以在开始界面和暂停菜单里修改的设置
+#[derive(PartialEq, Debug, Clone)]
+pub struct Settings {
+    pub speed: SpeedSetting,
+    pub theme: usize,             // 当前主题的下标
+    pub theme_names: Vec<String>, // 可选的主题，内置主题在前，用户主题在后
+    pub keys: KeyMap,
+    pub locale: Locale,         // 界面语言
+    pub mode: GameMode,         // 开始界面选的模式，闯关模式过关后换成下一关
+    pub rivals: Vec<Behaviour>, // 电脑控制的对手，下一关开始时生效
+}
+
+impl Settings {
+    pub fn new(theme_names: Vec<String>) -> Settings {
+        Settings {
+            speed: SpeedSetting::default(),
+            theme: 0,
+            theme_names,
+            keys: KeyMap::default(),
+            locale: i18n::locale(),
+            mode: GameMode::Endless,
+            rivals: vec![],
+        }
+    }
+
+    pub fn theme_name(&self) -> &str {
+        self.theme_names
+            .get(self.theme)
+            .map(|name| name.as_str())
+            .unwrap_or_default()
+    }
+
+    // 切换到前一个/后一个主题
+    pub fn cycle_theme(&mut self, step: i32) {
+        if self.theme_names.is_empty() {
+            return;
+        }
+        let len = self.theme_names.len() as i32;
+        self.theme = (self.theme as i32 + step).rem_euclid(len) as usize;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycle_theme() {
+        let mut settings = Settings::new(vec![String::from("a"), String::from("b")]);
+        settings.cycle_theme(-1);
+        assert_eq!(settings.theme_name(), "b");
+        settings.cycle_theme(1);
+        assert_eq!(settings.theme_name(), "a");
+    }
+}
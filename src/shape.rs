@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use crate::consts;
+
+// 第idx节的粗细比例，len为节数（含蛇头）
+// 尾巴最后几节逐渐变细
+pub fn taper(idx: usize, len: usize) -> f64 {
+    let from_tail = len.saturating_sub(idx + 1);
+    if from_tail >= consts::TAPER_SEGMENTS {
+        return 1.0;
+    }
+    0.4 + 0.6 * from_tail as f64 / consts::TAPER_SEGMENTS as f64
+}
+
+// 连接两节中心的四边形，a、b为中心，wa、wb为两端的粗细
+pub fn segment_quad(a: (i32, i32), wa: i32, b: (i32, i32), wb: i32) -> [(i32, i32); 4] {
+    let (dx, dy) = ((b.0 - a.0) as f64, (b.1 - a.1) as f64);
+    let len = (dx * dx + dy * dy).sqrt().max(1.0);
+    // 垂直方向的单位向量
+    let (nx, ny) = (-dy / len, dx / len);
+    let offset = |p: (i32, i32), w: i32, sign: f64| {
+        let half = w as f64 / 2.0 * sign;
+        (
+            p.0 + (nx * half).round() as i32,
+            p.1 + (ny * half).round() as i32,
+        )
+    };
+    [
+        offset(a, wa, 1.0),
+        offset(b, wb, 1.0),
+        offset(b, wb, -1.0),
+        offset(a, wa, -1.0),
+    ]
+}
+
+// 眼珠看向target，偏移不超过max
+pub fn look_offset(eye: (i32, i32), target: (i32, i32), max: i32) -> (i32, i32) {
+    let (dx, dy) = ((target.0 - eye.0) as f64, (target.1 - eye.1) as f64);
+    let len = (dx * dx + dy * dy).sqrt();
+    if len < 1.0 {
+        return (0, 0);
+    }
+    let scale = max as f64 / len;
+    ((dx * scale).round() as i32, (dy * scale).round() as i32)
+}
+
+// 舌头伸出的比例，0为收回，1为完全伸出
+// 每个周期的开头吐一次舌头
+pub fn tongue(elapsed: Duration) -> f64 {
+    let period = consts::TONGUE_PERIOD.as_secs_f64();
+    let flick = consts::TONGUE_FLICK.as_secs_f64();
+    let t = elapsed.as_secs_f64() % period;
+    if t >= flick {
+        return 0.0;
+    }
+    // 先伸出再收回
+    1.0 - (2.0 * t / flick - 1.0).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_taper() {
+        assert_eq!(taper(0, 10), 1.0);
+        assert_eq!(taper(9, 10), 0.4);
+        assert!(taper(8, 10) > taper(9, 10));
+    }
+
+    #[test]
+    fn test_segment_quad() {
+        // 水平的一段，上下展开
+        let quad = segment_quad((0, 15), 30, (30, 15), 10);
+        assert_eq!(quad, [(0, 30), (30, 20), (30, 10), (0, 0)]);
+    }
+
+    #[test]
+    fn test_look_and_tongue() {
+        assert_eq!(look_offset((0, 0), (100, 0), 3), (3, 0));
+        assert_eq!(look_offset((0, 0), (0, 0), 3), (0, 0));
+
+        let half = consts::TONGUE_FLICK / 2;
+        assert_eq!(tongue(half), 1.0);
+        assert_eq!(tongue(consts::TONGUE_FLICK), 0.0);
+        assert!((tongue(consts::TONGUE_PERIOD + half) - 1.0).abs() < 1e-6);
+    }
+}
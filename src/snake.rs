@@ -1,9 +1,12 @@
-use std::{cmp::max, collections::HashMap};
-
-use fltk::{prelude::*, *};
+use std::{
+    cmp::max,
+    collections::{HashMap, VecDeque},
+};
 
 use crate::{
+    board::Board,
     consts::{self, BODY_SIZE},
+    i18n::{self, Msg},
     utils,
 };
 
@@ -15,6 +18,47 @@ pub enum Direction {
     Right,
 }
 
+impl Direction {
+    // 配置文件和录像里使用的名字
+    pub fn name(&self) -> &'static str {
+        match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Left => "left",
+            Direction::Right => "right",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Direction> {
+        match name {
+            "up" => Some(Direction::Up),
+            "down" => Some(Direction::Down),
+            "left" => Some(Direction::Left),
+            "right" => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    // 往这个方向走一格，格子数的变化
+    pub fn offset(&self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0),
+        }
+    }
+
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
 #[derive(PartialEq, Clone, Debug, Eq, Hash)]
 pub struct Point {
     x: i32, // x方向位置
@@ -36,18 +80,24 @@ impl Point {
 // snake
 pub struct Snake {
     len: i32,
-    direction: Direction, // 移动方向
-    window: window::DoubleWindow,
-    occupied_points: Vec<Point>, // 已经占用的点
-    last_tail_point: Point,      // 上一次尾节点，可以用来新增节点
-    is_change: bool,
+    direction: Direction,         // 当前移动方向
+    turns: VecDeque<Direction>,   // 还没执行的转向，每移动一格执行一个
+    board: Board,                 // 逻辑棋盘，决定边界
+    occupied_points: Vec<Point>,  // 已经占用的点
+    last_tail_point: Point,       // 上一次尾节点，渲染时尾巴从这里收回
+    portals: Vec<(Point, Point)>, // 传送门，走进一个从另一个出来
+    growing: u32,                 // 还要变长的节数，移动时尾巴留在原地
 }
 
 impl Snake {
-    pub fn new(x: i32, y: i32, window: window::DoubleWindow) -> Snake {
+    pub fn new(x: i32, y: i32, board: Board) -> Snake {
         // 初始direction设置，哪边距离长就哪边
-        let (left, right, up, down) =
-            (x, window.w() - x - BODY_SIZE, y, window.h() - y - BODY_SIZE);
+        let (left, right, up, down) = (
+            x,
+            board.width() - x - BODY_SIZE,
+            y,
+            board.height() - y - BODY_SIZE,
+        );
         let max_ = max(max(left, right), max(up, down));
         let default_direction = match max_ {
             _ if max_ == left => Direction::Left,
@@ -59,44 +109,45 @@ impl Snake {
 
         Snake {
             len: 1,
-            window: window,
+            board: board,
             direction: default_direction,
+            turns: VecDeque::new(),
             occupied_points: vec![Point { x, y }], // 已经占用的点
             last_tail_point: Point { x: x, y: y },
-            is_change: false,
+            portals: vec![],
+            growing: 0,
         }
     }
     pub fn clear(&mut self) {
-        self.len = 1;
         // init snake/根据consts::BODY_SIZE 分为相应的份数
-        let max_x = (self.window.w() - consts::BODY_SIZE) / consts::BODY_SIZE;
-        let max_y = (self.window.h() - consts::BODY_SIZE) / consts::BODY_SIZE;
-        let rand_x: i32 = utils::rand_range(0, max_x) * consts::BODY_SIZE;
-        let rand_y = utils::rand_range(0, max_y) * consts::BODY_SIZE;
-
-        self.occupied_points = vec![Point {
-            x: rand_x,
-            y: rand_y,
-        }];
-        self.last_tail_point = Point {
-            x: rand_x,
-            y: rand_y,
-        };
+        let rand_x: i32 = utils::rand_range(0, self.board.cols()) * consts::BODY_SIZE;
+        let rand_y = utils::rand_range(0, self.board.rows()) * consts::BODY_SIZE;
+        self.reset_at(Point::new(rand_x, rand_y), None);
+    }
+
+    // 只剩蛇头，放在point重新开始
+    // 没有指定方向时，哪边距离长就往哪边
+    pub fn reset_at(&mut self, point: Point, direction: Option<Direction>) {
+        self.len = 1;
+        self.growing = 0;
+        self.occupied_points = vec![point.clone()];
+        self.last_tail_point = point.clone();
 
         let (left, right, up, down) = (
-            rand_x,
-            self.window.w() - rand_x,
-            rand_y,
-            self.window.h() - rand_y,
+            point.x,
+            self.board.width() - point.x,
+            point.y,
+            self.board.height() - point.y,
         );
         let max_ = max(max(left, right), max(up, down));
-        self.direction = match max_ {
+        self.turns.clear();
+        self.direction = direction.unwrap_or(match max_ {
             _ if max_ == left => Direction::Left,
             _ if max_ == right => Direction::Right,
             _ if max_ == up => Direction::Up,
             _ if max_ == down => Direction::Down,
             _ => Direction::Right,
-        };
+        });
     }
 
     // 获取当前🐍的长度
@@ -114,7 +165,43 @@ impl Snake {
         &self.direction
     }
 
-    // 改变移动方向
+    // 上一次移动时丢掉的尾节点
+    pub fn last_tail_point(&self) -> &Point {
+        &self.last_tail_point
+    }
+
+    // 这一关的传送门，每对两个格子
+    pub fn set_portals(&mut self, portals: Vec<(Point, Point)>) {
+        self.portals = portals;
+    }
+
+    // 走进传送门时从配对的传送门出来，方向不变，出来的格子在配对的传送门前面一格
+    fn through_portal(&self, point: Point, direction: &Direction) -> Point {
+        let exit = self.portals.iter().find_map(|(a, b)| match point {
+            _ if point == *a => Some(b),
+            _ if point == *b => Some(a),
+            _ => None,
+        });
+        match exit {
+            Some(exit) => {
+                let (dx, dy) = direction.offset();
+                Point::new(exit.x + dx * BODY_SIZE, exit.y + dy * BODY_SIZE)
+            }
+            None => point,
+        }
+    }
+
+    // 往direction走一格后蛇头的位置，经过传送门时是出口前面的格子
+    // 不判断是否出了棋盘
+    pub fn next_head(&self, direction: &Direction) -> Point {
+        let head = &self.occupied_points[0];
+        let (dx, dy) = direction.offset();
+        let point = Point::new(head.x + dx * BODY_SIZE, head.y + dy * BODY_SIZE);
+        self.through_portal(point, direction)
+    }
+
+    // 改变移动方向，在下一次移动时生效
+    // 一格之内连续按键会排队，依次执行
     pub fn set_direction(&mut self, direction: Direction) -> Result<(), String> {
         let reverse_direction: HashMap<Direction, Direction> = {
             let mut m = HashMap::new();
@@ -124,187 +211,163 @@ impl Snake {
             m.insert(Direction::Right, Direction::Left);
             m
         };
+        // 和排在最后的转向比较，没有转向时和当前方向比较
+        let last = self.turns.back().unwrap_or(&self.direction).clone();
         // 如果大于两个节点肯定不能向相反方向移动
-        if reverse_direction.get(&self.direction).unwrap().to_owned() == direction && self.len() > 1
-        {
-            return Err(String::from("不能移动相反方向"));
+        if reverse_direction.get(&last).unwrap().to_owned() == direction && self.len() > 1 {
+            return Err(i18n::tr(Msg::OppositeDirection).to_string());
         }
-        if direction != self.direction {
-            self.is_change = true;
+        if direction != last && self.turns.len() < consts::MAX_QUEUED_TURNS {
+            self.turns.push_back(direction);
         }
-        self.direction = direction;
         Ok(())
     }
 
-    // 添加body逻辑
-    // 在蛇尾添加，默认与移动方向相反
-    // 比如当前蛇头向左移动，则添加蛇尾的右边
-    // 当前蛇头向上移动，则添加到蛇尾的下边
-    // 当前也要进行边界值的判定
-    pub fn add_body(&mut self) {
-        let last_point = self.get_occupied_points().last().unwrap();
-        let mut can_move = vec![
-            (Direction::Up, 0, 0),
-            (Direction::Down, 0, 0),
-            (Direction::Left, 0, 0),
-            (Direction::Right, 0, 0),
-        ];
-
-        // 能不能在上边添加
-        if last_point.y() - consts::BODY_SIZE <= 0 {
-            can_move.retain(|x| x.0 != Direction::Up)
-        } else {
-            // 可以添加判断添加后是否与现有节点交叉
-            let new_x = last_point.x();
-            let new_y = last_point.y() - consts::BODY_SIZE;
-            let is_mix = self.is_mix_snake(&Point { x: new_x, y: new_y });
-            if is_mix {
-                can_move.retain(|x| x.0 != Direction::Up)
-            } else {
-                let idx = can_move.iter().position(|x| x.0 == Direction::Up).unwrap();
-                can_move[idx] = (Direction::Up, new_x, new_y)
-            }
-        }
-        // 能不能在下边添加
-        if last_point.y() + 2 * consts::BODY_SIZE >= self.window.h() {
-            can_move.retain(|x| x.0 != Direction::Down)
-        } else {
-            // 可以添加判断添加后是否与现有节点交叉
-            let new_x = last_point.x();
-            let new_y = last_point.y() + consts::BODY_SIZE;
-            let is_mix = self.is_mix_snake(&Point { x: new_x, y: new_y });
-            if is_mix {
-                can_move.retain(|x| x.0 != Direction::Down)
-            } else {
-                let idx = can_move
-                    .iter()
-                    .position(|x| x.0 == Direction::Down)
-                    .unwrap();
-                can_move[idx] = (Direction::Down, new_x, new_y)
-            }
-        }
-        // 能不能在左边添加
-        if last_point.x() - consts::BODY_SIZE <= 0 {
-            println!("3");
-            can_move.retain(|x| x.0 != Direction::Left)
-        } else {
-            // 可以添加判断添加后是否与现有节点交叉
-            let new_x = last_point.x() - consts::BODY_SIZE;
-            let new_y = last_point.y();
-            let is_mix = self.is_mix_snake(&Point { x: new_x, y: new_y });
-            if is_mix {
-                println!("3=");
-                can_move.retain(|x| x.0 != Direction::Left)
-            } else {
-                let idx = can_move
-                    .iter()
-                    .position(|x| x.0 == Direction::Left)
-                    .unwrap();
-                can_move[idx] = (Direction::Left, new_x, new_y)
-            }
-        }
-        // 能不能在右边添加
-        if last_point.x() + 2 * consts::BODY_SIZE >= self.window.w() {
-            can_move.retain(|x| x.0 != Direction::Right)
-        } else {
-            // 可以添加判断添加后是否与现有节点交叉
-            let new_x = last_point.x() + consts::BODY_SIZE;
-            let new_y = last_point.y();
-            let is_mix = self.is_mix_snake(&Point { x: new_x, y: new_y });
-            if is_mix {
-                can_move.retain(|x| x.0 != Direction::Right)
-            } else {
-                let idx = can_move
-                    .iter()
-                    .position(|x| x.0 == Direction::Right)
-                    .unwrap();
-                can_move[idx] = (Direction::Right, new_x, new_y)
+    // 变长size节，不马上加在尾巴后面
+    // 之后每移动一格尾巴留在原地一次，新的节点只会在蛇走过的格子上，不会出现在墙、障碍物或者别的蛇上
+    pub fn grow(&mut self, size: i32) {
+        self.growing += size.max(0) as u32;
+    }
+
+    // 从蛇尾删除size个节点，至少保留蛇头
+    // 还没长出来的节数先抵消
+    pub fn remove_body(&mut self, size: i32) {
+        let cancelled = self.growing.min(size.max(0) as u32);
+        self.growing -= cancelled;
+        for _ in cancelled as i32..size {
+            if self.occupied_points.len() <= 1 {
+                break;
             }
+            self.last_tail_point = self.occupied_points.pop().unwrap();
+            self.len -= 1;
         }
-        // 没有可以移动
-        if can_move.len() == 0 {
-            panic!("Game over")
+    }
+
+    // 从第idx节开始把后面的身体都切掉，至少保留蛇头，返回切掉的节数
+    pub fn cut_at(&mut self, idx: usize) -> i32 {
+        let idx = idx.max(1);
+        if idx >= self.occupied_points.len() {
+            return 0;
         }
+        let removed = self.occupied_points.split_off(idx).len() as i32;
+        self.len -= removed;
+        // 断掉的尾巴不需要收尾巴的动画
+        self.last_tail_point = self.occupied_points.last().unwrap().clone();
+        removed
+    }
 
-        // 根据移动方向调整优先级
-        // 把高优先级的方向优先添加
-        let priority_direction: Direction;
-        match self.direction {
-            Direction::Up => priority_direction = Direction::Down,
-            Direction::Down => priority_direction = Direction::Up,
-            Direction::Left => priority_direction = Direction::Right,
-            Direction::Right => priority_direction = Direction::Left,
+    // 移动一格，主要就是新增加一个node 当作head，新增加的head指向当前最新的head，删除tail
+    // 平滑的效果由渲染时插值完成，逻辑上每次都是整格移动
+    pub fn move_direction(&mut self) -> Result<(), String> {
+        // 执行排队的转向
+        if let Some(direction) = self.turns.pop_front() {
+            self.direction = direction;
         }
-        if let Some(idx) = can_move.iter().position(|x| x.0 == priority_direction) {
-            can_move.swap(0, idx)
+        let head = self.next_head(&self.direction);
+
+        // 超出边界
+        if !self.board.contains(head.x, head.y) {
+            return Err(String::from("Game over"));
         }
-        let move_direction = can_move.get(0).unwrap();
 
-        self.len += 1;
-        self.occupied_points
-            .push(Point::new(move_direction.1, move_direction.2))
+        self.advance(head);
+        Ok(())
     }
 
-    // 移动主要就是新增加一个node 当作head，新增加的head指向当前最新的head，删除tail
-    pub fn move_direction(&mut self, size: i32, is_direction: bool) -> Result<(), String> {
-        // 方向改变放弃这次渲染，渲染方向的改变（方向的改变已经调用过一次move_direction了）
-        if self.is_change && !is_direction {
-            self.is_change = false;
-            return Ok(());
+    // 蛇头走到head，最后一个丢掉；还要变长时尾巴留在原地
+    fn advance(&mut self, head: Point) {
+        if self.growing > 0 {
+            self.growing -= 1;
+            self.len += 1;
+            // 尾巴没动，不需要收尾巴的动画
+            self.last_tail_point = self.occupied_points.last().unwrap().clone();
+        } else {
+            self.last_tail_point = self.occupied_points.pop().unwrap();
         }
-        let first = self.occupied_points.first();
-        if let Some(head) = first {
-            let mut x = head.x;
-            let mut y = head.y;
-            let last_direction = self.direction.clone();
-            match self.direction {
-                Direction::Down => y += size * consts::BODY_SIZE,
-                Direction::Up => y -= size * consts::BODY_SIZE,
-                Direction::Right => x += size * consts::BODY_SIZE,
-                Direction::Left => x -= size * consts::BODY_SIZE,
-            }
+        self.occupied_points.insert(0, head);
+    }
 
-            // 超出边界
-            if x < 0 || x > self.window.width() - consts::BODY_SIZE {
-                return Err(String::from("Game over"));
-            }
-            if y < 0 || y > self.window.height() - consts::BODY_SIZE {
-                return Err(String::from("Game over"));
+    // 穿墙移动一格，从对面的墙出来，护盾抵挡撞墙时使用
+    // 从传送门出来就出了棋盘时也从对面出来
+    pub fn move_through_wall(&mut self) {
+        let head = self.occupied_points.first().unwrap();
+        let (cols, rows) = (self.board.cols(), self.board.rows());
+        let wrap = |point: &Point| {
+            let (col, row) = (point.x / consts::BODY_SIZE, point.y / consts::BODY_SIZE);
+            Point {
+                x: col.rem_euclid(cols) * consts::BODY_SIZE,
+                y: row.rem_euclid(rows) * consts::BODY_SIZE,
             }
+        };
+        let (dx, dy) = self.direction.offset();
+        let next = wrap(&Point::new(
+            head.x + dx * consts::BODY_SIZE,
+            head.y + dy * consts::BODY_SIZE,
+        ));
+        let next = wrap(&self.through_portal(next, &self.direction));
+        self.advance(next);
+    }
+}
 
-            // 被其他地方修改了方向，为了丝滑放弃本次渲染
-            if last_direction != self.direction && !is_direction {
-                return Ok(());
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            self.last_tail_point = self.occupied_points.pop().unwrap(); // 最后一个丢掉
-            self.occupied_points.insert(0, Point { x, y }); // 记录新的点
-            return Ok(());
+    #[test]
+    fn test_cut_at() {
+        let mut snake = Snake::new(90, 0, Board::new(300, 300));
+        snake.set_direction(Direction::Down).unwrap();
+        snake.grow(4);
+        for _ in 0..4 {
+            snake.move_direction().unwrap();
         }
-
-        Ok(())
+        assert_eq!(snake.len(), 5);
+        let points = snake.get_occupied_points().clone();
+        assert_eq!(snake.cut_at(3), 2);
+        assert_eq!(snake.len(), 3);
+        assert_eq!(snake.get_occupied_points()[..], points[..3]);
+        assert_eq!(snake.last_tail_point(), &points[2]);
+        // 蛇头不能切掉
+        assert_eq!(snake.cut_at(0), 2);
+        assert_eq!(snake.get_occupied_points()[..], points[..1]);
+        assert_eq!(snake.cut_at(5), 0);
     }
 
-    fn is_mix_point(&self, point1: &Point, point2: &Point) -> bool {
-        // 间隔小于等于2倍body就是穿过了
-        let x_space = max(
-            point1.x() + consts::BODY_SIZE - point2.x(),
-            point2.x() + consts::BODY_SIZE - point1.x(),
-        );
-        let y_space = max(
-            point1.y() + consts::BODY_SIZE - point2.y(),
-            point2.y() + consts::BODY_SIZE - point1.y(),
+    #[test]
+    fn test_grow() {
+        let mut snake = Snake::new(0, 0, Board::new(300, 300));
+        snake.reset_at(Point::new(0, 0), Some(Direction::Right));
+        // 尾巴在原地等着，新的节点就是走过的格子
+        snake.grow(3);
+        snake.move_direction().unwrap();
+        assert_eq!(snake.len(), 2);
+        assert_eq!(snake.last_tail_point(), &Point::new(0, 0));
+        // 没长出来的先抵消
+        snake.remove_body(1);
+        snake.move_direction().unwrap();
+        assert_eq!(
+            snake.get_occupied_points()[..],
+            [Point::new(60, 0), Point::new(30, 0), Point::new(0, 0)]
         );
-        (point1.x() == point2.x() || point1.y() == point2.y()/*在同一条线*/)
-            && (x_space < 2 * consts::BODY_SIZE && y_space < 2 * consts::BODY_SIZE/*有交叉*/)
+        snake.move_direction().unwrap();
+        assert_eq!(snake.len(), 3);
+        assert_eq!(snake.last_tail_point(), &Point::new(0, 0));
     }
 
-    fn is_mix_snake(&self, point_: &Point) -> bool {
-        self.get_occupied_points().iter().any(move |p| {
-            if self.is_mix_point(point_, p) {
-                return true;
-            }
-            false
-        })
+    #[test]
+    fn test_portal() {
+        let mut snake = Snake::new(30, 0, Board::new(300, 300));
+        snake.reset_at(Point::new(30, 0), Some(Direction::Down));
+        snake.set_portals(vec![(Point::new(30, 30), Point::new(270, 150))]);
+        // 走进传送门，从另一个的下面出来，方向不变
+        assert_eq!(snake.next_head(&Direction::Down), Point::new(270, 180));
+        snake.move_direction().unwrap();
+        assert_eq!(snake.get_occupied_points()[0], Point::new(270, 180));
+        assert_eq!(snake.get_direction(), &Direction::Down);
+        // 出口在棋盘边上，往右出来就撞墙，穿墙时从左边出来
+        snake.reset_at(Point::new(0, 30), Some(Direction::Right));
+        assert!(snake.move_direction().is_err());
+        snake.move_through_wall();
+        assert_eq!(snake.get_occupied_points()[0], Point::new(0, 150));
     }
 }
@@ -0,0 +1,106 @@
+// 游戏状态
+// Title -> Playing -> Paused -> GameOver/Victory -> Title
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum GameState {
+    Title,    // 开始界面
+    Playing,  // 游戏中
+    Paused,   // 暂停，显示暂停菜单
+    GameOver, // 失败
+    Victory,  // 胜利
+}
+
+// 状态转换，由按键、按钮、菜单或者游戏逻辑发起
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Transition {
+    Start,   // 开始新的一局
+    Pause,   // 暂停
+    Resume,  // 继续
+    Restart, // 放弃这局，重新开始
+    Die,     // 失败
+    Win,     // 胜利
+    ToTitle, // 返回开始界面
+    Quit,    // 退出游戏
+}
+
+impl GameState {
+    // 转换后的状态，不合法的转换返回None
+    // Quit 不改变状态，由主循环直接退出
+    pub fn next(&self, transition: Transition) -> Option<GameState> {
+        use GameState::*;
+        use Transition::*;
+        match (self, transition) {
+            (Title | GameOver | Victory, Start) => Some(Playing),
+            (Playing, Pause) => Some(Paused),
+            (Paused, Resume | Restart) => Some(Playing),
+            (Playing, Die) => Some(GameOver),
+            (Playing, Win) => Some(Victory),
+            (Paused | GameOver | Victory, ToTitle) => Some(Title),
+            (_, Quit) => Some(*self),
+            _ => None,
+        }
+    }
+}
+
+// 按键和按钮的回调里拿不到MyApp，先记录下转换，由主循环统一处理
+pub struct StateMachine {
+    state: GameState,
+    pending: Option<Transition>,
+}
+
+impl StateMachine {
+    pub fn new() -> StateMachine {
+        StateMachine {
+            state: GameState::Title,
+            pending: None,
+        }
+    }
+
+    pub fn state(&self) -> GameState {
+        self.state
+    }
+
+    // 请求一次转换，当前状态下不合法的请求直接丢弃
+    pub fn request(&mut self, transition: Transition) {
+        if self.state.next(transition).is_some() {
+            self.pending = Some(transition);
+        }
+    }
+
+    pub fn take_pending(&mut self) -> Option<Transition> {
+        self.pending.take()
+    }
+
+    // 执行转换，返回新的状态
+    pub fn apply(&mut self, transition: Transition) -> Option<GameState> {
+        let next = self.state.next(transition)?;
+        self.state = next;
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transitions() {
+        let mut machine = StateMachine::new();
+        assert_eq!(machine.apply(Transition::Pause), None);
+        assert_eq!(machine.apply(Transition::Start), Some(GameState::Playing));
+        assert_eq!(machine.apply(Transition::Pause), Some(GameState::Paused));
+        assert_eq!(machine.apply(Transition::Die), None);
+        assert_eq!(machine.apply(Transition::Resume), Some(GameState::Playing));
+        assert_eq!(machine.apply(Transition::Die), Some(GameState::GameOver));
+        assert_eq!(machine.apply(Transition::ToTitle), Some(GameState::Title));
+    }
+
+    #[test]
+    fn test_request() {
+        let mut machine = StateMachine::new();
+        machine.request(Transition::Resume);
+        assert_eq!(machine.take_pending(), None);
+        machine.request(Transition::Start);
+        assert_eq!(machine.take_pending(), Some(Transition::Start));
+        assert_eq!(machine.take_pending(), None);
+    }
+}
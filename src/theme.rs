@@ -0,0 +1,365 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{parse_color, Config},
+    food::FoodKind,
+    i18n::{self, Msg},
+};
+
+// 身体花纹
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BodyPattern {
+    Solid,    // 纯色，只用第一个颜色
+    Stripes,  // 条纹，按顺序循环使用颜色
+    Gradient, // 渐变，从第一个颜色过渡到最后一个颜色
+}
+
+impl BodyPattern {
+    pub fn from_name(name: &str) -> Option<BodyPattern> {
+        match name {
+            "solid" => Some(BodyPattern::Solid),
+            "stripes" => Some(BodyPattern::Stripes),
+            "gradient" => Some(BodyPattern::Gradient),
+            _ => None,
+        }
+    }
+}
+
+// 蛇头形状
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum HeadStyle {
+    Round,  // 半圆加半个方形，朝向前进方向
+    Square, // 方形
+}
+
+impl HeadStyle {
+    pub fn from_name(name: &str) -> Option<HeadStyle> {
+        match name {
+            "round" => Some(HeadStyle::Round),
+            "square" => Some(HeadStyle::Square),
+            _ => None,
+        }
+    }
+}
+
+// 食物的画法：字符或者图片
+#[derive(PartialEq, Debug, Clone)]
+pub enum FoodSprite {
+    Glyph(String),
+    Image(PathBuf),
+}
+
+// 主题，颜色都是 0xRRGGBB
+#[derive(PartialEq, Debug, Clone)]
+pub struct Theme {
+    pub name: String,
+    pub background: u32, // 棋盘背景
+    pub border: u32,     // 窗口比例和棋盘不一致时的边
+    pub text: u32,       // 状态栏的文字
+    pub head: u32,
+    pub body: Vec<u32>,
+    pub pattern: BodyPattern,
+    pub head_style: HeadStyle,
+    pub eye: u32,
+    pub eye_white: u32,
+    pub tongue: u32,
+    pub panel: u32,      // 暂停菜单和结束界面的背景
+    pub panel_text: u32, // 暂停菜单和结束界面的文字
+    pub accent: u32,     // 选中项、开始按钮
+    pub title_background: u32,
+    pub title_text: u32,
+    pub font: String, // 字体：helvetica/courier/times/screen
+    pub foods: HashMap<FoodKind, FoodSprite>,
+    pub tileset: Option<PathBuf>, // 图块集文件，没有时用图形绘制
+}
+
+impl Theme {
+    // 经典主题，和原来的样子一样
+    pub fn classic() -> Theme {
+        Theme {
+            name: i18n::tr(Msg::ThemeClassic).to_string(),
+            background: 0xFFFFFF,
+            border: 0x000000,
+            text: 0x000000,
+            head: 0x00A4E9,
+            body: vec![0xFF0000, 0x00A4E9],
+            pattern: BodyPattern::Stripes,
+            head_style: HeadStyle::Round,
+            eye: 0x000000,
+            eye_white: 0xFFFFFF,
+            tongue: 0xE53935,
+            panel: 0x555555,
+            panel_text: 0xFFFFFF,
+            accent: 0x4CAF50,
+            title_background: 0xECECEC,
+            title_text: 0x333333,
+            font: String::from("helvetica"),
+            foods: HashMap::new(),
+            tileset: None,
+        }
+    }
+
+    pub fn dark() -> Theme {
+        Theme {
+            name: i18n::tr(Msg::ThemeDark).to_string(),
+            background: 0x1E1E1E,
+            border: 0x000000,
+            text: 0xD4D4D4,
+            head: 0x4EC9B0,
+            body: vec![0x4EC9B0, 0x1B5E50],
+            pattern: BodyPattern::Gradient,
+            head_style: HeadStyle::Round,
+            eye: 0x000000,
+            eye_white: 0xFFFFFF,
+            tongue: 0xF44747,
+            panel: 0x333333,
+            panel_text: 0xD4D4D4,
+            accent: 0x007ACC,
+            title_background: 0x252526,
+            title_text: 0xD4D4D4,
+            font: String::from("helvetica"),
+            foods: HashMap::new(),
+            tileset: None,
+        }
+    }
+
+    // 高对比度，纯色，方头，食物用字母区分
+    pub fn high_contrast() -> Theme {
+        let glyphs = [
+            (FoodKind::Normal, "●"),
+            (FoodKind::Golden, "★"),
+            (FoodKind::Shrink, "−"),
+            (FoodKind::SpeedUp, "»"),
+            (FoodKind::SlowDown, "«"),
+            (FoodKind::Ghost, "G"),
+            (FoodKind::Magnet, "M"),
+            (FoodKind::Shield, "S"),
+            (FoodKind::Prey, "P"),
+        ];
+        Theme {
+            name: i18n::tr(Msg::ThemeHighContrast).to_string(),
+            background: 0x000000,
+            border: 0x808080,
+            text: 0xFFFFFF,
+            head: 0xFFFF00,
+            body: vec![0xFFFFFF],
+            pattern: BodyPattern::Solid,
+            head_style: HeadStyle::Square,
+            eye: 0x000000,
+            eye_white: 0x000000,
+            tongue: 0xFF0000,
+            panel: 0x000000,
+            panel_text: 0xFFFFFF,
+            accent: 0x0000FF,
+            title_background: 0x000000,
+            title_text: 0xFFFF00,
+            font: String::from("helvetica"),
+            foods: glyphs
+                .iter()
+                .map(|(kind, glyph)| (*kind, FoodSprite::Glyph(glyph.to_string())))
+                .collect(),
+            tileset: None,
+        }
+    }
+
+    pub fn forest() -> Theme {
+        Theme {
+            name: i18n::tr(Msg::ThemeForest).to_string(),
+            background: 0xDCE8C8,
+            border: 0x2E3B1F,
+            text: 0x2E3B1F,
+            head: 0x556B2F,
+            body: vec![0x556B2F, 0x8FBC8F],
+            pattern: BodyPattern::Stripes,
+            head_style: HeadStyle::Round,
+            eye: 0x000000,
+            eye_white: 0xFFFFE0,
+            tongue: 0xB22222,
+            panel: 0x2E3B1F,
+            panel_text: 0xF5F5DC,
+            accent: 0x8B4513,
+            title_background: 0xDCE8C8,
+            title_text: 0x2E3B1F,
+            font: String::from("times"),
+            foods: HashMap::new(),
+            tileset: None,
+        }
+    }
+
+    // 内置主题，第一个为默认主题
+    pub fn builtin() -> Vec<Theme> {
+        vec![
+            Theme::classic(),
+            Theme::dark(),
+            Theme::high_contrast(),
+            Theme::forest(),
+        ]
+    }
+
+    // 从主题文件读取，没有写的项使用经典主题的值
+    // 图片路径相对于主题文件所在目录
+    pub fn from_config(config: &Config, dir: &Path) -> Result<Theme, String> {
+        let mut theme = Theme::classic();
+        for key in config.keys() {
+            let value = config.get(key).unwrap_or_default();
+            match key.as_str() {
+                "name" => theme.name = value.to_string(),
+                "background" => theme.background = parse_color(value)?,
+                "border" => theme.border = parse_color(value)?,
+                "text" => theme.text = parse_color(value)?,
+                "head" => theme.head = parse_color(value)?,
+                "body" => {
+                    theme.body = value
+                        .split(',')
+                        .map(|v| parse_color(v.trim()))
+                        .collect::<Result<Vec<u32>, String>>()?;
+                    if theme.body.is_empty() {
+                        return Err(i18n::tr(Msg::BodyNeedsColor).to_string());
+                    }
+                }
+                "pattern" => {
+                    theme.pattern = BodyPattern::from_name(value)
+                        .ok_or_else(|| i18n::trf(Msg::UnknownPattern, &[&value]))?
+                }
+                "head_style" => {
+                    theme.head_style = HeadStyle::from_name(value)
+                        .ok_or_else(|| i18n::trf(Msg::UnknownHeadShape, &[&value]))?
+                }
+                "eye" => theme.eye = parse_color(value)?,
+                "eye_white" => theme.eye_white = parse_color(value)?,
+                "tongue" => theme.tongue = parse_color(value)?,
+                "panel" => theme.panel = parse_color(value)?,
+                "panel_text" => theme.panel_text = parse_color(value)?,
+                "accent" => theme.accent = parse_color(value)?,
+                "title_background" => theme.title_background = parse_color(value)?,
+                "title_text" => theme.title_text = parse_color(value)?,
+                "font" => theme.font = value.to_string(),
+                "tileset" => theme.tileset = Some(dir.join(value)),
+                _ => {
+                    // food.<种类> = 字符，或者 image:图片路径
+                    let kind = key
+                        .strip_prefix("food.")
+                        .and_then(FoodKind::from_name)
+                        .ok_or_else(|| i18n::trf(Msg::UnknownOption, &[key]))?;
+                    let sprite = match value.strip_prefix("image:") {
+                        Some(path) => FoodSprite::Image(dir.join(path.trim())),
+                        None => FoodSprite::Glyph(value.to_string()),
+                    };
+                    theme.foods.insert(kind, sprite);
+                }
+            }
+        }
+        Ok(theme)
+    }
+
+    // 读取目录下所有的 .theme 文件，读取失败的跳过
+    pub fn load_dir(dir: &Path) -> Vec<Theme> {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "theme"))
+                .collect(),
+            Err(_) => return vec![],
+        };
+        paths.sort();
+
+        paths
+            .iter()
+            .filter_map(|path| {
+                match Config::load(path).and_then(|config| Theme::from_config(&config, dir)) {
+                    Ok(theme) => Some(theme),
+                    Err(e) => {
+                        println!("{}", i18n::trf(Msg::LoadThemeFailed, &[&e]));
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    // 第idx节身体的颜色，len为身体节数（不含蛇头）
+    pub fn body_color(&self, idx: usize, len: usize) -> u32 {
+        let first = self.body[0];
+        let last = self.body[self.body.len() - 1];
+        match self.pattern {
+            BodyPattern::Solid => first,
+            BodyPattern::Stripes => self.body[idx % self.body.len()],
+            BodyPattern::Gradient => {
+                let t = if len > 1 {
+                    idx as f64 / (len - 1) as f64
+                } else {
+                    0.0
+                };
+                lerp_color(first, last, t)
+            }
+        }
+    }
+
+    // 食物的画法，主题没有指定时使用默认字符
+    pub fn food_sprite(&self, kind: FoodKind) -> FoodSprite {
+        self.foods
+            .get(&kind)
+            .cloned()
+            .unwrap_or_else(|| FoodSprite::Glyph(kind.glyph().to_string()))
+    }
+}
+
+// 两个颜色之间插值，t 在 0~1 之间
+fn lerp_color(from: u32, to: u32, t: f64) -> u32 {
+    let channel = |shift: u32| {
+        let a = ((from >> shift) & 0xFF) as f64;
+        let b = ((to >> shift) & 0xFF) as f64;
+        ((a + (b - a) * t).round() as u32) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_color() {
+        let theme = Theme::classic();
+        assert_eq!(theme.body_color(0, 4), 0xFF0000);
+        assert_eq!(theme.body_color(1, 4), 0x00A4E9);
+
+        let mut theme = Theme::dark();
+        theme.body = vec![0x000000, 0xFFFFFF];
+        assert_eq!(theme.body_color(0, 3), 0x000000);
+        assert_eq!(theme.body_color(1, 3), 0x808080);
+        assert_eq!(theme.body_color(2, 3), 0xFFFFFF);
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = Config::parse(
+            "name = 测试\nbody = #111111, #222222\npattern = gradient\nfood.normal = 🍎\nfood.golden = image:gold.png\n",
+        )
+        .unwrap();
+        let theme = Theme::from_config(&config, Path::new("/themes")).unwrap();
+        assert_eq!(theme.name, "测试");
+        assert_eq!(theme.pattern, BodyPattern::Gradient);
+        assert_eq!(theme.background, Theme::classic().background);
+        assert_eq!(theme.tileset, None);
+        assert_eq!(
+            theme.food_sprite(FoodKind::Normal),
+            FoodSprite::Glyph(String::from("🍎"))
+        );
+        assert_eq!(
+            theme.food_sprite(FoodKind::Golden),
+            FoodSprite::Image(PathBuf::from("/themes/gold.png"))
+        );
+        assert_eq!(
+            theme.food_sprite(FoodKind::Shrink),
+            FoodSprite::Glyph(String::from("🍄"))
+        );
+
+        let config = Config::parse("pattern = zigzag").unwrap();
+        assert!(Theme::from_config(&config, Path::new(".")).is_err());
+    }
+}
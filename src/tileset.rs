@@ -0,0 +1,246 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::Config,
+    consts,
+    food::FoodKind,
+    i18n::{self, Msg},
+    snake::{Direction, Point},
+};
+
+// 按上右下左的顺序，身体图块的两个方向按这个顺序排列
+const DIRECTIONS: [Direction; 4] = [
+    Direction::Up,
+    Direction::Right,
+    Direction::Down,
+    Direction::Left,
+];
+
+fn direction_order(direction: &Direction) -> usize {
+    DIRECTIONS.iter().position(|d| d == direction).unwrap_or(0)
+}
+
+// 图块种类
+#[derive(PartialEq, Debug, Clone, Hash, Eq)]
+pub enum Tile {
+    Head(Direction),            // 蛇头，方向为前进方向
+    Body(Direction, Direction), // 身体，两个方向为连接前后两节的一侧，按上右下左排序
+    Tail(Direction),            // 尾巴，方向为连接身体的一侧
+    Food(FoodKind),
+    Obstacle,
+}
+
+impl Tile {
+    fn body(a: Direction, b: Direction) -> Tile {
+        if direction_order(&a) <= direction_order(&b) {
+            Tile::Body(a, b)
+        } else {
+            Tile::Body(b, a)
+        }
+    }
+
+    // 配置文件里的名字：head.up、body.up_down、body.up_right、tail.left、food.golden、obstacle
+    pub fn from_name(name: &str) -> Option<Tile> {
+        if name == "obstacle" {
+            return Some(Tile::Obstacle);
+        }
+        let (kind, value) = name.split_once('.')?;
+        match kind {
+            "head" => Direction::from_name(value).map(Tile::Head),
+            "tail" => Direction::from_name(value).map(Tile::Tail),
+            "body" => {
+                let (a, b) = value.split_once('_')?;
+                let (a, b) = (Direction::from_name(a)?, Direction::from_name(b)?);
+                if a == b {
+                    return None;
+                }
+                Some(Tile::body(a, b))
+            }
+            "food" => FoodKind::from_name(value).map(Tile::Food),
+            _ => None,
+        }
+    }
+}
+
+// 图块集：一张png图片，等大的图块排成网格
+// 没有配置的图块使用原来的图形绘制
+#[derive(PartialEq, Debug, Clone)]
+pub struct Tileset {
+    pub image: PathBuf,
+    pub size: i32, // 图块边长，像素
+    tiles: HashMap<Tile, (i32, i32)>,
+}
+
+impl Tileset {
+    // image = 图片路径，相对于图块集文件
+    // size = 图块边长
+    // <图块名> = 列,行
+    pub fn from_config(config: &Config, dir: &Path) -> Result<Tileset, String> {
+        let image = config
+            .get("image")
+            .ok_or_else(|| i18n::trf(Msg::Missing, &[&"image"]))?;
+        let size = config
+            .get("size")
+            .ok_or_else(|| i18n::trf(Msg::Missing, &[&"size"]))?
+            .parse::<i32>()
+            .map_err(|e| format!("size: {}", e))?;
+        if size <= 0 {
+            return Err(i18n::tr(Msg::SizePositive).to_string());
+        }
+
+        let mut tiles = HashMap::new();
+        for key in config.keys() {
+            if key == "image" || key == "size" {
+                continue;
+            }
+            let tile = Tile::from_name(key).ok_or_else(|| i18n::trf(Msg::UnknownTile, &[key]))?;
+            let value = config.get(key).unwrap_or_default();
+            let position = value
+                .split_once(',')
+                .and_then(|(col, row)| Some((col.trim().parse().ok()?, row.trim().parse().ok()?)))
+                .ok_or_else(|| i18n::trf(Msg::TileFormat, &[key, &value]))?;
+            tiles.insert(tile, position);
+        }
+
+        Ok(Tileset {
+            image: dir.join(image),
+            size,
+            tiles,
+        })
+    }
+
+    pub fn load(path: &Path) -> Result<Tileset, String> {
+        let config = Config::load(path)?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+        Tileset::from_config(&config, dir)
+    }
+
+    // 已配置的图块和在图片里的像素位置
+    pub fn tiles(&self) -> impl Iterator<Item = (&Tile, (i32, i32))> {
+        self.tiles
+            .iter()
+            .map(|(tile, (col, row))| (tile, (col * self.size, row * self.size)))
+    }
+}
+
+// b在a的哪个方向，穿墙时两节不相邻，方向反过来
+fn neighbour(a: &Point, b: &Point) -> Option<Direction> {
+    let (dx, dy) = (b.x() - a.x(), b.y() - a.y());
+    let wrapped = dx.abs() > consts::BODY_SIZE || dy.abs() > consts::BODY_SIZE;
+    let direction = match (dx.signum(), dy.signum(), wrapped) {
+        (0, -1, false) | (0, 1, true) => Direction::Up,
+        (0, 1, false) | (0, -1, true) => Direction::Down,
+        (-1, 0, false) | (1, 0, true) => Direction::Left,
+        (1, 0, false) | (-1, 0, true) => Direction::Right,
+        _ => return None, // 重叠或者不在一条线上
+    };
+    Some(direction)
+}
+
+// 第idx节使用的图块，根据前后两节的位置决定
+pub fn segment_tile(points: &[Point], idx: usize, direction: &Direction) -> Option<Tile> {
+    if idx == 0 {
+        return Some(Tile::Head(direction.clone()));
+    }
+    let prev = neighbour(&points[idx], &points[idx - 1])?;
+    match points.get(idx + 1) {
+        Some(next) => {
+            let next = neighbour(&points[idx], next)?;
+            if next == prev {
+                return None;
+            }
+            Some(Tile::body(prev, next))
+        }
+        None => Some(Tile::Tail(prev)),
+    }
+}
+
+// 从 RGB(A) 图片数据里裁剪出一个图块，depth 为每个像素的字节数
+pub fn crop(data: &[u8], width: i32, depth: usize, x: i32, y: i32, size: i32) -> Vec<u8> {
+    if x < 0 || y < 0 || x + size > width {
+        return vec![];
+    }
+    let row_len = size as usize * depth;
+    let mut tile = Vec::with_capacity(row_len * size as usize);
+    for row in y..y + size {
+        let start = (row * width + x) as usize * depth;
+        match data.get(start..start + row_len) {
+            Some(pixels) => tile.extend_from_slice(pixels),
+            None => return vec![],
+        }
+    }
+    tile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_tile() {
+        // 向右走，在(30,0)处从下面拐上来
+        let points = vec![
+            Point::new(60, 0),
+            Point::new(30, 0),
+            Point::new(30, 30),
+            Point::new(30, 60),
+        ];
+        assert_eq!(
+            segment_tile(&points, 0, &Direction::Right),
+            Some(Tile::Head(Direction::Right))
+        );
+        assert_eq!(
+            segment_tile(&points, 1, &Direction::Right),
+            Tile::from_name("body.right_down")
+        );
+        assert_eq!(
+            segment_tile(&points, 2, &Direction::Right),
+            Tile::from_name("body.up_down")
+        );
+        assert_eq!(
+            segment_tile(&points, 3, &Direction::Right),
+            Some(Tile::Tail(Direction::Up))
+        );
+
+        // 穿墙后两节不相邻
+        let points = vec![Point::new(0, 0), Point::new(450, 0)];
+        assert_eq!(
+            segment_tile(&points, 1, &Direction::Right),
+            Some(Tile::Tail(Direction::Right))
+        );
+    }
+
+    #[test]
+    fn test_from_config() {
+        let config = Config::parse(
+            "image = snake.png\nsize = 16\nhead.up = 1,0\nbody.down_up = 2,3\nobstacle = 0,4\n",
+        )
+        .unwrap();
+        let tileset = Tileset::from_config(&config, Path::new("/tiles")).unwrap();
+        assert_eq!(tileset.image, PathBuf::from("/tiles/snake.png"));
+        let mut tiles: Vec<_> = tileset.tiles().collect();
+        tiles.sort_by_key(|(_, position)| *position);
+        assert_eq!(
+            tiles,
+            vec![
+                (&Tile::Obstacle, (0, 64)),
+                (&Tile::Head(Direction::Up), (16, 0)),
+                (&Tile::Body(Direction::Up, Direction::Down), (32, 48)),
+            ]
+        );
+
+        let config = Config::parse("image = snake.png\nsize = 16\nhead.north = 0,0\n").unwrap();
+        assert!(Tileset::from_config(&config, Path::new(".")).is_err());
+    }
+
+    #[test]
+    fn test_crop() {
+        // 4x2 的灰度图，裁剪右边2x2
+        let data = [0, 1, 2, 3, 4, 5, 6, 7];
+        assert_eq!(crop(&data, 4, 1, 2, 0, 2), vec![2, 3, 6, 7]);
+        assert_eq!(crop(&data, 4, 1, 3, 1, 2), Vec::<u8>::new());
+    }
+}
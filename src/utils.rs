@@ -1,3 +1,8 @@
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use rand::{distributions::uniform::SampleUniform, Rng};
 
 // rand 获取[min,max)随机数
@@ -17,6 +22,20 @@ where
     range.min().unwrap()
 }
 
+// 存档目录，$HOME/.rust_snake
+pub fn data_dir() -> PathBuf {
+    let home = std::env::var("HOME").unwrap_or_else(|_| String::from("."));
+    PathBuf::from(home).join(".rust_snake")
+}
+
+// 当前时间戳，秒
+pub fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;